@@ -0,0 +1,162 @@
+export duration, duration_days, duration_hours, duration_minutes,
+       duration_seconds, duration_millis, duration_micros, duration_nanos,
+       duration_from_parts, difftime, difftime_exact, rate, format_rate;
+
+#[doc = "
+A signed span of time in seconds and nanoseconds. Like a `timespec`,
+`nanos` is always in the range [0, 1000000000), so -1.5 seconds is
+`{secs: -2, nanos: 500000000}`.
+"]
+type duration = {secs: i64, nanos: i32};
+
+#[doc = "
+Returns the number of seconds from `b` to `a`, like the C `difftime`.
+The result is negative if `a` is before `b`.
+"]
+fn difftime(a: timespec, b: timespec) -> f64 {
+    let d = difftime_exact(a, b);
+    (d.secs as f64) + (d.nanos as f64) / 1e9
+}
+
+#[doc = "
+Returns the exact duration from `b` to `a`, see `difftime`.
+"]
+fn difftime_exact(a: timespec, b: timespec) -> duration { a.since(b) }
+
+#[doc = "
+Returns the number of operations per second when `count` of them took
+`elapsed`. Fails if `elapsed` is not positive.
+"]
+fn rate(count: u64, elapsed: duration) -> f64 {
+    (count as f64) * 1e9 / (elapsed.total_nanos() as f64)
+}
+
+#[doc = "
+Formats a rate from `rate` to three significant digits with an SI
+prefix, e.g. \"12.3k ops/s\".
+"]
+fn format_rate(ops: f64) -> str {
+    if ops < 0.0 {
+        ret "-" + format_rate(-ops);
+    }
+    if ops >= 1e14 {
+        ret u64::str((ops / 1e9) as u64) + "G ops/s";
+    }
+
+    // In thousandths of an operation, so that slow rates keep digits.
+    let n = (ops * 1000.0 + 0.5) as u64;
+    let s = if n < 1000u64 {
+        let frac = u64::str(n);
+        "0." + str::from_chars(vec::from_elem(3u - str::len(frac), '0')) +
+            frac + " "
+    } else if n < 999500u64 {
+        format_significant(n, 1000u64) + " "
+    } else if n < 999500000u64 {
+        format_significant(n, 1000000u64) + "k "
+    } else if n < 999500000000u64 {
+        format_significant(n, 1000000000u64) + "M "
+    } else {
+        format_significant(n, 1000000000000u64) + "G "
+    };
+    s + "ops/s"
+}
+
+#[doc = "Returns a duration of `n` days of 86400 seconds."]
+fn duration_days(n: i64) -> duration { duration_seconds(n * 86400_i64) }
+
+#[doc = "Returns a duration of `n` hours."]
+fn duration_hours(n: i64) -> duration { duration_seconds(n * 3600_i64) }
+
+#[doc = "Returns a duration of `n` minutes."]
+fn duration_minutes(n: i64) -> duration { duration_seconds(n * 60_i64) }
+
+#[doc = "Returns a duration of `n` seconds."]
+fn duration_seconds(n: i64) -> duration { { secs: n, nanos: 0_i32 } }
+
+#[doc = "Returns a duration of `n` milliseconds."]
+fn duration_millis(n: i64) -> duration {
+    duration_from_parts(n / 1000_i64, n % 1000_i64 * 1000000_i64)
+}
+
+#[doc = "Returns a duration of `n` microseconds."]
+fn duration_micros(n: i64) -> duration {
+    duration_from_parts(n / 1000000_i64, n % 1000000_i64 * 1000_i64)
+}
+
+#[doc = "Returns a duration of `n` nanoseconds."]
+fn duration_nanos(n: i64) -> duration { duration_from_parts(0_i64, n) }
+
+impl duration for duration {
+    #[doc = "Returns the negated duration."]
+    fn neg() -> duration {
+        duration_from_parts(-self.secs, -(self.nanos as i64))
+    }
+
+    #[doc = "Returns true if the duration is greater than zero."]
+    fn is_positive() -> bool {
+        self.secs > 0_i64 || (self.secs == 0_i64 && self.nanos > 0_i32)
+    }
+
+    #[doc = "
+    Formats the duration in whichever of nanoseconds, microseconds,
+    milliseconds or seconds suits its size, to three significant digits,
+    e.g. \"1.53ms\" or \"-250ns\". Seconds aren't carried into larger units.
+    "]
+    fn to_display() -> str {
+        if self.secs < 0_i64 {
+            ret "-" + self.neg().to_display();
+        }
+
+        if self.secs >= 100_i64 {
+            let half = if self.nanos >= 500000000_i32 { 1_i64 } else { 0_i64 };
+            ret i64::str(self.secs + half) + "s";
+        }
+        // Each unit is used up to where its value would round to 1000.
+        let n = (self.secs as u64) * 1000000000u64 + (self.nanos as u64);
+        if n < 1000u64 {
+            #fmt("%uns", n as uint)
+        } else if n < 999500u64 {
+            format_significant(n, 1000u64) + "µs"
+        } else if n < 999500000u64 {
+            format_significant(n, 1000000u64) + "ms"
+        } else {
+            format_significant(n, 1000000000u64) + "s"
+        }
+    }
+
+    // The positive duration in nanoseconds. Fails if the duration is not
+    // positive.
+    fn total_nanos() -> u64 {
+        if !self.is_positive() {
+            fail "Expected a positive duration";
+        }
+        (self.secs as u64) * 1000000000u64 + (self.nanos as u64)
+    }
+}
+
+// Formats `n / unit`, which is at least 1, rounded to three significant
+// digits, or to a whole number if it has more than three.
+fn format_significant(n: u64, unit: u64) -> str {
+    let mut digits = 2u;
+    let mut pow = 100u64;
+    loop {
+        let scaled = (n * pow + unit / 2u64) / unit;
+        if scaled < 1000u64 || digits == 0u {
+            if digits == 0u {
+                ret u64::str(scaled);
+            }
+            let frac = u64::str(scaled % pow);
+            ret u64::str(scaled / pow) + "." +
+                str::from_chars(vec::from_elem(digits - str::len(frac),
+                                               '0')) + frac;
+        }
+        digits -= 1u;
+        pow /= 10u64;
+    }
+}
+
+// Builds a duration, carrying the nanoseconds into the seconds.
+fn duration_from_parts(secs: i64, nanos: i64) -> duration {
+    let (secs, nanos) = carry_nanos(secs, nanos);
+    { secs: secs, nanos: nanos }
+}
@@ -0,0 +1,571 @@
+import libc::{c_int, c_long};
+
+export strftime, strftime_with_era, strftime_with_locale, log_formatter,
+       format_buffer, format_rfc822, format_rfc5322, format_cookie_expires,
+       format_rfc3339, year_str, rfc3339_nsec;
+
+#[doc = "
+A buffer reused between formatting calls, so hot paths such as
+`format_buffer::rfc3339` don't allocate a new string each time. Like
+every boxed value it belongs to the task that created it; keep one per
+task. Created with `format_buffer`.
+"]
+type format_buffer = @{mut buf: str};
+
+#[doc = "
+Formats timestamps for log lines, caching the part of the output that
+only changes once a second. Created with `log_formatter`.
+"]
+type log_formatter = @{
+    format: str,
+    zone: zone,
+    digits: uint,
+    mut sec: i64,
+    mut prefix: str
+};
+
+#[doc = "
+Creates a log formatter. Each timestamp is formatted in the zone with
+`format`, which should end at the seconds, followed by a period and
+`digits` digits of the fraction of the second, e.g. with \"%F %T\" and 3
+digits, \"2009-02-13 23:31:30.123\". `format` is only rendered again when
+the second changes.
+"]
+fn log_formatter(format: str, z: zone, digits: uint) -> log_formatter {
+    assert digits <= 9u;
+    @{ format: format, zone: z, digits: digits, mut sec: i64_min,
+       mut prefix: "" }
+}
+
+impl log_formatter for log_formatter {
+    #[doc = "Formats the timestamp."]
+    fn format(t: timespec) -> str {
+        if t.sec != self.sec {
+            self.prefix = at_zone(t, self.zone).strftime(self.format);
+            self.sec = t.sec;
+        }
+
+        let mut buf = self.prefix;
+        if self.digits > 0u {
+            str::reserve(buf, str::len(buf) + self.digits + 1u);
+            str::push_char(buf, '.');
+            let mut div = 100000000_i32;
+            let mut i = 0u;
+            while i < self.digits {
+                let d = t.nsec / div % 10_i32;
+                str::push_char(buf, ('0' as u8 + d as u8) as char);
+                div /= 10_i32;
+                i += 1u;
+            }
+        }
+        buf
+    }
+}
+
+#[doc = "Creates an empty format buffer."]
+fn format_buffer() -> format_buffer { @{mut buf: ""} }
+
+impl format_buffer for format_buffer {
+    #[doc = "
+    Formats the time according to the format string into the buffer, and
+    calls `f` with the result. The string is only valid during the call;
+    copy it to keep it.
+    "]
+    fn strftime<T>(tm: tm, format: str, f: fn(str) -> T) -> T {
+        self.with_buf {|buf|
+            strftime_into(buf, format, tm, era_japanese, c_locale());
+        }
+        f(self.buf)
+    }
+
+    #[doc = "Formats the time like `tm::rfc3339`, see `strftime`."]
+    fn rfc3339<T>(tm: tm, f: fn(str) -> T) -> T {
+        self.with_buf {|buf|
+            push_rfc3339_prefix(buf, tm);
+            push_rfc3339_offset(buf, tm.tm_gmtoff as i32, 'Z');
+        }
+        f(self.buf)
+    }
+
+    #[doc = "Formats the time like `tm::ctime`, see `strftime`."]
+    fn ctime<T>(tm: tm, f: fn(str) -> T) -> T {
+        self.strftime(tm, "%c", f)
+    }
+
+    // Empties the buffer, keeping its allocation, and calls `f` to refill
+    // it.
+    fn with_buf(f: fn(&str)) {
+        let mut buf = "";
+        buf <-> self.buf;
+        unsafe { str::unsafe::set_len(buf, 0u); }
+        f(buf);
+        buf <-> self.buf;
+    }
+}
+
+fn strftime(format: str, tm: tm) -> str {
+    strftime_with_era(format, tm, era_japanese)
+}
+
+fn strftime_with_era(format: str, tm: tm, sys: era_system) -> str {
+    let mut buf = "";
+    strftime_into(buf, format, tm, sys, c_locale());
+    buf
+}
+
+fn strftime_with_locale(format: str, tm: tm, loc: locale) -> str {
+    let mut buf = "";
+    strftime_into(buf, format, tm, era_japanese, loc);
+    buf
+}
+
+// Formats the time according to the format string, appending to `buf`.
+// %x and %X are written in the locale's formats, within which they fall
+// back to the C locale's.
+fn strftime_into(&buf: str, format: str, tm: tm, sys: era_system,
+                 loc: locale) {
+    fn push_type(&buf: str, ch: char, tm: tm) {
+        //FIXME: Implement missing types.
+        alt check ch {
+          'A' { push_name(buf, day_names(), tm.tm_wday as int, false); }
+          'a' { push_name(buf, day_names(), tm.tm_wday as int, true); }
+          'B' { push_name(buf, month_names(), tm.tm_mon as int, false); }
+          'b' | 'h' { push_name(buf, month_names(), tm.tm_mon as int, true); }
+          'C' {
+            let year = (tm.tm_year as int + 1900) as i64;
+            push_2d(buf, div_floor(year, 100_i64) as int, '0');
+          }
+          'c' {
+            push_type(buf, 'a', tm);
+            str::push_char(buf, ' ');
+            push_type(buf, 'b', tm);
+            str::push_char(buf, ' ');
+            push_type(buf, 'e', tm);
+            str::push_char(buf, ' ');
+            push_type(buf, 'T', tm);
+            str::push_char(buf, ' ');
+            push_type(buf, 'Y', tm);
+          }
+          'D' | 'x' {
+            push_type(buf, 'm', tm);
+            str::push_char(buf, '/');
+            push_type(buf, 'd', tm);
+            str::push_char(buf, '/');
+            push_type(buf, 'y', tm);
+          }
+          'd' { push_2d(buf, tm.tm_mday as int, '0'); }
+          'e' { push_2d(buf, tm.tm_mday as int, ' '); }
+          'F' {
+            push_type(buf, 'Y', tm);
+            str::push_char(buf, '-');
+            push_type(buf, 'm', tm);
+            str::push_char(buf, '-');
+            push_type(buf, 'd', tm);
+          }
+          //'G' {}
+          //'g' {}
+          'H' { push_2d(buf, tm.tm_hour as int, '0'); }
+          'I' {
+            let mut h = tm.tm_hour as int;
+            if h == 0 { h = 12 }
+            if h > 12 { h -= 12 }
+            push_2d(buf, h, '0');
+          }
+          'j' {
+            let d = tm.tm_yday as int + 1;
+            if d >= 0 && d <= 999 {
+                str::push_char(buf, ('0' as u8 + (d / 100) as u8) as char);
+                push_2d(buf, d % 100, '0');
+            } else {
+                str::push_str(buf, #fmt("%03d", d));
+            }
+          }
+          'k' { push_2d(buf, tm.tm_hour as int, ' '); }
+          'l' {
+            let mut h = tm.tm_hour as int;
+            if h == 0 { h = 12 }
+            if h > 12 { h -= 12 }
+            push_2d(buf, h, ' ');
+          }
+          'M' { push_2d(buf, tm.tm_min as int, '0'); }
+          'm' { push_2d(buf, tm.tm_mon as int + 1, '0'); }
+          'n' { str::push_char(buf, '\n'); }
+          'P' {
+            str::push_str(buf, if tm.tm_hour as int < 12 { "am" }
+                               else { "pm" });
+          }
+          'p' {
+            str::push_str(buf, if tm.tm_hour as int < 12 { "AM" }
+                               else { "PM" });
+          }
+          'Q' {
+            alt tm.to_timespec().to_millis() {
+              ok(ms) { str::push_str(buf, i64::str(ms)); }
+              err(e) { fail e.to_str(); }
+            }
+          }
+          'R' {
+            push_type(buf, 'H', tm);
+            str::push_char(buf, ':');
+            push_type(buf, 'M', tm);
+          }
+          'r' {
+            push_type(buf, 'I', tm);
+            str::push_char(buf, ':');
+            push_type(buf, 'M', tm);
+            str::push_char(buf, ':');
+            push_type(buf, 'S', tm);
+            str::push_char(buf, ' ');
+            push_type(buf, 'p', tm);
+          }
+          'S' { push_2d(buf, tm.tm_sec as int, '0'); }
+          's' {
+            str::push_str(buf, #fmt("%d", tm.to_timespec().sec as int));
+          }
+          'T' | 'X' {
+            push_type(buf, 'H', tm);
+            str::push_char(buf, ':');
+            push_type(buf, 'M', tm);
+            str::push_char(buf, ':');
+            push_type(buf, 'S', tm);
+          }
+          't' { str::push_char(buf, '\t'); }
+          //'U' {}
+          'u' {
+            let i = tm.tm_wday as int;
+            str::push_str(buf, int::str(if i == 0 { 7 } else { i }));
+          }
+          //'V' {}
+          'v' {
+            push_type(buf, 'e', tm);
+            str::push_char(buf, '-');
+            push_type(buf, 'b', tm);
+            str::push_char(buf, '-');
+            push_type(buf, 'Y', tm);
+          }
+          //'W' {}
+          'w' { str::push_str(buf, int::str(tm.tm_wday as int)); }
+          //'X' {}
+          //'x' {}
+          'Y' { push_year(buf, tm.tm_year as int + 1900); }
+          'y' {
+            let year = (tm.tm_year as int + 1900) as i64;
+            let y = (year - div_floor(year, 100_i64) * 100_i64) as int;
+            push_2d(buf, y, '0');
+          }
+          'Z' { push_zone_name(buf, tm); }
+          'z' { push_rfc822_offset(buf, tm.tm_gmtoff as i32); }
+          //'+' {}
+          '%' { str::push_char(buf, '%'); }
+        }
+    }
+
+    // The E modifier selects the era for %EC, %Ey and %EY, and is ignored
+    // for other conversions. In the Buddhist era, `tm` has already been
+    // shifted into the era.
+    fn push_era_type(&buf: str, ch: char, tm: tm, sys: era_system) {
+        alt sys {
+          era_buddhist {
+            alt ch {
+              'C' { str::push_str(buf, buddhist_era_name); }
+              'y' { push_type(buf, 'Y', tm); }
+              'Y' {
+                str::push_str(buf, buddhist_era_name);
+                str::push_char(buf, ' ');
+                push_type(buf, 'Y', tm);
+              }
+              _ { push_type(buf, ch, tm); }
+            }
+          }
+          era_japanese { push_japanese_era_type(buf, ch, tm); }
+        }
+    }
+
+    fn push_japanese_era_type(&buf: str, ch: char, tm: tm) {
+        alt calendar::japanese::era_of(tm.date()) {
+          some((era, year)) {
+            alt ch {
+              'C' { str::push_str(buf, era.name); }
+              'y' { str::push_str(buf, int::str(year)); }
+              'Y' {
+                str::push_str(buf,
+                              calendar::japanese::era_year_str(era, year));
+              }
+              _ { push_type(buf, ch, tm); }
+            }
+          }
+          none { push_type(buf, ch, tm); }
+        }
+    }
+
+    // An estimate of the length of the output, so the buffer only needs
+    // to be allocated once.
+    fn estimate_len(format: str) -> uint {
+        let mut n = 0u;
+        let mut i = 0u;
+        let len = str::len(format);
+        while i < len {
+            if format[i] == '%' as u8 && i + 1u < len {
+                i += 1u;
+                n += alt format[i] as char {
+                  'c' { 24u }
+                  'Q' { 13u }
+                  'v' | 'r' | 's' { 11u }
+                  'F' { 10u }
+                  'A' | 'B' { 9u }
+                  'D' | 'x' | 'T' | 'X' { 8u }
+                  'R' | 'Z' | 'z' { 5u }
+                  'Y' { 4u }
+                  'a' | 'b' | 'h' | 'j' { 3u }
+                  _ { 2u }
+                };
+            } else {
+                n += 1u;
+            }
+            i += 1u;
+        }
+        n
+    }
+
+    let tm = with_derived(tm);
+    let era_tm = alt sys {
+      era_buddhist {
+        { tm_year: tm.tm_year + buddhist_era_offset as c_int with tm }
+      }
+      era_japanese { tm }
+    };
+    str::reserve(buf, str::len(buf) + estimate_len(format));
+
+    let mut i = 0u;
+    let len = str::len(format);
+    while i < len {
+        let {ch, next} = str::char_range_at(format, i);
+        i = next;
+        if ch != '%' || i >= len {
+            str::push_char(buf, ch);
+            cont;
+        }
+
+        let {ch, next} = str::char_range_at(format, i);
+        i = next;
+        alt ch {
+          'E' if i < len {
+            let {ch, next} = str::char_range_at(format, i);
+            i = next;
+            push_era_type(buf, ch, era_tm, sys);
+          }
+          // The time since the epoch doesn't depend on the era.
+          's' | 'Q' { push_type(buf, ch, tm); }
+          'x' { strftime_into(buf, loc.date_format, tm, sys, c_locale()); }
+          'X' { strftime_into(buf, loc.time_format, tm, sys, c_locale()); }
+          ty { push_type(buf, ty, era_tm); }
+        }
+    }
+
+}
+
+// The tm with the weekday and day of the year worked out afresh if its
+// date is valid, so that %a, %A, %j, %u and %w are never stale.
+fn with_derived(tm: tm) -> tm {
+    if has_valid_date(tm) { tm.fill_derived() } else { tm }
+}
+
+// Appends a zone offset as RFC 3339 writes it, e.g. "-07:00", or `zero`
+// if the offset is zero.
+fn push_rfc3339_offset(&buf: str, gmtoff: i32, zero: char) {
+    if gmtoff == 0_i32 && zero == 'Z' {
+        str::push_char(buf, 'Z');
+        ret;
+    }
+
+    let sign = if gmtoff >= 0_i32 { '+' } else { '-' };
+    let mut m = i32::abs(gmtoff) / 60_i32;
+    let h = m / 60_i32;
+    m -= h * 60_i32;
+    str::push_str(buf, #fmt("%c%02d:%02d", sign, h as int, m as int));
+}
+
+// Pushes a number as two digits from a lookup table, padded on the
+// left with `pad` if it is below 10, like #fmt's %02d or %2d.
+fn push_2d(&buf: str, n: int, pad: char) {
+    if n < 0 || n > 99 {
+        str::push_str(buf, if pad == '0' { #fmt("%02d", n) }
+                           else { #fmt("%2d", n) });
+        ret;
+    }
+
+    let row = alt n / 25 {
+      0 { digits_00 }
+      1 { digits_25 }
+      2 { digits_50 }
+      _ { digits_75 }
+    };
+    let i = ((n % 25) as uint) * 2u;
+    str::push_char(buf, if n < 10 { pad } else { row[i] as char });
+    str::push_char(buf, row[i + 1u] as char);
+}
+
+// Pushes the `i`th name in a table, or its first three letters if
+// `abbrev` is set. An index outside the table, from a tm whose fields
+// were set by hand, pushes "?" as C's strftime does.
+fn push_name(&buf: str, table: [str], i: int, abbrev: bool) {
+    if i < 0 || i as uint >= vec::len(table) {
+        str::push_char(buf, '?');
+    } else if abbrev {
+        str::push_str(buf, str::slice(table[i], 0u, 3u));
+    } else {
+        str::push_str(buf, table[i]);
+    }
+}
+
+// Pushes a year as `%Y` formats it.
+fn push_year(&buf: str, year: int) {
+    if year >= 0 && year <= 9999 {
+        push_2d(buf, year / 100, '0');
+        push_2d(buf, year % 100, '0');
+    } else {
+        str::push_str(buf, year_str(year));
+    }
+}
+
+// Pushes the canonical zone abbreviation of a tm, if it has one.
+fn push_zone_name(&buf: str, tm: tm) {
+    str::push_str(buf, zone_abbrev(tm));
+}
+
+// Appends a zone offset as RFC 822 and `%z` write it, e.g. "-0700". A
+// zero offset is written as "-0000".
+fn push_rfc822_offset(&buf: str, gmtoff: i32) {
+    let sign = if gmtoff > 0_i32 { '+' } else { '-' };
+    let mut m = i32::abs(gmtoff) / 60_i32;
+    let h = m / 60_i32;
+    m -= h * 60_i32;
+    str::push_char(buf, sign);
+    push_2d(buf, h as int, '0');
+    push_2d(buf, m as int, '0');
+}
+
+// Pushes the date and time of an RFC 822 timestamp and the space before
+// its zone, like "%a, %d %b %Y %T ".
+fn push_rfc822_prefix(&buf: str, tm: tm) {
+    push_name(buf, day_names(), tm.tm_wday as int, true);
+    str::push_str(buf, ", ");
+    push_2d(buf, tm.tm_mday as int, '0');
+    str::push_char(buf, ' ');
+    push_name(buf, month_names(), tm.tm_mon as int, true);
+    str::push_char(buf, ' ');
+    push_year(buf, tm.tm_year as int + 1900);
+    str::push_char(buf, ' ');
+    push_2d(buf, tm.tm_hour as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_min as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_sec as int, '0');
+    str::push_char(buf, ' ');
+}
+
+// Pushes the date and time of an RFC 3339 timestamp without its offset,
+// like "%Y-%m-%dT%H:%M:%S".
+fn push_rfc3339_prefix(&buf: str, tm: tm) {
+    push_year(buf, tm.tm_year as int + 1900);
+    str::push_char(buf, '-');
+    push_2d(buf, tm.tm_mon as int + 1, '0');
+    str::push_char(buf, '-');
+    push_2d(buf, tm.tm_mday as int, '0');
+    str::push_char(buf, 'T');
+    push_2d(buf, tm.tm_hour as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_min as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_sec as int, '0');
+}
+
+// Formats a tm as RFC 822 in a single pass. `gmt` writes "GMT" for a
+// zero offset in place of the zone abbreviation, and `numeric` writes
+// the offset as digits instead.
+fn format_rfc822(tm: tm, gmt: bool, numeric: bool) -> str {
+    let mut buf = "";
+    str::reserve(buf, 32u);
+    push_rfc822_prefix(buf, with_derived(tm));
+    if numeric {
+        push_rfc822_offset(buf, tm.tm_gmtoff as i32);
+    } else if gmt && tm.tm_gmtoff == 0 as c_long {
+        str::push_str(buf, "GMT");
+    } else {
+        push_zone_name(buf, tm);
+    }
+    buf
+}
+
+// Formats a tm as an RFC 5322 date. The weekday is computed from the
+// date rather than taken from tm_wday, and the offset is always numeric,
+// with UTC written as "+0000" rather than the "-0000" of an unknown zone.
+fn format_rfc5322(tm: tm) -> str {
+    let mut buf = "";
+    str::reserve(buf, 32u);
+    push_rfc822_prefix(buf, tm.fill_derived());
+    let gmtoff = tm.tm_gmtoff as i32;
+    str::push_char(buf, if gmtoff < 0_i32 { '-' } else { '+' });
+    let m = i32::abs(gmtoff) / 60_i32;
+    push_2d(buf, (m / 60_i32) as int, '0');
+    push_2d(buf, (m % 60_i32) as int, '0');
+    buf
+}
+
+// Formats a UTC tm as a Netscape cookie Expires date, which differs from
+// RFC 822 in the hyphens between the day, month and year.
+fn format_cookie_expires(tm: tm) -> str {
+    let mut buf = "";
+    str::reserve(buf, 29u);
+    push_name(buf, day_names(), tm.tm_wday as int, true);
+    str::push_str(buf, ", ");
+    push_2d(buf, tm.tm_mday as int, '0');
+    str::push_char(buf, '-');
+    push_name(buf, month_names(), tm.tm_mon as int, true);
+    str::push_char(buf, '-');
+    push_year(buf, tm.tm_year as int + 1900);
+    str::push_char(buf, ' ');
+    push_2d(buf, tm.tm_hour as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_min as int, '0');
+    str::push_char(buf, ':');
+    push_2d(buf, tm.tm_sec as int, '0');
+    str::push_str(buf, " GMT");
+    buf
+}
+
+// Formats a tm as RFC 3339 in a single pass, with `zero` as the offset
+// for UTC as in `push_rfc3339_offset`.
+fn format_rfc3339(tm: tm, zero: char) -> str {
+    let mut buf = "";
+    str::reserve(buf, 25u);
+    push_rfc3339_prefix(buf, tm);
+    push_rfc3339_offset(buf, tm.tm_gmtoff as i32, zero);
+    buf
+}
+
+// The numbers 0 through 99 as two digits each, for fast formatting, in
+// rows of 25 to keep the lines short.
+const digits_00: str = "00010203040506070809101112131415161718192021222324";
+
+const digits_25: str = "25262728293031323334353637383940414243444546474849";
+
+const digits_50: str = "50515253545556575859606162636465666768697071727374";
+
+const digits_75: str = "75767778798081828384858687888990919293949596979899";
+
+// Formats a year with at least four digits, and a sign if it is negative.
+fn year_str(year: int) -> str {
+    if year < 0 { #fmt("-%04d", -year) } else { #fmt("%04d", year) }
+}
+
+// Formats like `tm::rfc3339`, but includes the nanoseconds when present.
+fn rfc3339_nsec(tm: tm) -> str {
+    let mut s = "";
+    push_rfc3339_prefix(s, tm);
+    if tm.tm_nsec != 0_i32 {
+        str::push_str(s, #fmt(".%09d", tm.tm_nsec as int));
+    }
+    push_rfc3339_offset(s, tm.tm_gmtoff as i32, 'Z');
+    s
+}
@@ -0,0 +1,1302 @@
+import libc::{c_int, c_long};
+import result::{result, ok, err, methods};
+
+export strptime, strptime_with_era, strptime_options, strptime_opts,
+       strptime_bytes, partial_parse, strptime_partial, verify_roundtrip,
+       parse_rfc2822, parse_cookie_expires,
+       parse_ls_date, parse_rfc3339;
+
+#[doc = "
+Options controlling how `strptime_opts` parses a time:
+
+* strict - whether the whole string must match the format. Otherwise
+  anything after the end of the format is ignored.
+* whitespace - whether whitespace in the format, `%n` and `%t` each
+  match any run of whitespace, including none, as in POSIX, rather than
+  exactly the character written. So do the spaces within `%c` and `%r`.
+  The whitespace matched includes Unicode spaces such as the no-break
+  space, which turn up in copied and localized strings.
+* case_sensitive - whether names of days, months, AM and PM and zones
+  must match in case.
+* ordinals - whether `%d` and `%e` accept the English ordinal suffix of
+  the day, as in \"February 13th\".
+* year_pivot - for `%y`, two-digit years below the pivot are in the
+  2000s and the rest in the 1900s. Without a pivot they are years 0-99.
+* default_zone - the zone of a time whose string has no `%z` or `%Z`.
+  Without one, such a time has a zero offset and no zone name.
+* default_isdst - the `tm_isdst` of a time whose zone doesn't say
+  whether it is daylight saving time: -1, unknown, unless overridden
+  with 0 or 1. A name from `%Z`, UTC and the local zone say.
+* era - the era for `%EC`, `%Ey` and `%EY`.
+* locale - the formats of `%x` and `%X`.
+"]
+type strptime_options = {
+    strict: bool,
+    whitespace: bool,
+    case_sensitive: bool,
+    ordinals: bool,
+    year_pivot: option<int>,
+    default_zone: option<zone>,
+    default_isdst: int,
+    era: era_system,
+    locale: locale
+};
+
+#[doc = "
+Returns the options `strptime` uses, which are strict about the whole
+format, spacing and case, leave two-digit years and the zone as
+written, leave daylight saving time unknown unless the zone says, and
+read `%x` and `%X` as the C locale writes them.
+"]
+fn strptime_options() -> strptime_options {
+    {
+        strict: true,
+        whitespace: false,
+        case_sensitive: true,
+        ordinals: false,
+        year_pivot: none,
+        default_zone: none,
+        default_isdst: -1,
+        era: era_japanese,
+        locale: c_locale()
+    }
+}
+
+#[doc = "
+Where and why `strptime_partial` failed: the error, the offsets into the
+string and the format at which it stopped, and a tm holding the fields
+parsed before then, with the rest zero but for `tm_isdst`, which is
+the `default_isdst` option.
+"]
+type partial_parse = {error: error, pos: uint, format_pos: uint, partial: tm};
+
+#[doc = "Parses the time from the string according to the format string."]
+fn strptime(s: str, format: str) -> result<tm, error> {
+    strptime_opts(s, format, strptime_options())
+}
+
+#[doc = "
+Parses the time from the string according to the format string, using
+the given era for %EC, %Ey and %EY.
+"]
+fn strptime_with_era(s: str, format: str, sys: era_system)
+    -> result<tm, error> {
+    strptime_opts(s, format, { era: sys with strptime_options() })
+}
+
+#[doc = "
+Parses the time from the string according to the format string, as
+controlled by the options.
+"]
+fn strptime_opts(s: str, format: str, opts: strptime_options)
+    -> result<tm, error> {
+    strptime_prefix(s, format, opts).chain { |parsed|
+        let (tm, _) = parsed;
+        ok(tm)
+    }
+}
+
+#[doc = "
+Parses a time from the start of a byte vector according to the format
+string, returning it with the number of bytes it took up. Anything after
+the time is ignored, whatever the `strict` option says.
+
+The bytes are checked to be UTF-8 only as far as the parser needs to
+read them, so a timestamp can be taken from the start of a large buffer
+without validating the rest of it.
+"]
+fn strptime_bytes(buf: [u8], format: str, opts: strptime_options)
+    -> result<(tm, uint), error> {
+    let opts = { strict: false with opts };
+    let len = vec::len(buf);
+
+    // Parse ever larger windows of the buffer until the time ends before
+    // the end of the window, and so couldn't have gone on past it.
+    let mut size = 64u;
+    loop {
+        let end = uint::min(size, len);
+        let (valid, bad) = utf8_prefix_len(buf, end);
+        let s = unsafe { str::unsafe::from_bytes(vec::slice(buf, 0u, valid)) };
+        let r = strptime_prefix(s, format, opts);
+        let complete = alt r {
+          ok((_, pos)) { pos < valid }
+          err(_) { false }
+        };
+        if complete || bad || end == len {
+            ret r;
+        }
+        size *= 2u;
+    }
+}
+
+// Returns the length of the longest prefix of the first `end` bytes of
+// a buffer which is whole UTF-8 characters, and whether it was cut short
+// by bytes which aren't UTF-8 rather than by the end.
+fn utf8_prefix_len(buf: [u8], end: uint) -> (uint, bool) {
+    let mut pos = 0u;
+    while pos < end {
+        let w = str::utf8_char_width(buf[pos]);
+        if w == 0u {
+            ret (pos, true);
+        }
+        if pos + w > end {
+            // A character straddling the end may be whole in the buffer.
+            ret (pos, pos + w > vec::len(buf));
+        }
+        let mut i = pos + 1u;
+        while i < pos + w {
+            if buf[i] & 192u8 != 128u8 {
+                ret (pos, true);
+            }
+            i += 1u;
+        }
+        pos += w;
+    }
+    (pos, false)
+}
+
+// Parses a time from the string, returning it with the position after it.
+fn strptime_prefix(s: str, format: str, opts: strptime_options)
+    -> result<(tm, uint), error> {
+    alt strptime_diag(s, format, opts) {
+      ok(parsed) { ok(parsed) }
+      err(p) { err(p.error) }
+    }
+}
+
+#[doc = "
+Parses the time from the string according to the format string, as
+`strptime_opts` does, but on failure returns what was parsed before it;
+see `partial_parse`.
+"]
+fn strptime_partial(s: str, format: str, opts: strptime_options)
+    -> result<tm, partial_parse> {
+    strptime_diag(s, format, opts).chain { |parsed|
+        let (tm, _) = parsed;
+        ok(tm)
+    }
+}
+
+#[doc = "
+Checks that a tm survives being formatted with `strftime` and parsed back
+with `strptime` in the format, so that a program can check its formats
+when it starts. Only the fields which the format writes are compared.
+Returns the parse error, or a parse error naming the fields which came
+back changed.
+"]
+fn verify_roundtrip(format: str, tm: tm) -> result<(), error> {
+    let s = strftime(format, tm);
+    strptime(s, format).chain { |parsed|
+        let mut seen = [];
+        let mut changed = [];
+        for vec::each(roundtrip_fields(format)) {|name|
+            let (was, now) = (field_str(tm, name), field_str(parsed, name));
+            if was != now && !vec::contains(seen, name) {
+                seen += [name];
+                changed += [#fmt("%s was %s but parsed as %s", name, was,
+                                 now)];
+            }
+        }
+        if changed == [] {
+            ok(())
+        } else {
+            err(parse_error(#fmt("Round trip through %? changed %s", s,
+                                 str::connect(changed, ", "))))
+        }
+    }
+}
+
+// Parses a time from the string, returning it with the position after it,
+// or the partial_parse describing where it failed.
+fn strptime_diag(s: str, format: str, opts: strptime_options)
+    -> result<(tm, uint), partial_parse> {
+    type tm_mut = {
+       mut tm_sec: c_int,
+       mut tm_min: c_int,
+       mut tm_hour: c_int,
+       mut tm_mday: c_int,
+       mut tm_mon: c_int,
+       mut tm_year: c_int,
+       mut tm_wday: c_int,
+       mut tm_yday: c_int,
+       mut tm_isdst: c_int,
+       mut tm_gmtoff: c_long,
+       mut tm_zone: str,
+       mut tm_nsec: i32,
+    };
+
+    fn match_str(s: str, pos: uint, needle: str) -> bool {
+        match_str_case(s, pos, needle, true)
+    }
+
+    // Matches `needle` at `pos`, ignoring the case of ASCII letters unless
+    // `case` is set.
+    fn match_str_case(s: str, pos: uint, needle: str, case: bool) -> bool {
+        if pos + str::len(needle) > str::len(s) {
+            ret false;
+        }
+        let mut i = pos;
+        for str::each(needle) {|ch|
+            if !byte_eq(s[i], ch, case) {
+                ret false;
+            }
+            i += 1u;
+        }
+        ret true;
+    }
+
+    fn names_eq(a: str, b: str, case: bool) -> bool {
+        str::len(a) == str::len(b) && match_str_case(a, 0u, b, case)
+    }
+
+    // The English ordinal suffix of a number, as in "1st" or "13th".
+    fn ordinal_suffix(n: int) -> str {
+        if n % 100 >= 11 && n % 100 <= 13 { ret "th"; }
+        alt n % 10 {
+          1 { "st" }
+          2 { "nd" }
+          3 { "rd" }
+          _ { "th" }
+        }
+    }
+
+    // Skips any run of whitespace at `pos`, Unicode spaces included.
+    fn skip_spaces(s: str, pos: uint) -> uint {
+        let mut pos = pos;
+        while pos < str::len(s) {
+            let {ch, next} = str::char_range_at(s, pos);
+            if !char::is_whitespace(ch) { break; }
+            pos = next;
+        }
+        pos
+    }
+
+    // Matches the space between the parts of a conversion such as %c,
+    // which may be any run of whitespace if `lenient` is set.
+    fn parse_space(s: str, pos: uint, lenient: bool)
+        -> result<uint, error> {
+        if lenient { ok(skip_spaces(s, pos)) } else { parse_char(s, pos, ' ') }
+    }
+
+    fn byte_eq(a: u8, b: u8, case: bool) -> bool {
+        if case {
+            a == b
+        } else {
+            u8_to_lower(a) == u8_to_lower(b)
+        }
+    }
+
+    fn u8_to_lower(b: u8) -> u8 {
+        if b >= 'A' as u8 && b <= 'Z' as u8 { b + 32u8 } else { b }
+    }
+
+    // Matches one of the names in a table at `pos`, returning its index
+    // and the position after it. Only the first three letters of each name
+    // are matched if `abbrev` is set.
+    fn match_names(s: str, pos: uint, table: [str], abbrev: bool,
+                   case: bool) -> option<(i32, uint)> {
+        let len = str::len(s);
+        let mut i = 0u;
+        while i < vec::len(table) {
+            let name = table[i];
+            let nlen = if abbrev { 3u } else { str::len(name) };
+            if pos + nlen <= len {
+                let mut j = 0u;
+                while j < nlen && byte_eq(s[pos + j], name[j], case) {
+                    j += 1u;
+                }
+                if j == nlen { ret some((i as i32, pos + nlen)); }
+            }
+            i += 1u;
+        }
+        none
+    }
+
+    fn match_digits(s: str, pos: uint, digits: uint, ws: bool)
+      -> option<(i32, uint)> {
+        let mut pos = pos;
+        let mut value = 0 as c_int;
+
+        let mut i = 0u;
+        while i < digits {
+            if pos >= str::len(s) { ret none; }
+            let {ch, next} = str::char_range_at(s, pos);
+            pos = next;
+
+            alt ch {
+              '0' to '9' {
+                value = value * 10 as c_int + (ch as i32 - '0' as i32);
+              }
+              ' ' if ws { }
+              _ { ret none; }
+            }
+            i += 1u;
+        }
+
+        some((value, pos))
+    }
+
+    fn parse_char(s: str, pos: uint, c: char) -> result<uint, error> {
+        if pos >= str::len(s) { ret err(parse_error(input_ended)); }
+        let {ch, next} = str::char_range_at(s, pos);
+
+        if c == ch {
+            ok(next)
+        } else {
+            err(parse_error(#fmt("Expected %?, found %?",
+                                 str::from_char(c),
+                                 str::from_char(ch))))
+        }
+    }
+
+    type era_mut = {
+        mut era: option<calendar::japanese::era>,
+        mut year: option<int>
+    };
+
+    // Parses the Japanese era conversions %EC, %Ey and %EY. The E
+    // modifier is ignored for other conversions.
+    fn parse_era_type(s: str, pos: uint, ch: char, tm: tm_mut, era: era_mut,
+                      opts: strptime_options) -> result<uint, error> {
+        alt (opts.era, ch) {
+          (era_buddhist, 'C') {
+            if match_str(s, pos, buddhist_era_name) {
+                ok(pos + str::len(buddhist_era_name))
+            } else {
+                err(parse_error("Invalid era"))
+            }
+          }
+          (era_buddhist, 'y') { parse_type(s, pos, 'Y', tm, opts) }
+          (era_buddhist, 'Y') {
+            parse_era_type(s, pos, 'C', tm, era, opts)
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'Y', tm, opts) }
+          }
+          (era_buddhist, _) { parse_type(s, pos, ch, tm, opts) }
+          (era_japanese, _) { parse_japanese_type(s, pos, ch, tm, era, opts) }
+        }
+    }
+
+    fn parse_japanese_type(s: str, pos: uint, ch: char, tm: tm_mut,
+                           era: era_mut, opts: strptime_options)
+      -> result<uint, error> {
+        alt ch {
+          'C' {
+            alt calendar::japanese::match_era(s, pos) {
+              some((e, pos)) { era.era = some(e); ok(pos) }
+              none { err(parse_error("Invalid era")) }
+            }
+          }
+          'y' {
+            alt calendar::japanese::match_era_year(s, pos) {
+              some((y, pos)) { era.year = some(y); ok(pos) }
+              none { err(parse_error("Invalid year")) }
+            }
+          }
+          'Y' {
+            alt calendar::japanese::match_era(s, pos) {
+              some((e, pos)) {
+                alt calendar::japanese::match_era_year(s, pos) {
+                  some((y, pos)) if match_str(s, pos, "年") {
+                    tm.tm_year = (e.start.year + y - 1 - 1900) as c_int;
+                    ok(pos + str::len("年"))
+                  }
+                  _ { err(parse_error("Invalid year")) }
+                }
+              }
+              none { err(parse_error("Invalid era")) }
+            }
+          }
+          _ { parse_type(s, pos, ch, tm, opts) }
+        }
+    }
+
+    fn parse_type(s: str, pos: uint, ch: char, tm: tm_mut,
+                  opts: strptime_options) -> result<uint, error> {
+        // Composite conversions like %T can run out of input part way.
+        let lenient_space = opts.whitespace && (ch == 'n' || ch == 't');
+        if pos >= str::len(s) && !lenient_space {
+            ret err(parse_error(input_ended));
+        }
+        let case = opts.case_sensitive;
+        alt ch {
+          'A' {
+            alt match_names(s, pos, day_names(), false, case) {
+              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
+              none { err(parse_error("Invalid day")) }
+            }
+          }
+          'a' {
+            alt match_names(s, pos, day_names(), true, case) {
+              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
+              none { err(parse_error("Invalid day")) }
+            }
+          }
+          'B' {
+            alt match_names(s, pos, month_names(), false, case) {
+              some(item) { let (v, pos) = item; tm.tm_mon = v; ok(pos) }
+              none { err(parse_error("Invalid month")) }
+            }
+          }
+          'b' | 'h' {
+            alt match_names(s, pos, month_names(), true, case) {
+              some(item) { let (v, pos) = item; tm.tm_mon = v; ok(pos) }
+              none { err(parse_error("Invalid month")) }
+            }
+          }
+          'C' {
+            alt match_digits(s, pos, 2u, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_year += (v * 100 as c_int) - 1900 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid year")) }
+            }
+          }
+          'c' {
+            parse_type(s, pos, 'a', tm, opts)
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'b', tm, opts) }
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'e', tm, opts) }
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'T', tm, opts) }
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'Y', tm, opts) }
+          }
+          'x' | 'X' {
+            // Parsed in the locale's format, within which %x and %X are
+            // the C locale's, keeping only the fields of the date or the
+            // time.
+            let format = if ch == 'x' { opts.locale.date_format }
+                         else { opts.locale.time_format };
+            let sub_opts = { strict: false, default_zone: none,
+                             era: era_japanese, locale: c_locale()
+                             with opts };
+            alt strptime_diag(str::slice(s, pos, str::len(s)), format,
+                              sub_opts) {
+              ok(parsed) {
+                let (sub, n) = parsed;
+                if ch == 'x' {
+                    tm.tm_year = sub.tm_year;
+                    tm.tm_mon = sub.tm_mon;
+                    tm.tm_mday = sub.tm_mday;
+                } else {
+                    tm.tm_hour = sub.tm_hour;
+                    tm.tm_min = sub.tm_min;
+                    tm.tm_sec = sub.tm_sec;
+                }
+                ok(pos + n)
+              }
+              err(p) {
+                alt p.error {
+                  parse_error(m) if str::starts_with(m, input_ended) {
+                    err(parse_error(input_ended))
+                  }
+                  e { err(e) }
+                }
+              }
+            }
+          }
+          'D' {
+            parse_type(s, pos, 'm', tm, opts)
+                .chain { |pos| parse_char(s, pos, '/') }
+                .chain { |pos| parse_type(s, pos, 'd', tm, opts) }
+                .chain { |pos| parse_char(s, pos, '/') }
+                .chain { |pos| parse_type(s, pos, 'y', tm, opts) }
+          }
+          'd' | 'e' {
+            alt match_digits(s, pos, 2u, ch == 'e') {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_mday = v;
+                let suffix = ordinal_suffix(v as int);
+                if opts.ordinals && match_str_case(s, pos, suffix, case) {
+                    ok(pos + 2u)
+                } else {
+                    ok(pos)
+                }
+              }
+              none { err(parse_error("Invalid day of the month")) }
+            }
+          }
+          'F' {
+            parse_type(s, pos, 'Y', tm, opts)
+                .chain { |pos| parse_char(s, pos, '-') }
+                .chain { |pos| parse_type(s, pos, 'm', tm, opts) }
+                .chain { |pos| parse_char(s, pos, '-') }
+                .chain { |pos| parse_type(s, pos, 'd', tm, opts) }
+          }
+          'H' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) { let (v, pos) = item; tm.tm_hour = v; ok(pos) }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'I' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) {
+                  let (v, pos) = item;
+                  tm.tm_hour = if v == 12 as c_int { 0 as c_int } else { v };
+                  ok(pos)
+              }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'j' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 3u, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_yday = v - 1 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid year")) }
+            }
+          }
+          'k' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, true) {
+              some(item) { let (v, pos) = item; tm.tm_hour = v; ok(pos) }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'l' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, true) {
+              some(item) {
+                  let (v, pos) = item;
+                  tm.tm_hour = if v == 12 as c_int { 0 as c_int } else { v };
+                  ok(pos)
+              }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'M' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) { let (v, pos) = item; tm.tm_min = v; ok(pos) }
+              none { err(parse_error("Invalid minute")) }
+            }
+          }
+          'm' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_mon = v - 1 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid month")) }
+            }
+          }
+          'n' if opts.whitespace { ok(skip_spaces(s, pos)) }
+          'n' { parse_char(s, pos, '\n') }
+          'P' {
+            alt match_names(s, pos, meridiems_lower(), false, case) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_hour += v * 12 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'p' {
+            alt match_names(s, pos, meridiems(), false, case) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_hour += v * 12 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid hour")) }
+            }
+          }
+          'Q' {
+            // The milliseconds since the epoch, which set every field.
+            let {ch, next} = str::char_range_at(s, pos);
+            let (sign, start) = alt ch {
+              '-' { (-1_i64, next) }
+              _ { (1_i64, pos) }
+            };
+            let mut end = start;
+            while end < str::len(s) && end - start < 18u &&
+                  char::is_digit(s[end] as char) {
+                end += 1u;
+            }
+            alt i64::from_str(str::slice(s, start, end)) {
+              some(ms) if end > start {
+                let t = at_utc(timespec_from_millis(sign * ms));
+                tm.tm_sec = t.tm_sec;
+                tm.tm_min = t.tm_min;
+                tm.tm_hour = t.tm_hour;
+                tm.tm_mday = t.tm_mday;
+                tm.tm_mon = t.tm_mon;
+                tm.tm_year = t.tm_year;
+                tm.tm_wday = t.tm_wday;
+                tm.tm_yday = t.tm_yday;
+                tm.tm_isdst = t.tm_isdst;
+                tm.tm_gmtoff = t.tm_gmtoff;
+                tm.tm_zone = t.tm_zone;
+                tm.tm_nsec = t.tm_nsec;
+                ok(end)
+              }
+              _ { err(parse_error("Invalid milliseconds since the epoch")) }
+            }
+          }
+          'R' {
+            parse_type(s, pos, 'H', tm, opts)
+                .chain { |pos| parse_char(s, pos, ':') }
+                .chain { |pos| parse_type(s, pos, 'M', tm, opts) }
+          }
+          'r' {
+            parse_type(s, pos, 'I', tm, opts)
+                .chain { |pos| parse_char(s, pos, ':') }
+                .chain { |pos| parse_type(s, pos, 'M', tm, opts) }
+                .chain { |pos| parse_char(s, pos, ':') }
+                .chain { |pos| parse_type(s, pos, 'S', tm, opts) }
+                .chain { |pos| parse_space(s, pos, opts.whitespace) }
+                .chain { |pos| parse_type(s, pos, 'p', tm, opts) }
+          }
+          'S' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_sec = v;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid second")) }
+            }
+          }
+          //'s' {}
+          'T' {
+            parse_type(s, pos, 'H', tm, opts)
+                .chain { |pos| parse_char(s, pos, ':') }
+                .chain { |pos| parse_type(s, pos, 'M', tm, opts) }
+                .chain { |pos| parse_char(s, pos, ':') }
+                .chain { |pos| parse_type(s, pos, 'S', tm, opts) }
+          }
+          't' if opts.whitespace { ok(skip_spaces(s, pos)) }
+          't' { parse_char(s, pos, '\t') }
+          'u' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 1u, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_wday = v;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid weekday")) }
+            }
+          }
+          'v' {
+            parse_type(s, pos, 'e', tm, opts)
+                .chain { |pos| parse_char(s, pos, '-') }
+                .chain { |pos| parse_type(s, pos, 'b', tm, opts) }
+                .chain { |pos| parse_char(s, pos, '-') }
+                .chain { |pos| parse_type(s, pos, 'Y', tm, opts) }
+          }
+          //'W' {}
+          'w' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 1u, false) {
+              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
+              none { err(parse_error("Invalid weekday")) }
+            }
+          }
+          //'X' {}
+          //'x' {}
+          'Y' {
+            // The year may be signed, for years before 1 AD.
+            let {ch, next} = str::char_range_at(s, pos);
+            let (sign, pos) = alt ch {
+              '-' { (-1 as c_int, next) }
+              '+' { (1 as c_int, next) }
+              _ { (1 as c_int, pos) }
+            };
+            // A signed year may have more than four digits, as in ISO
+            // 8601's expanded representation.
+            let mut digits = 4u;
+            if pos != next {
+                while digits < 9u && pos + digits < str::len(s) &&
+                      char::is_digit(s[pos + digits] as char) {
+                    digits += 1u;
+                }
+            }
+            alt match_digits(s, pos, digits, false) {
+              some(item) {
+                let (v, pos) = item;
+                tm.tm_year = sign * v - 1900 as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid year")) }
+            }
+          }
+          'y' {
+            // FIXME: range check.
+            alt match_digits(s, pos, 2u, false) {
+              some(item) {
+                let (v, pos) = item;
+                let year = alt opts.year_pivot {
+                  some(pivot) if (v as int) < pivot { 2000 + v as int }
+                  some(_) { 1900 + v as int }
+                  none { v as int }
+                };
+                tm.tm_year = (year - 1900) as c_int;
+                ok(pos)
+              }
+              none { err(parse_error("Invalid weekday")) }
+            }
+          }
+          'Z' {
+            if match_str_case(s, pos, "UTC", case) ||
+               match_str_case(s, pos, "GMT", case) {
+                tm.tm_isdst = 0 as c_int;
+                tm.tm_gmtoff = 0 as c_long;
+                tm.tm_zone = str::to_upper(str::slice(s, pos, pos + 3u));
+                ok(pos + 3u)
+            } else {
+                let start = pos;
+                let mut end = pos;
+                let mut pos = pos;
+                let len = str::len(s);
+                while pos < len {
+                    let {ch, next} = str::char_range_at(s, pos);
+                    pos = next;
+                    if ch == ' ' { break; }
+                    end = next;
+                }
+
+                // The local zone's abbreviations are recorded along with
+                // their offsets, as are the other common abbreviations
+                // which aren't ambiguous. Any other name is ignored, as
+                // c's strptime ignores every other timezone.
+                let name = str::slice(s, start, end);
+                let info = local_zone_info();
+                let dst_name = option::get_default(info.dst_name, "");
+                if name != "" && names_eq(name, info.std_name, case) {
+                    tm.tm_isdst = 0 as c_int;
+                    tm.tm_gmtoff = info.std_offset as c_long;
+                    tm.tm_zone = info.std_name;
+                } else if name != "" && names_eq(name, dst_name, case) {
+                    tm.tm_isdst = 1 as c_int;
+                    tm.tm_gmtoff = option::get(info.dst_offset) as c_long;
+                    tm.tm_zone = dst_name;
+                } else if !case || name == str::to_upper(name) {
+                    alt lookup_abbrev(name) {
+                      some((offsets, dst)) if vec::len(offsets) == 1u {
+                        tm.tm_isdst = if dst { 1 as c_int }
+                                      else { 0 as c_int };
+                        tm.tm_gmtoff = offsets[0] as c_long;
+                        tm.tm_zone = str::to_upper(name);
+                      }
+                      _ { }
+                    }
+                }
+
+                ok(pos)
+            }
+          }
+          'z' {
+            let {ch, next} = str::char_range_at(s, pos);
+
+            if ch == '+' || ch == '-' {
+                alt match_digits(s, next, 4u, false) {
+                  some(item) {
+                    let (v, pos) = item;
+                    let (h, m) = (v as int / 100, v as int % 100);
+                    if h >= 24 || m >= 60 {
+                        ret err(parse_error("Invalid zone offset"));
+                    }
+
+                    // The offset is recorded, and any name %Z gave the
+                    // zone is forgotten, since it may not agree; "-0000"
+                    // says nothing about the zone at all.
+                    let sign = if ch == '-' { -1 } else { 1 };
+                    tm.tm_gmtoff = (sign * (h * 3600 + m * 60)) as c_long;
+                    tm.tm_zone = "";
+                    ok(pos)
+                  }
+                  none { err(parse_error("Invalid zone offset")) }
+                }
+            } else {
+                err(parse_error("Invalid zone offset"))
+            }
+          }
+          '%' { parse_char(s, pos, '%') }
+          ch {
+            err(parse_error(#fmt("unknown formatting type: %?",
+                                 str::from_char(ch))))
+          }
+        }
+    }
+
+    fn freeze(tm: tm_mut) -> tm {
+        {
+            tm_sec: tm.tm_sec,
+            tm_min: tm.tm_min,
+            tm_hour: tm.tm_hour,
+            tm_mday: tm.tm_mday,
+            tm_mon: tm.tm_mon,
+            tm_year: tm.tm_year,
+            tm_wday: tm.tm_wday,
+            tm_yday: tm.tm_yday,
+            tm_isdst: tm.tm_isdst,
+            tm_gmtoff: tm.tm_gmtoff,
+            tm_zone: tm.tm_zone,
+            tm_nsec: tm.tm_nsec,
+        }
+    }
+
+    // Returns the character of the format at `fpos` and moves past it, or
+    // NUL at the end of the format.
+    fn next_format_char(format: str, &fpos: uint) -> char {
+        if fpos >= str::len(format) { ret '\x00'; }
+        let {ch, next} = str::char_range_at(format, fpos);
+        fpos = next;
+        ch
+    }
+
+    let tm = {
+        mut tm_sec: 0 as c_int,
+        mut tm_min: 0 as c_int,
+        mut tm_hour: 0 as c_int,
+        mut tm_mday: 0 as c_int,
+        mut tm_mon: 0 as c_int,
+        mut tm_year: 0 as c_int,
+        mut tm_wday: 0 as c_int,
+        mut tm_yday: 0 as c_int,
+        mut tm_isdst: opts.default_isdst as c_int,
+        mut tm_gmtoff: 0 as c_long,
+        mut tm_zone: "",
+        mut tm_nsec: 0i32,
+    };
+    let mut pos = 0u;
+    let len = str::len(s);
+    let mut fpos = 0u;
+    let flen = str::len(format);
+    let mut failure = parse_error("Invalid time");
+    let mut saw_yday = false;
+    let mut saw_zone = false;
+    let mut stopped = false;
+    let era = { mut era: none, mut year: none };
+
+    while fpos < flen && pos < len {
+        let {ch, next} = str::char_range_at(s, pos);
+        let start = fpos;
+
+        alt next_format_char(format, fpos) {
+          '%' {
+            let spec = fpos - 1u;
+            let ty = next_format_char(format, fpos);
+            if ty == 'j' { saw_yday = true; }
+            if ty == 'z' || ty == 'Z' || ty == 'Q' { saw_zone = true; }
+            let year = tm.tm_year;
+            let r = if ty == 'E' {
+                let ty = next_format_char(format, fpos);
+                parse_era_type(s, pos, ty, tm, era, opts)
+            } else {
+                parse_type(s, pos, ty, tm, opts)
+            };
+            alt r {
+              ok(next) {
+                // Any year parsed in the Buddhist era is shifted back
+                // to the Gregorian calendar.
+                if opts.era == era_buddhist && tm.tm_year != year {
+                    tm.tm_year -= buddhist_era_offset as c_int;
+                }
+                pos = next;
+              }
+              err(parse_error(m)) if m == input_ended {
+                fpos = spec;
+                pos = len;
+                break;
+              }
+              err(e) {
+                failure = e;
+                fpos = spec;
+                stopped = true;
+                break;
+              }
+            }
+          }
+          c if opts.whitespace && char::is_whitespace(c) {
+            pos = skip_spaces(s, pos);
+          }
+          c {
+            if c != ch {
+                fpos = start;
+                stopped = true;
+                break;
+            }
+            pos = next;
+          }
+        }
+    }
+
+    // Whitespace at the end of the format matches the empty end of the
+    // string.
+    if opts.whitespace {
+        while fpos < flen {
+            let {ch, next} = str::char_range_at(format, fpos);
+            if char::is_whitespace(ch) {
+                fpos = next;
+            } else if format[fpos] == '%' as u8 && fpos + 1u < flen &&
+                      (format[fpos + 1u] == 'n' as u8 ||
+                       format[fpos + 1u] == 't' as u8) {
+                fpos += 2u;
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Say what the format still wanted if the string ran out first.
+    if !stopped && fpos < flen {
+        let mut i = fpos;
+        let c = next_format_char(format, i);
+        let pending = if c != '%' {
+            #fmt("expecting %?", str::from_char(c))
+        } else if format[i] == 'E' as u8 && i + 1u < flen {
+            "parsing " + str::slice(format, fpos, i + 2u)
+        } else {
+            "parsing " + str::slice(format, fpos, uint::min(i + 1u, flen))
+        };
+        failure = parse_error(#fmt("%s while %s at offset %u",
+                                   input_ended, pending, pos));
+    }
+
+    if fpos == flen && (pos == len || !opts.strict) {
+        // An era and a year of the era, from %EC and %Ey, determine
+        // the year.
+        alt (era.era, era.year) {
+          (some(e), some(y)) {
+            tm.tm_year = (e.start.year + y - 1 - 1900) as c_int;
+          }
+          _ { }
+        }
+        let year = tm.tm_year as int + 1900;
+
+        // A day of the year without a day of the month determines
+        // the month and day.
+        if saw_yday && tm.tm_mday == 0 as c_int {
+            alt date_from_ordinal(year, tm.tm_yday as int + 1) {
+              ok(d) {
+                tm.tm_mon = d.month.to_tm_mon();
+                tm.tm_mday = d.day as c_int;
+              }
+              err(e) {
+                ret err({ error: e, pos: pos, format_pos: fpos,
+                          partial: freeze(tm) });
+              }
+            }
+        }
+
+        // Derive the weekday and day of the year when a complete,
+        // valid date was parsed.
+        let parsed = freeze(tm);
+        let parsed = if has_valid_date(parsed) {
+            parsed.fill_derived()
+        } else {
+            parsed
+        };
+        let r = alt opts.default_zone {
+          some(z) if !saw_zone { with_default_zone(parsed, z) }
+          _ { ok(parsed) }
+        };
+        alt r {
+          ok(tm) { ok((tm, pos)) }
+          err(e) {
+            err({ error: e, pos: pos, format_pos: fpos, partial: parsed })
+          }
+        }
+    } else {
+        err({ error: failure, pos: pos, format_pos: fpos,
+              partial: freeze(tm) })
+    }
+}
+
+// The error of a conversion which ran out of input, which is reported
+// with the conversion it was parsing.
+const input_ended: str = "Input ended";
+
+// Gives a parsed time without a zone of its own the offset and name of
+// the zone.
+fn with_default_zone(tm: tm, z: zone) -> result<tm, error> {
+    alt z {
+      zone_utc {
+        ok({ tm_isdst: 0 as c_int, tm_gmtoff: 0 as c_long, tm_zone: "UTC"
+             with tm })
+      }
+      zone_offset(off) {
+        ok({ tm_gmtoff: off as c_long, tm_zone: "" with tm })
+      }
+      zone_local {
+        resolve_local(tm).chain { |local|
+            ok({
+                tm_isdst: local.tm_isdst,
+                tm_gmtoff: local.tm_gmtoff,
+                tm_zone: local.tm_zone
+                with tm
+            })
+        }
+      }
+    }
+}
+
+// The fields of a tm which the conversions in a format write.
+fn roundtrip_fields(format: str) -> [str] {
+    let mut fields = [];
+    let mut i = 0u;
+    let len = str::len(format);
+    while i + 1u < len {
+        if format[i] != '%' as u8 {
+            i += 1u;
+            cont;
+        }
+        i += 1u;
+        if format[i] == 'E' as u8 && i + 1u < len { i += 1u; }
+        fields += alt format[i] as char {
+          'a' | 'A' | 'u' | 'w' { ["tm_wday"] }
+          'b' | 'B' | 'h' | 'm' { ["tm_mon"] }
+          'C' | 'y' | 'Y' { ["tm_year"] }
+          'c' {
+            ["tm_wday", "tm_mon", "tm_mday", "tm_hour", "tm_min", "tm_sec",
+             "tm_year"]
+          }
+          'D' | 'x' | 'F' | 'v' { ["tm_year", "tm_mon", "tm_mday"] }
+          'd' | 'e' { ["tm_mday"] }
+          'H' | 'k' | 'I' | 'l' { ["tm_hour"] }
+          'j' { ["tm_yday"] }
+          'M' { ["tm_min"] }
+          'Q' {
+            ["tm_year", "tm_mon", "tm_mday", "tm_hour", "tm_min", "tm_sec"]
+          }
+          'R' { ["tm_hour", "tm_min"] }
+          'r' | 'T' | 'X' { ["tm_hour", "tm_min", "tm_sec"] }
+          'S' { ["tm_sec"] }
+          'z' { ["tm_gmtoff"] }
+          'Z' { ["tm_zone"] }
+          _ { [] }
+        };
+        i += 1u;
+    }
+    fields
+}
+
+// The value of the named field of a tm, for reporting.
+fn field_str(tm: tm, name: str) -> str {
+    alt check name {
+      "tm_sec" { int::str(tm.tm_sec as int) }
+      "tm_min" { int::str(tm.tm_min as int) }
+      "tm_hour" { int::str(tm.tm_hour as int) }
+      "tm_mday" { int::str(tm.tm_mday as int) }
+      "tm_mon" { int::str(tm.tm_mon as int) }
+      "tm_year" { int::str(tm.tm_year as int) }
+      "tm_wday" { int::str(tm.tm_wday as int) }
+      "tm_yday" { int::str(tm.tm_yday as int) }
+      "tm_gmtoff" { int::str(tm.tm_gmtoff as int) }
+      "tm_zone" { #fmt("%?", tm.tm_zone) }
+    }
+}
+
+#[doc = "
+Parses an RFC 2822 date, as in a mail Date header such as
+\"Fri, 13 Feb 2009 23:31:30 +0000 (UTC)\". Folded whitespace and
+comments are allowed wherever the grammar allows them, the weekday and
+the seconds may be left out, and the obsolete zone names are accepted
+along with numeric offsets. The offset is kept as written.
+"]
+fn parse_rfc2822(s: str) -> result<tm, error> {
+    // The day may have one or two digits; %e takes either, with the space
+    // after a single digit standing in for the missing one.
+    let formats = ["%a , %e %b %Y %H:%M:%S", "%a , %e %b %Y %H:%M",
+                   "%e %b %Y %H:%M:%S", "%e %b %Y %H:%M"];
+    let opts = { whitespace: true with strptime_options() };
+
+    unfold_rfc2822(s).chain { |s|
+        alt str::rfind_char(s, ' ') {
+          none { err(parse_error("Invalid RFC 2822 date")) }
+          some(i) {
+            let date = str::slice(s, 0u, i);
+            let zone = str::slice(s, i + 1u, str::len(s));
+            rfc2822_zone(zone).chain { |z|
+                let (gmtoff, name) = z;
+                let mut r = err(parse_error("Invalid RFC 2822 date"));
+                for vec::each(formats) {|format|
+                    alt strptime_opts(date, format, opts) {
+                      ok(tm) {
+                        r = ok({ tm_gmtoff: gmtoff as c_long,
+                                 tm_zone: name with tm });
+                        break;
+                      }
+                      err(_) { }
+                    }
+                }
+                r
+            }
+          }
+        }
+    }
+}
+
+#[doc = "
+Parses a Netscape cookie Expires date such as
+\"Fri, 13-Feb-2009 23:31:30 GMT\", as written by `tm::cookie_expires`,
+into a UTC tm.
+"]
+fn parse_cookie_expires(s: str) -> result<tm, error> {
+    strptime(s, "%a, %d-%b-%Y %H:%M:%S GMT").chain { |tm|
+        ok(at_utc(tm.to_timespec()))
+    }
+}
+
+#[doc = "
+Parses a date as `ls -l` lists it, either \"Feb 13 23:31\" for a recent
+file or \"Feb 13  2009\" for an older one. A recent date has no year,
+so it is given the year of `reference`, usually the time of the
+listing, or the year before if that would put it more than a day after
+`reference`. Like `strptime`, the tm has no offset or zone.
+"]
+fn parse_ls_date(s: str, reference: tm) -> result<tm, error> {
+    let opts = { whitespace: true with strptime_options() };
+    if !str::contains(s, ":") {
+        ret strptime_opts(s, "%b %e %Y", opts);
+    }
+
+    strptime_opts(s, "%b %e %H:%M", opts).chain { |tm|
+        let now = { tm_gmtoff: 0 as c_long with reference };
+        let this_year = { tm_year: reference.tm_year with tm };
+        if civil_seconds(this_year) > civil_seconds(now) + 86400_i64 {
+            ok({ tm_year: reference.tm_year - 1 as c_int with tm })
+        } else {
+            ok(this_year)
+        }
+    }
+}
+
+// Replaces the comments and runs of folding whitespace in an RFC 2822
+// date with single spaces, trimming the ends.
+fn unfold_rfc2822(s: str) -> result<str, error> {
+    let mut out = "";
+    let mut depth = 0u;
+    let mut space = false;
+    let mut escaped = false;
+
+    for str::each_char(s) {|ch|
+        if depth > 0u {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '(' {
+                depth += 1u;
+            } else if ch == ')' {
+                depth -= 1u;
+                space = true;
+            }
+        } else if ch == '(' {
+            depth = 1u;
+        } else if ch == ')' {
+            ret err(parse_error("Unbalanced comment"));
+        } else if char::is_whitespace(ch) {
+            space = true;
+        } else {
+            if space && out != "" { str::push_char(out, ' '); }
+            space = false;
+            str::push_char(out, ch);
+        }
+    }
+
+    if depth > 0u {
+        err(parse_error("Unterminated comment"))
+    } else {
+        ok(out)
+    }
+}
+
+// The offset and name of an RFC 2822 zone. Numeric offsets have no name,
+// and the military zones are taken as an unknown offset, as the RFC says.
+fn rfc2822_zone(zone: str) -> result<(i32, str), error> {
+    let len = str::len(zone);
+    if len == 5u && (zone[0] == '+' as u8 || zone[0] == '-' as u8) {
+        alt (int::from_str(str::slice(zone, 1u, 3u)),
+             int::from_str(str::slice(zone, 3u, 5u))) {
+          (some(h), some(m)) if h >= 0 && m >= 0 && m < 60 {
+            let sign = if zone[0] == '-' as u8 { -1 } else { 1 };
+            ok(((sign * (h * 3600 + m * 60)) as i32, ""))
+          }
+          _ { err(parse_error("Invalid zone offset")) }
+        }
+    } else {
+        let name = str::to_upper(zone);
+        let hours = alt name {
+          "UT" | "GMT" { 0 }
+          "EDT" { -4 }
+          "EST" | "CDT" { -5 }
+          "CST" | "MDT" { -6 }
+          "MST" | "PDT" { -7 }
+          "PST" { -8 }
+          _ if len == 1u && char::is_alphabetic(name[0] as char) {
+            ret ok((0_i32, ""));
+          }
+          _ { ret err(unknown_zone(zone)); }
+        };
+        ok(((hours * 3600) as i32, name))
+    }
+}
+
+// Parses an RFC 3339 timestamp, keeping the civil fields and offset as
+// written.
+fn parse_rfc3339(s: str) -> result<tm, error> {
+    let len = str::len(s);
+    if len < 20u { ret err(parse_error("Invalid RFC 3339 time")); }
+
+    strptime(str::slice(s, 0u, 19u), "%Y-%m-%dT%H:%M:%S").chain { |tm|
+        let mut pos = 19u;
+        let mut nsec = 0_i32;
+
+        if s[pos] == '.' as u8 {
+            pos += 1u;
+            let mut digits = 0u;
+            while pos < len && s[pos] >= '0' as u8 && s[pos] <= '9' as u8 {
+                if digits < 9u {
+                    nsec = nsec * 10_i32 + (s[pos] - ('0' as u8)) as i32;
+                    digits += 1u;
+                }
+                pos += 1u;
+            }
+            if digits == 0u {
+                ret err(parse_error("Invalid fractional seconds"));
+            }
+            while digits < 9u { nsec *= 10_i32; digits += 1u; }
+        }
+
+        let rest = str::slice(s, pos, len);
+        if rest == "Z" || rest == "z" {
+            ok({ tm_nsec: nsec with tm })
+        } else {
+            if str::len(rest) != 6u || rest[3] != ':' as u8 ||
+               (rest[0] != '+' as u8 && rest[0] != '-' as u8) {
+                ret err(parse_error("Invalid zone offset"));
+            }
+
+            alt (int::from_str(str::slice(rest, 1u, 3u)),
+                 int::from_str(str::slice(rest, 4u, 6u))) {
+              (some(h), some(m)) if h >= 0 && h < 24 && m >= 0 && m < 60 {
+                let sign = if rest[0] == '-' as u8 { -1 } else { 1 };
+                ok({
+                    tm_gmtoff: (sign * (h * 3600 + m * 60)) as c_long,
+                    tm_nsec: nsec
+                    with tm
+                })
+              }
+              _ { err(parse_error("Invalid zone offset")) }
+            }
+        }
+    }
+}
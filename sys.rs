@@ -0,0 +1,647 @@
+// The parts of the crate that call into the platform, other than
+// get_time. UTC and fixed offsets are always converted in Rust, whatever
+// the configuration, so the pure Rust path is already the default for
+// them. Only the local zone needs the C library to be correct.
+//
+// By default the C library is used for the local zone, the locale's
+// formats and file and process times. Two cfgs replace it:
+//
+// * `--cfg time_pure` selects the pure Rust conversions at the bottom of
+//   this file, which treat the local zone as UTC, so nothing calls the C
+//   library's time functions.
+// * `--cfg time_freestanding` also replaces the file and process times,
+//   which then report that they are unavailable, leaving get_time as the
+//   only platform call. No native module is compiled in.
+//
+// The native modules are nested inside modules gated on the backend,
+// since their own cfgs select the platform.
+import libc::{c_char, c_int, c_long};
+
+export libc_tm, to_libc_tm, from_libc_tm, with_libc_tm, localtime, mktime,
+       stat_times, set_times, process_start, locale_formats;
+
+#[doc = "
+The platform's C `struct tm`, for passing times to C functions. Where
+the zone abbreviation is a pointer into the C library, the tm's own is
+copied in and out by `with_libc_tm` and `from_libc_tm`.
+"]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+type libc_tm = {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *c_char,
+};
+
+#[doc = "
+The platform's C `struct tm`, for passing times to C functions. It has
+no offset or zone abbreviation on Windows.
+"]
+#[cfg(target_os = "win32")]
+type libc_tm = {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+};
+
+#[doc = "
+Converts a tm to the platform's `struct tm`. Its zone abbreviation is
+null, since the tm's can't outlive the tm; use `with_libc_tm` to pass
+the abbreviation to C. The nanoseconds are dropped.
+"]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn to_libc_tm(tm: tm) -> libc_tm {
+    {
+        tm_sec: tm.tm_sec,
+        tm_min: tm.tm_min,
+        tm_hour: tm.tm_hour,
+        tm_mday: tm.tm_mday,
+        tm_mon: tm.tm_mon,
+        tm_year: tm.tm_year,
+        tm_wday: tm.tm_wday,
+        tm_yday: tm.tm_yday,
+        tm_isdst: tm.tm_isdst,
+        tm_gmtoff: tm.tm_gmtoff,
+        tm_zone: ptr::null(),
+    }
+}
+
+#[doc = "
+Converts a tm to the platform's `struct tm`. The offset, zone
+abbreviation and nanoseconds are dropped.
+"]
+#[cfg(target_os = "win32")]
+fn to_libc_tm(tm: tm) -> libc_tm {
+    {
+        tm_sec: tm.tm_sec,
+        tm_min: tm.tm_min,
+        tm_hour: tm.tm_hour,
+        tm_mday: tm.tm_mday,
+        tm_mon: tm.tm_mon,
+        tm_year: tm.tm_year,
+        tm_wday: tm.tm_wday,
+        tm_yday: tm.tm_yday,
+        tm_isdst: tm.tm_isdst,
+    }
+}
+
+#[doc = "
+Converts the platform's `struct tm` to a tm, copying the zone
+abbreviation, which must be null or point to a C string. The nanoseconds
+are zero.
+"]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn from_libc_tm(tm: libc_tm) -> tm {
+    let zone = if tm.tm_zone == ptr::null() {
+        ""
+    } else {
+        unsafe { str::unsafe::from_c_str(tm.tm_zone) }
+    };
+    {
+        tm_sec: tm.tm_sec,
+        tm_min: tm.tm_min,
+        tm_hour: tm.tm_hour,
+        tm_mday: tm.tm_mday,
+        tm_mon: tm.tm_mon,
+        tm_year: tm.tm_year,
+        tm_wday: tm.tm_wday,
+        tm_yday: tm.tm_yday,
+        tm_isdst: tm.tm_isdst,
+        tm_gmtoff: tm.tm_gmtoff,
+        tm_zone: zone,
+        tm_nsec: 0_i32,
+    }
+}
+
+#[doc = "
+Converts the platform's `struct tm` to a tm, which has a zero offset and
+no zone abbreviation. The nanoseconds are zero.
+"]
+#[cfg(target_os = "win32")]
+fn from_libc_tm(tm: libc_tm) -> tm {
+    {
+        tm_sec: tm.tm_sec,
+        tm_min: tm.tm_min,
+        tm_hour: tm.tm_hour,
+        tm_mday: tm.tm_mday,
+        tm_mon: tm.tm_mon,
+        tm_year: tm.tm_year,
+        tm_wday: tm.tm_wday,
+        tm_yday: tm.tm_yday,
+        tm_isdst: tm.tm_isdst,
+        tm_gmtoff: 0 as c_long,
+        tm_zone: "",
+        tm_nsec: 0_i32,
+    }
+}
+
+#[doc = "
+Calls `f` with a tm converted to the platform's `struct tm`, whose zone
+abbreviation points to a copy of the tm's for the duration of the call.
+"]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn with_libc_tm<T>(tm: tm, f: fn(libc_tm) -> T) -> T {
+    str::as_c_str(tm.tm_zone) {|zone|
+        f({ tm_zone: zone with to_libc_tm(tm) })
+    }
+}
+
+#[doc = "Calls `f` with a tm converted to the platform's `struct tm`."]
+#[cfg(target_os = "win32")]
+fn with_libc_tm<T>(tm: tm, f: fn(libc_tm) -> T) -> T {
+    f(to_libc_tm(tm))
+}
+
+#[cfg(not(time_pure, time_freestanding))]
+mod libc_sys {
+    import libc::{c_char, c_int, time_t};
+
+    export localtime, mktime, locale_formats;
+
+    #[abi = "cdecl"]
+    #[nolink]
+    native mod libtime {
+        // FIXME: The i64 values can be passed by-val when #2064 is fixed.
+        fn tzset();
+        fn localtime_r(&&sec: time_t, &&result: libc_tm) -> *libc_tm;
+        fn mktime(&&tm: libc_tm) -> time_t;
+    }
+
+    // Converts a time to the local zone, or returns none if the platform
+    // can't convert it: it is outside of the range of time_t, or is
+    // rejected (some platforms reject times before 1970).
+    fn localtime(clock: timespec) -> option<tm> {
+        let mut sec = clock.sec as time_t;
+        if sec as i64 != clock.sec {
+            ret none;
+        }
+
+        let mut tm = to_libc_tm(empty_tm());
+        libtime::tzset();
+        if libtime::localtime_r(sec, tm) == ptr::null() {
+            none
+        } else {
+            some({ tm_nsec: clock.nsec with from_libc_tm(tm) })
+        }
+    }
+
+    // Converts a tm in the local zone to seconds since the epoch, or
+    // returns none if it is outside of the range of time_t.
+    fn mktime(tm: tm) -> option<i64> {
+        let sec = libtime::mktime(to_libc_tm(tm)) as i64;
+        if sec == -1_i64 { none } else { some(sec) }
+    }
+
+    // The formats of %x and %X in the C library's current LC_TIME locale,
+    // which a program selects with setlocale, or none if the platform
+    // has no nl_langinfo.
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    fn locale_formats() -> option<(str, str)> {
+        fn langinfo(item: c_int) -> str {
+            let p = liblanginfo::nl_langinfo(item);
+            if p == ptr::null() {
+                ""
+            } else {
+                unsafe { str::unsafe::from_c_str(p) }
+            }
+        }
+        some((langinfo(langinfo_items::d_fmt),
+              langinfo(langinfo_items::t_fmt)))
+    }
+
+    #[cfg(target_os = "win32")]
+    fn locale_formats() -> option<(str, str)> { none }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    native mod liblanginfo {
+        fn nl_langinfo(item: c_int) -> *c_char;
+    }
+
+    // The nl_langinfo items for the date and time formats.
+    #[cfg(target_os = "linux")]
+    mod langinfo_items {
+        const d_fmt: i32 = 0x20029_i32;
+        const t_fmt: i32 = 0x2002a_i32;
+    }
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    mod langinfo_items {
+        const d_fmt: i32 = 2_i32;
+        const t_fmt: i32 = 3_i32;
+    }
+}
+
+#[cfg(not(time_freestanding))]
+mod libc_files {
+    import libc::{c_char, c_int, c_uint, c_long, size_t, time_t};
+
+    export stat_times, set_times, process_start;
+
+    // Reads the times of the file at `path` from its struct stat, or
+    // returns none if it can't be stat'd.
+    fn stat_times(path: str) -> option<file_times> {
+        if !stat_layout::known {
+            ret none;
+        }
+        let buf = vec::from_elem(stat_layout::size, 0u8);
+        let r = str::as_c_str(path) {|p|
+            libstat::stat(p, vec::unsafe::to_ptr(buf))
+        };
+        if r != 0 as c_int {
+            ret none;
+        }
+
+        let time_at = fn@(off: uint) -> timespec {
+            let sec = read_native_i64(buf, off);
+            if stat_layout::has_nsec {
+                timespec_from_stat(sec, some(read_native_i64(buf, off + 8u)))
+            } else {
+                timespec_from_stat(sec, none)
+            }
+        };
+        some({ accessed: time_at(stat_layout::atime),
+               modified: time_at(stat_layout::mtime),
+               changed: time_at(stat_layout::ctime) })
+    }
+
+    // Sets the access and modification times of the file at `path`,
+    // returning whether it succeeded.
+    fn set_times(path: str, atime: timespec, mtime: timespec) -> bool {
+        let r = str::as_c_str(path) {|p| utime_native(p, atime, mtime) };
+        r == 0 as c_int
+    }
+
+    fn process_start() -> option<timespec> { process_start_native() }
+
+    // stat(2) is declared with the platform's struct stat treated as an
+    // opaque buffer; the `stat_layout` module says where the times live.
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    native mod libstat {
+        fn stat(path: *c_char, buf: *u8) -> c_int;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "win32")]
+    native mod libstat {
+        #[link_name = "_stat64"]
+        fn stat(path: *c_char, buf: *u8) -> c_int;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "linux")]
+    native mod libutime {
+        fn utimensat(dirfd: c_int, path: *c_char, times: *c_timespec,
+                     flags: c_int) -> c_int;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    native mod libutime {
+        fn utimes(path: *c_char, times: *c_timeval) -> c_int;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "win32")]
+    native mod libutime {
+        #[link_name = "_utime64"]
+        fn utime(path: *c_char, times: *c_utimbuf) -> c_int;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "linux")]
+    native mod libproc {
+        fn sysconf(name: c_int) -> c_long;
+    }
+
+    #[abi = "cdecl"]
+    #[nolink]
+    #[cfg(target_os = "macos")]
+    native mod libproc {
+        fn getpid() -> c_int;
+        fn sysctl(name: *c_int, namelen: c_uint, oldp: *u8, &oldlenp: size_t,
+                  newp: *u8, newlen: size_t) -> c_int;
+    }
+
+    #[abi = "stdcall"]
+    #[cfg(target_os = "win32")]
+    native mod kernel32 {
+        fn GetCurrentProcess() -> *u8;
+        fn GetProcessTimes(process: *u8, creation: *u64, exit: *u64,
+                           kernel: *u64, user: *u64) -> c_int;
+    }
+
+    // The Unix calls take an array of two {seconds, fraction} pairs, laid
+    // out as the platform's struct timespec or struct timeval; Windows
+    // takes a struct __utimbuf64 of the two seconds alone.
+    #[cfg(target_os = "linux")]
+    type c_timespec = { tv_sec: time_t, tv_nsec: c_long };
+
+    // tv_usec is a 32-bit suseconds_t on macOS, but a long on FreeBSD.
+    #[cfg(target_os = "macos")]
+    type c_timeval = { tv_sec: time_t, tv_usec: i32 };
+
+    #[cfg(target_os = "freebsd")]
+    type c_timeval = { tv_sec: time_t, tv_usec: c_long };
+
+    #[cfg(target_os = "win32")]
+    type c_utimbuf = { actime: i64, modtime: i64 };
+
+    // Whether `sec` survives the trip through a time_t, which is only 32
+    // bits wide on some targets.
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    fn fits_time_t(sec: i64) -> bool {
+        sec as time_t as i64 == sec
+    }
+
+    #[cfg(target_os = "linux")]
+    fn utime_native(p: *c_char, atime: timespec, mtime: timespec) -> c_int {
+        if !fits_time_t(atime.sec) || !fits_time_t(mtime.sec) {
+            ret -1 as c_int;
+        }
+        let times = [{ tv_sec: atime.sec as time_t,
+                       tv_nsec: atime.nsec as c_long },
+                     { tv_sec: mtime.sec as time_t,
+                       tv_nsec: mtime.nsec as c_long }];
+        // AT_FDCWD: resolve relative paths against the working directory.
+        libutime::utimensat(-100 as c_int, p, vec::unsafe::to_ptr(times),
+                            0 as c_int)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn utime_native(p: *c_char, atime: timespec, mtime: timespec) -> c_int {
+        if !fits_time_t(atime.sec) || !fits_time_t(mtime.sec) {
+            ret -1 as c_int;
+        }
+        let times = [{ tv_sec: atime.sec as time_t,
+                       tv_usec: atime.nsec / 1000_i32 },
+                     { tv_sec: mtime.sec as time_t,
+                       tv_usec: mtime.nsec / 1000_i32 }];
+        libutime::utimes(p, vec::unsafe::to_ptr(times))
+    }
+
+    #[cfg(target_os = "freebsd")]
+    fn utime_native(p: *c_char, atime: timespec, mtime: timespec) -> c_int {
+        if !fits_time_t(atime.sec) || !fits_time_t(mtime.sec) {
+            ret -1 as c_int;
+        }
+        let times = [{ tv_sec: atime.sec as time_t,
+                       tv_usec: (atime.nsec / 1000_i32) as c_long },
+                     { tv_sec: mtime.sec as time_t,
+                       tv_usec: (mtime.nsec / 1000_i32) as c_long }];
+        libutime::utimes(p, vec::unsafe::to_ptr(times))
+    }
+
+    #[cfg(target_os = "win32")]
+    fn utime_native(p: *c_char, atime: timespec, mtime: timespec) -> c_int {
+        let times = { actime: atime.sec, modtime: mtime.sec };
+        libutime::utime(p, ptr::addr_of(times))
+    }
+
+    // The process start time is kept in /proc/self/stat as a count of clock
+    // ticks since the boot time recorded in /proc/stat. Returns none if
+    // /proc is missing or doesn't look as expected.
+    #[cfg(target_os = "linux")]
+    fn process_start_native() -> option<timespec> {
+        let stat = alt io::read_whole_file_str("/proc/self/stat") {
+          ok(s) { s }
+          err(_) { ret none; }
+        };
+
+        // The command name may contain spaces, so skip past its parenthesis.
+        // The remaining fields start at the third, and starttime is the 22nd.
+        let name_end = alt str::rfind_char(stat, ')') {
+          some(i) if i + 2u <= str::len(stat) { i }
+          _ { ret none; }
+        };
+        let fields = str::split_char(str::slice(stat, name_end + 2u,
+                                                str::len(stat)), ' ');
+        if vec::len(fields) < 20u { ret none; }
+        let ticks = alt int::from_str(fields[19]) {
+          some(n) { n as i64 }
+          none { ret none; }
+        };
+
+        let info = alt io::read_whole_file_str("/proc/stat") {
+          ok(s) { s }
+          err(_) { ret none; }
+        };
+        let mut boot = none;
+        for vec::each(str::lines(info)) {|line|
+            if str::starts_with(line, "btime ") {
+                boot = int::from_str(str::slice(line, 6u, str::len(line)));
+            }
+        }
+
+        // _SC_CLK_TCK
+        let hz = libproc::sysconf(2 as c_int) as i64;
+        if hz <= 0_i64 { ret none; }
+        option::map(boot) {|boot|
+            { sec: (boot as i64) + ticks / hz,
+              nsec: ((ticks % hz) * 1000000000_i64 / hz) as i32 }
+        }
+    }
+
+    // The start time is the first field of the kinfo_proc returned by the
+    // KERN_PROC_PID sysctl, as a timeval.
+    #[cfg(target_os = "macos")]
+    fn process_start_native() -> option<timespec> {
+        // CTL_KERN, KERN_PROC, KERN_PROC_PID
+        let mib = [1 as c_int, 14 as c_int, 1 as c_int, libproc::getpid()];
+        let buf = vec::from_elem(648u, 0u8);
+        let mut len = 648u as size_t;
+        let r = libproc::sysctl(vec::unsafe::to_ptr(mib), 4 as c_uint,
+                                vec::unsafe::to_ptr(buf), len, ptr::null(),
+                                0u as size_t);
+        if r != 0 as c_int {
+            ret none;
+        }
+
+        let usec = unsafe {
+            *(ptr::offset(vec::unsafe::to_ptr(buf), 8u) as *i32)
+        };
+        some({ sec: read_native_i64(buf, 0u), nsec: usec * 1000_i32 })
+    }
+
+    // GetProcessTimes returns the creation time as a FILETIME, a count of
+    // 100ns intervals since 1601-01-01.
+    #[cfg(target_os = "win32")]
+    fn process_start_native() -> option<timespec> {
+        let mut creation = 0u64, exit = 0u64, kernel = 0u64, user = 0u64;
+        let r = kernel32::GetProcessTimes(kernel32::GetCurrentProcess(),
+                                          ptr::addr_of(creation),
+                                          ptr::addr_of(exit),
+                                          ptr::addr_of(kernel),
+                                          ptr::addr_of(user));
+        if r == 0 as c_int {
+            ret none;
+        }
+
+        let v = (creation as i64) - 116444736000000000_i64;
+        some({ sec: v / 10000000_i64,
+               nsec: ((v % 10000000_i64) * 100_i64) as i32 })
+    }
+
+    // Reads a native-endian i64 from `buf` at byte offset `off`.
+    fn read_native_i64(buf: [u8], off: uint) -> i64 {
+        unsafe {
+            *(ptr::offset(vec::unsafe::to_ptr(buf), off) as *i64)
+        }
+    }
+
+    // The size of struct stat and the offsets of its time fields. Each time
+    // is a 64-bit seconds field, followed by a 64-bit nanoseconds field where
+    // `has_nsec` is set. Only the x86_64 layouts are known for Unix. The
+    // attributes in a single cfg are alternatives, so each module starts from
+    // the unknown layout and overrides it item by item on x86_64; where
+    // `known` is false the times are not read.
+    mod unknown_stat_layout {
+        const known: bool = false;
+        const size: uint = 0u;
+        const atime: uint = 0u;
+        const mtime: uint = 0u;
+        const ctime: uint = 0u;
+        const has_nsec: bool = false;
+    }
+
+    #[cfg(target_os = "linux")]
+    mod stat_layout {
+        import unknown_stat_layout::*;
+
+        #[cfg(target_arch = "x86_64")]
+        const known: bool = true;
+        #[cfg(target_arch = "x86_64")]
+        const size: uint = 144u;
+        #[cfg(target_arch = "x86_64")]
+        const atime: uint = 72u;
+        #[cfg(target_arch = "x86_64")]
+        const mtime: uint = 88u;
+        #[cfg(target_arch = "x86_64")]
+        const ctime: uint = 104u;
+        #[cfg(target_arch = "x86_64")]
+        const has_nsec: bool = true;
+    }
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    mod stat_layout {
+        import unknown_stat_layout::*;
+
+        #[cfg(target_arch = "x86_64")]
+        const known: bool = true;
+        #[cfg(target_arch = "x86_64")]
+        const size: uint = 144u;
+        #[cfg(target_arch = "x86_64")]
+        const atime: uint = 24u;
+        #[cfg(target_arch = "x86_64")]
+        const mtime: uint = 40u;
+        #[cfg(target_arch = "x86_64")]
+        const ctime: uint = 56u;
+        #[cfg(target_arch = "x86_64")]
+        const has_nsec: bool = true;
+    }
+
+    // struct _stat64 is the same on 32- and 64-bit Windows.
+    #[cfg(target_os = "win32")]
+    mod stat_layout {
+        const known: bool = true;
+        const size: uint = 56u;
+        const atime: uint = 32u;
+        const mtime: uint = 40u;
+        const ctime: uint = 48u;
+        const has_nsec: bool = false;
+    }
+}
+
+// The C library backend, the default.
+#[cfg(not(time_pure, time_freestanding))]
+fn localtime(clock: timespec) -> option<tm> { libc_sys::localtime(clock) }
+
+#[cfg(not(time_pure, time_freestanding))]
+fn mktime(tm: tm) -> option<i64> { libc_sys::mktime(tm) }
+
+#[cfg(not(time_pure, time_freestanding))]
+fn locale_formats() -> option<(str, str)> { libc_sys::locale_formats() }
+
+// The file and process times come from the C library in both the C
+// library and the pure Rust backends.
+#[cfg(not(time_freestanding))]
+fn stat_times(path: str) -> option<file_times> {
+    libc_files::stat_times(path)
+}
+
+#[cfg(not(time_freestanding))]
+fn set_times(path: str, atime: timespec, mtime: timespec) -> bool {
+    libc_files::set_times(path, atime, mtime)
+}
+
+#[cfg(not(time_freestanding))]
+fn process_start() -> option<timespec> { libc_files::process_start() }
+
+// Without the C library there is no local zone, so it is UTC. Local
+// conversions succeed as UTC conversions, rather than reporting that they
+// can't convert, so that resolve_local gives UTC times too instead of an
+// error.
+#[cfg(time_pure)]
+#[cfg(time_freestanding)]
+fn localtime(clock: timespec) -> option<tm> { some(at_utc(clock)) }
+
+#[cfg(time_pure)]
+#[cfg(time_freestanding)]
+fn mktime(tm: tm) -> option<i64> { some(civil_seconds(tm)) }
+
+// Nor is there a locale other than C's.
+#[cfg(time_pure)]
+#[cfg(time_freestanding)]
+fn locale_formats() -> option<(str, str)> { none }
+
+// Freestanding, there are no files or processes to ask about.
+#[cfg(time_freestanding)]
+fn stat_times(_path: str) -> option<file_times> { none }
+
+#[cfg(time_freestanding)]
+fn set_times(_path: str, _atime: timespec, _mtime: timespec) -> bool {
+    false
+}
+
+#[cfg(time_freestanding)]
+fn process_start() -> option<timespec> { none }
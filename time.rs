@@ -1,33 +1,203 @@
-import libc::{c_char, c_int, c_long, size_t, time_t};
+import libc::{c_char, c_int, c_long};
 import io::{reader, reader_util};
 import result::{result, ok, err, methods};
 import std::time;
+import std::json;
+import std::map;
+import map::hashmap;
+import std::serialization::{serializer, deserializer};
+import tz::{zone, zone_utc, zone_local, zone_offset, conversion_cache, at_utc,
+            now_utc, at, now, at_zone, try_at_utc, try_at, resolve_local,
+            is_dst_at, local_offset_at, zone_info, local_zone_info,
+            local_zone_info_at, zone_abbrev, offset_for_abbrev,
+            lookup_abbrev, next_occurrence_of, next_occurrence_after,
+            midnight_in_zone, at_gmtoff};
+import parse::{strptime, strptime_with_era, strptime_options, strptime_opts,
+               strptime_bytes, partial_parse, strptime_partial,
+               verify_roundtrip, parse_rfc2822, parse_cookie_expires,
+               parse_ls_date, parse_rfc3339};
+import format::{strftime, strftime_with_era, strftime_with_locale,
+                log_formatter, format_buffer, format_rfc822, format_rfc5322,
+                format_cookie_expires, format_rfc3339, year_str, rfc3339_nsec};
+import duration::{duration, duration_days, duration_hours, duration_minutes,
+                  duration_seconds, duration_millis, duration_micros,
+                  duration_nanos, duration_from_parts, difftime,
+                  difftime_exact, rate, format_rate};
+import sys::{libc_tm, to_libc_tm, from_libc_tm, with_libc_tm};
 
 export
+    calendar,
+    error,
     timespec,
     get_time,
+    unique_clock,
     tm,
     empty_tm,
+    libc_tm,
+    to_libc_tm,
+    from_libc_tm,
+    with_libc_tm,
+    c_tm,
+    to_c_tm,
+    from_c_tm,
+    with_c_tm,
     now,
     at,
     now_utc,
     at_utc,
-    strptime;
-
-#[abi = "cdecl"]
-#[nolink]
-native mod libtime {
-    // FIXME: The i64 values can be passed by-val when #2064 is fixed.
-    fn tzset();
-    fn gmtime_r(&&sec: time_t, &&result: tm) -> *tm;
-    fn localtime_r(&&sec: time_t, &&result: tm) -> *tm;
-    fn timegm(&&tm: tm) -> time_t;
-    fn mktime(&&tm: tm) -> time_t;
-}
+    try_at,
+    try_at_utc,
+    resolve_local,
+    is_dst_at,
+    local_offset_at,
+    zone_info,
+    local_zone_info,
+    local_zone_info_at,
+    zone_abbrev,
+    offset_for_abbrev,
+    next_occurrence_of,
+    next_occurrence_after,
+    strptime,
+    strptime_with_era,
+    strptime_options,
+    strptime_opts,
+    strptime_bytes,
+    partial_parse,
+    strptime_partial,
+    verify_roundtrip,
+    parse_rfc2822,
+    parse_cookie_expires,
+    parse_ls_date,
+    era_system,
+    locale,
+    c_locale,
+    system_locale,
+    json_format,
+    timespec_from_json,
+    tm_from_json,
+    timespec_from_bytes,
+    tm_from_bytes,
+    serialize_timespec,
+    deserialize_timespec,
+    serialize_tm,
+    deserialize_tm,
+    hash_timespec,
+    eq_timespec,
+    hash_tm,
+    eq_tm,
+    timespec_hash,
+    tm_hash,
+    cmp_timespec,
+    le_timespec,
+    cmp_tm,
+    le_tm,
+    bsearch_timespec,
+    zone,
+    at_zone,
+    tm_from_ymd,
+    tm_from_ymd_hms,
+    tm_from_ymd_hms_nsec,
+    weekday,
+    weekday_from_days_from_sunday,
+    month,
+    month_from_number,
+    month_from_tm_mon,
+    date,
+    date_from_ymd,
+    date_from_str,
+    date_from_iso_expanded,
+    time_of_day,
+    time_of_day_from_hms,
+    time_of_day_from_hms_nsec,
+    time_of_day_from_str,
+    datetime,
+    datetime_at,
+    datetime_now,
+    datetime_from_tm,
+    datetime_from_str,
+    utc_tm,
+    local_tm,
+    at_utc_tm,
+    at_local_tm,
+    is_leap_year,
+    days_in_month,
+    days_in_year,
+    weekday_from_ymd,
+    date_from_ordinal,
+    week_convention,
+    nth_weekday_of_month,
+    last_day_of_month,
+    holiday_rule,
+    holiday,
+    holiday_calendar,
+    log_formatter,
+    format_buffer,
+    conversion_cache,
+    gregorian_easter,
+    julian_easter,
+    period,
+    calendar_diff,
+    months_between,
+    whole_months_between,
+    time_unit,
+    duration,
+    duration_days,
+    duration_hours,
+    duration_minutes,
+    duration_seconds,
+    duration_millis,
+    duration_micros,
+    duration_nanos,
+    timespec_from_millis,
+    timespec_from_micros,
+    timespec_from_nanos,
+    timespec_from_key_i64,
+    timespec_from_js_millis,
+    timespec_from_julianday_f64,
+    timespec_from_uuid_timestamp,
+    timespec_from_uuid,
+    timespec_from_ulid_timestamp,
+    timespec_from_ulid,
+    file_times,
+    timespec_from_stat,
+    tm_from_stat,
+    get_file_times,
+    set_file_times,
+    process_start_time,
+    process_uptime,
+    difftime,
+    difftime_exact,
+    rate,
+    format_rate;
 
 #[doc = "A record specifying a time value in seconds and microseconds."]
 type timespec = {sec: i64, nsec: i32};
 
+#[doc = "Selects how `timespec` and `tm` values are represented in JSON."]
+enum json_format {
+    // An RFC 3339 string, e.g. "2009-02-13T23:31:30Z".
+    json_rfc3339,
+    // An object of the form {"sec": 1234567890, "nsec": 54321}.
+    json_sec_nsec,
+}
+
+#[doc = "
+The ways in which the functions of this crate can fail. Each carries a
+message describing the failure, see `error::to_str`.
+"]
+enum error {
+    // The input is not in the expected form.
+    parse_error(str),
+    // A field or value is outside of its valid range.
+    out_of_range(str),
+    // A zone name or abbreviation is not known.
+    unknown_zone(str),
+    // A result does not fit in its integer type.
+    overflow,
+    // A call into the platform failed.
+    platform_error(str),
+}
+
 #[doc = "
 Returns the current time as a `timespec` containing the seconds and
 microseconds since 1970-01-01T00:00:00Z.
@@ -37,18 +207,202 @@ fn get_time() -> timespec {
     {sec: sec as i64, nsec: usec as i32 * 1000_i32}
 }
 
+#[doc = "
+A source of strictly increasing timestamps, for ordering events and
+making unique keys. Created with `unique_clock`. Every timestamp from a
+clock is later than the ones before it, so share one clock wherever the
+timestamps need to be ordered.
+"]
+type unique_clock = @{mut last: option<timespec>};
+
+#[doc = "Creates a unique clock which hasn't returned any timestamps."]
+fn unique_clock() -> unique_clock { @{mut last: none} }
+
+impl unique_clock for unique_clock {
+    #[doc = "
+    Returns the current time, or a nanosecond after the last timestamp
+    if the clock hasn't moved past it.
+    "]
+    fn next() -> timespec { self.next_from(get_time()) }
+
+    #[doc = "
+    Returns `clock`, or a nanosecond after the last timestamp if `clock`
+    isn't after it.
+    "]
+    fn next_from(clock: timespec) -> timespec {
+        let next = alt self.last {
+          some(last) if !clock.gt(last) { last.add(duration_nanos(1_i64)) }
+          _ { clock }
+        };
+        self.last = some(next);
+        next
+    }
+}
+
+#[doc = "A day of the week."]
+enum weekday {
+    sunday = 0,
+    monday = 1,
+    tuesday = 2,
+    wednesday = 3,
+    thursday = 4,
+    friday = 5,
+    saturday = 6,
+}
+
+#[doc = "A month of the year. Converting to an int gives 1 to 12."]
+enum month {
+    january = 1,
+    february = 2,
+    march = 3,
+    april = 4,
+    may = 5,
+    june = 6,
+    july = 7,
+    august = 8,
+    september = 9,
+    october = 10,
+    november = 11,
+    december = 12,
+}
+
+#[doc = "
+A calendar date without a time of day or zone, in the proleptic Gregorian
+calendar. Years are numbered astronomically: year 0 is 1 BC, and year -1
+is 2 BC.
+"]
+type date = {year: int, month: month, day: int};
+
+#[doc = "A wall-clock time of day without a date or zone."]
+type time_of_day = {hour: int, minute: int, second: int, nsec: i32};
+
+#[doc = "
+A date and time of day at a fixed offset from UTC, in seconds east of
+UTC. This is the recommended type for application code; the C-style `tm`
+record is kept for compatibility and interop.
+"]
+type datetime = {date: date, time: time_of_day, gmtoff: i32};
+
+#[doc = "
+A tm known to be in UTC. Wrapping a tm in `utc_tm` or `local_tm` costs
+nothing at runtime, but lets formatting pick the right code path by type
+instead of by checking `tm_gmtoff`.
+"]
+enum utc_tm = tm;
+
+#[doc = "A tm known to be in the local timezone."]
+enum local_tm = tm;
+
+#[doc = "
+Selects the era used by the E-modified conversions %EC, %Ey and %EY of
+`tm::strftime_with_era` and `strptime_with_era`.
+"]
+enum era_system {
+    // Japanese eras, e.g. %EY is "平成21年".
+    era_japanese,
+    // The Thai Buddhist era, 543 years ahead of the Gregorian calendar,
+    // e.g. %EY is "พ.ศ. 2552". In this mode every year, including %Y,
+    // is a Buddhist era year.
+    era_buddhist,
+}
+
+#[doc = "
+The formats a locale prefers for dates and times, which `%x` and `%X`
+stand for in `tm::strftime_with_locale` and in `strptime_opts` through
+`strptime_options::locale`. Either may be set by hand, or taken from the
+C library with `system_locale`.
+"]
+type locale = {date_format: str, time_format: str};
+
+#[doc = "
+Returns the C locale, in which `%x` is `%m/%d/%y` and `%X` is `%H:%M:%S`.
+"]
+fn c_locale() -> locale {
+    { date_format: "%m/%d/%y", time_format: "%H:%M:%S" }
+}
+
+#[doc = "
+Returns the date and time formats of the C library's current LC_TIME
+locale, which a program selects with setlocale. Where they can't be
+asked for, as on Windows or when built with `--cfg time_pure`, those of
+the C locale are returned.
+"]
+fn system_locale() -> locale {
+    alt sys::locale_formats() {
+      some((date, time)) if date != "" && time != "" {
+        { date_format: date, time_format: time }
+      }
+      _ { c_locale() }
+    }
+}
+
+#[doc = "How the days of a month are grouped into numbered weeks."]
+enum week_convention {
+    // Weeks start on Sunday, and week 1 contains the first of the month.
+    sunday_weeks,
+    // Weeks start on Monday, and week 1 contains the first Thursday of
+    // the month, as ISO 8601 does for weeks of the year.
+    iso_weeks,
+}
+
+#[doc = "A unit of time to truncate to."]
+enum time_unit {
+    unit_second,
+    unit_minute,
+    unit_hour,
+    unit_day,
+    unit_month,
+    unit_year,
+}
+
+#[doc = "A calendar difference in years, months and days."]
+type period = {years: int, months: int, days: int};
+
+#[doc = "A rule giving the date of a holiday in each year."]
+enum holiday_rule {
+    // The same month and day every year. February 29th only occurs in
+    // leap years.
+    fixed_date(month, int),
+    // The nth weekday of a month, counting from the end when negative,
+    // as for `nth_weekday_of_month`.
+    nth_weekday(month, weekday, int),
+    // A number of days after Easter Sunday, or before it if negative.
+    easter_relative(int),
+    // A single date.
+    one_off(date),
+}
+
+#[doc = "A named holiday."]
+type holiday = {name: str, rule: holiday_rule};
+
+#[doc = "A set of holidays, populated with `holiday_calendar::add`."]
+type holiday_calendar = @{mut holidays: [holiday]};
+
+#[doc = "The access, modification and status change times of a file."]
+type file_times = {accessed: timespec, modified: timespec,
+                   changed: timespec};
+
+#[doc = "
+A broken-down time, with the fields of the C `struct tm`. The zone
+abbreviation is owned by the tm rather than pointing into the C library.
+
+Comparing two tms with `==` compares every field, including the derived
+`tm_wday` and `tm_yday` and the zone abbreviation, so two tms for the
+same instant may compare unequal. Use `tm::same_instant` or
+`tm::same_civil` to compare times in a well-defined way.
+"]
 type tm = {
     tm_sec: c_int, // seconds after the minute [0-60]
     tm_min: c_int, // minutes after the hour [0-59]
     tm_hour: c_int, // hours after midnight [0-23]
     tm_mday: c_int, // days of the month [1-31]
     tm_mon: c_int, // months since January [0-11]
-    tm_year: c_int, // years since 1900
+    tm_year: c_int, // years since 1900, may be negative
     tm_wday: c_int, // days since Sunday [0-6]
     tm_yday: c_int, // days since January 1 [0-365]
     tm_isdst: c_int, // Daylight Savings Time flag
     tm_gmtoff: c_long, // offset from UTC in seconds
-    tm_zone: *c_char, // timezone abbreviation
+    tm_zone: str, // timezone abbreviation, or "" if unknown
     tm_nsec: i32,
 };
 
@@ -64,1052 +418,6153 @@ fn empty_tm() -> tm {
         tm_yday: 0 as c_int,
         tm_isdst: 0 as c_int,
         tm_gmtoff: 0 as c_long,
-        tm_zone: ptr::null(),
+        tm_zone: "",
         tm_nsec: 0_i32,
     }
 }
 
-#[doc = "Returns the specified time in UTC"]
-fn at_utc(clock: timespec) -> tm {
-    let mut sec = clock.sec as time_t;
-    let mut tm = empty_tm();
-    libtime::tzset();
-    libtime::gmtime_r(sec, tm);
-    { tm_nsec: clock.nsec with tm }
+#[doc = "
+A tm with a fixed layout, for C bindings which can't follow changes to
+the tm record or the platform's `struct tm`. In C it is:
+
+    struct rust_time_tm {
+        int32_t tm_sec, tm_min, tm_hour, tm_mday, tm_mon, tm_year;
+        int32_t tm_wday, tm_yday, tm_isdst, tm_nsec;
+        int64_t tm_gmtoff;
+        const char *tm_zone;
+    };
+"]
+type c_tm = {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    tm_mon: i32,
+    tm_year: i32,
+    tm_wday: i32,
+    tm_yday: i32,
+    tm_isdst: i32,
+    tm_nsec: i32,
+    tm_gmtoff: i64,
+    tm_zone: *c_char,
+};
+
+#[doc = "
+Converts a tm to a `c_tm`. Its zone abbreviation is null, since the
+tm's can't outlive the tm; use `with_c_tm` to pass the abbreviation to C.
+"]
+fn to_c_tm(tm: tm) -> c_tm {
+    {
+        tm_sec: tm.tm_sec as i32,
+        tm_min: tm.tm_min as i32,
+        tm_hour: tm.tm_hour as i32,
+        tm_mday: tm.tm_mday as i32,
+        tm_mon: tm.tm_mon as i32,
+        tm_year: tm.tm_year as i32,
+        tm_wday: tm.tm_wday as i32,
+        tm_yday: tm.tm_yday as i32,
+        tm_isdst: tm.tm_isdst as i32,
+        tm_nsec: tm.tm_nsec,
+        tm_gmtoff: tm.tm_gmtoff as i64,
+        tm_zone: ptr::null(),
+    }
 }
 
-#[doc = "Returns the current time in UTC"]
-fn now_utc() -> tm {
-    at_utc(get_time())
+#[doc = "
+Converts a `c_tm` to a tm, copying the zone abbreviation, which must be
+null or point to a C string.
+"]
+fn from_c_tm(tm: c_tm) -> tm {
+    let zone = if tm.tm_zone == ptr::null() {
+        ""
+    } else {
+        unsafe { str::unsafe::from_c_str(tm.tm_zone) }
+    };
+    {
+        tm_sec: tm.tm_sec as c_int,
+        tm_min: tm.tm_min as c_int,
+        tm_hour: tm.tm_hour as c_int,
+        tm_mday: tm.tm_mday as c_int,
+        tm_mon: tm.tm_mon as c_int,
+        tm_year: tm.tm_year as c_int,
+        tm_wday: tm.tm_wday as c_int,
+        tm_yday: tm.tm_yday as c_int,
+        tm_isdst: tm.tm_isdst as c_int,
+        tm_gmtoff: tm.tm_gmtoff as c_long,
+        tm_zone: zone,
+        tm_nsec: tm.tm_nsec,
+    }
 }
 
-#[doc = "Returns the specified time in the local timezone"]
-fn at(clock: timespec) -> tm {
-    let mut sec = clock.sec as time_t;
-    let mut tm = empty_tm();
-    libtime::tzset();
-    libtime::localtime_r(sec, tm);
-    { tm_nsec: clock.nsec with tm }
+#[doc = "
+Calls `f` with a tm converted to a `c_tm`, whose zone abbreviation points
+to a copy of the tm's for the duration of the call.
+"]
+fn with_c_tm<T>(tm: tm, f: fn(c_tm) -> T) -> T {
+    str::as_c_str(tm.tm_zone) {|zone|
+        f({ tm_zone: zone with to_c_tm(tm) })
+    }
 }
 
-#[doc = "Returns the current time in the local timezone"]
-fn now() -> tm {
-    at(get_time())
+#[doc = "
+Creates a UTC tm at midnight on the given date. The day is 1-based.
+Returns an error if a field is out of range.
+"]
+fn tm_from_ymd(year: int, mon: month, day: int) -> result<tm, error> {
+    tm_from_ymd_hms_nsec(year, mon, day, 0, 0, 0, 0_i32)
 }
 
-#[doc = "Parses the time from the string according to the format string."]
-fn strptime(s: str, format: str) -> result<tm, str> {
-    type tm_mut = {
-       mut tm_sec: c_int,
-       mut tm_min: c_int,
-       mut tm_hour: c_int,
-       mut tm_mday: c_int,
-       mut tm_mon: c_int,
-       mut tm_year: c_int,
-       mut tm_wday: c_int,
-       mut tm_yday: c_int,
-       mut tm_isdst: c_int,
-       mut tm_gmtoff: c_long,
-       mut tm_zone: *c_char,
-       mut tm_nsec: i32,
-    };
+#[doc = "
+Creates a UTC tm at the given date and time. The day is 1-based. Returns
+an error if a field is out of range.
+"]
+fn tm_from_ymd_hms(year: int, mon: month, day: int,
+                   hour: int, min: int, sec: int) -> result<tm, error> {
+    tm_from_ymd_hms_nsec(year, mon, day, hour, min, sec, 0_i32)
+}
 
-    fn match_str(s: str, pos: uint, needle: str) -> bool {
-        let mut i = pos;
-        for str::each(needle) {|ch|
-            if s[i] != ch {
-                ret false;
-            }
-            i += 1u;
-        }
-        ret true;
-    }
+#[doc = "
+Creates a UTC tm at the given date and time. The day is 1-based, and a
+second of 60 is accepted for leap seconds. The weekday and day of the
+year are filled in. Returns an error if a field is out of range.
+"]
+fn tm_from_ymd_hms_nsec(year: int, mon: month, day: int,
+                        hour: int, min: int, sec: int,
+                        nsec: i32) -> result<tm, error> {
+    civil_tm(year, mon.number(), day, hour, min, sec, nsec)
+}
 
-    fn match_strs(s: str, pos: uint, strs: [(str, i32)])
-      -> option<(i32, uint)> {
-        let mut i = 0u;
-        let len = vec::len(strs);
-        while i < len {
-            let (needle, value) = strs[i];
+// Builds a UTC tm from civil fields with a 1-based month, validating
+// them and filling in the weekday and day of the year.
+fn civil_tm(year: int, month: int, day: int, hour: int, min: int, sec: int,
+            nsec: i32) -> result<tm, error> {
+    if month < 1 || month > 12 { ret err(out_of_range("Invalid month")); }
+    if day < 1 || day > month_length(year, month) {
+        ret err(out_of_range("Invalid day of the month"));
+    }
+    if hour < 0 || hour > 23 { ret err(out_of_range("Invalid hour")); }
+    if min < 0 || min > 59 { ret err(out_of_range("Invalid minute")); }
+    if sec < 0 || sec > 60 { ret err(out_of_range("Invalid second")); }
+    if nsec < 0_i32 || nsec > 999999999_i32 {
+        ret err(out_of_range("Invalid nanosecond"));
+    }
 
-            if match_str(s, pos, needle) {
-                ret some((value, pos + str::len(needle)));
-            }
-            i += 1u;
-        }
+    let days = days_from_civil(year, month, day);
+    ok({
+        tm_sec: sec as c_int,
+        tm_min: min as c_int,
+        tm_hour: hour as c_int,
+        tm_mday: day as c_int,
+        tm_mon: (month - 1) as c_int,
+        tm_year: (year - 1900) as c_int,
+        tm_wday: weekday_from_days(days) as c_int,
+        tm_yday: (days_before_month(year, month) + day - 1) as c_int,
+        tm_isdst: 0 as c_int,
+        tm_gmtoff: 0 as c_long,
+        tm_zone: "",
+        tm_nsec: nsec,
+    })
+}
 
-        none
+#[doc = "
+Returns the weekday that is `n` days after Sunday, or none if `n` is not
+in the range 0-6.
+"]
+fn weekday_from_days_from_sunday(n: int) -> option<weekday> {
+    alt n {
+      0 { some(sunday) }
+      1 { some(monday) }
+      2 { some(tuesday) }
+      3 { some(wednesday) }
+      4 { some(thursday) }
+      5 { some(friday) }
+      6 { some(saturday) }
+      _ { none }
     }
+}
 
-    fn match_digits(s: str, pos: uint, digits: uint, ws: bool)
-      -> option<(i32, uint)> {
-        let mut pos = pos;
-        let mut value = 0 as c_int;
+impl weekday for weekday {
+    #[doc = "Returns the number of days since Sunday, from 0 to 6."]
+    fn num_days_from_sunday() -> int { self as int }
 
-        let mut i = 0u;
-        while i < digits {
-            let {ch, next} = str::char_range_at(s, pos);
-            pos = next;
+    #[doc = "Returns the following day of the week."]
+    fn succ() -> weekday {
+        option::get(weekday_from_days_from_sunday((self as int + 1) % 7))
+    }
 
-            alt ch {
-              '0' to '9' {
-                value = value * 10 as c_int + (ch as i32 - '0' as i32);
-              }
-              ' ' if ws { }
-              _ { ret none; }
-            }
-            i += 1u;
-        }
+    #[doc = "Returns the preceding day of the week."]
+    fn pred() -> weekday {
+        option::get(weekday_from_days_from_sunday((self as int + 6) % 7))
+    }
+}
 
-        some((value, pos))
+#[doc = "
+Returns the month with the given 1-based number, or none if `n` is not in
+the range 1-12.
+"]
+fn month_from_number(n: int) -> option<month> {
+    alt n {
+      1 { some(january) }
+      2 { some(february) }
+      3 { some(march) }
+      4 { some(april) }
+      5 { some(may) }
+      6 { some(june) }
+      7 { some(july) }
+      8 { some(august) }
+      9 { some(september) }
+      10 { some(october) }
+      11 { some(november) }
+      12 { some(december) }
+      _ { none }
     }
+}
 
-    fn parse_char(s: str, pos: uint, c: char) -> result<uint, str> {
-        let {ch, next} = str::char_range_at(s, pos);
+#[doc = "
+Returns the month for a 0-based `tm_mon` value, or none if it is not in
+the range 0-11.
+"]
+fn month_from_tm_mon(tm_mon: c_int) -> option<month> {
+    month_from_number(tm_mon as int + 1)
+}
 
-        if c == ch {
-            ok(next)
-        } else {
-            err(#fmt("Expected %?, found %?",
-                str::from_char(c),
-                str::from_char(ch)))
-        }
-    }
-
-    fn parse_type(s: str, pos: uint, ch: char, tm: tm_mut)
-      -> result<uint, str> {
-        alt ch {
-          'A' {
-            alt match_strs(s, pos, [
-                ("Sunday", 0 as c_int),
-                ("Monday", 1 as c_int),
-                ("Tuesday", 2 as c_int),
-                ("Wednesday", 3 as c_int),
-                ("Thursday", 4 as c_int),
-                ("Friday", 5 as c_int),
-                ("Saturday", 6 as c_int)
-            ]) {
-              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
-              none { err("Invalid day") }
-            }
-          }
-          'a' {
-            alt match_strs(s, pos, [
-                ("Sun", 0 as c_int),
-                ("Mon", 1 as c_int),
-                ("Tue", 2 as c_int),
-                ("Wed", 3 as c_int),
-                ("Thu", 4 as c_int),
-                ("Fri", 5 as c_int),
-                ("Sat", 6 as c_int)
-            ]) {
-              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
-              none { err("Invalid day") }
-            }
-          }
-          'B' {
-            alt match_strs(s, pos, [
-                ("January", 0 as c_int),
-                ("February", 1 as c_int),
-                ("March", 2 as c_int),
-                ("April", 3 as c_int),
-                ("May", 4 as c_int),
-                ("June", 5 as c_int),
-                ("July", 6 as c_int),
-                ("August", 7 as c_int),
-                ("September", 8 as c_int),
-                ("October", 9 as c_int),
-                ("November", 10 as c_int),
-                ("December", 11 as c_int)
-            ]) {
-              some(item) { let (v, pos) = item; tm.tm_mon = v; ok(pos) }
-              none { err("Invalid month") }
-            }
-          }
-          'b' | 'h' {
-            alt match_strs(s, pos, [
-                ("Jan", 0 as c_int),
-                ("Feb", 1 as c_int),
-                ("Mar", 2 as c_int),
-                ("Apr", 3 as c_int),
-                ("May", 4 as c_int),
-                ("Jun", 5 as c_int),
-                ("Jul", 6 as c_int),
-                ("Aug", 7 as c_int),
-                ("Sep", 8 as c_int),
-                ("Oct", 9 as c_int),
-                ("Nov", 10 as c_int),
-                ("Dec", 11 as c_int)
-            ]) {
-              some(item) { let (v, pos) = item; tm.tm_mon = v; ok(pos) }
-              none { err("Invalid month") }
-            }
-          }
-          'C' {
-            alt match_digits(s, pos, 2u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_year += (v * 100 as c_int) - 1900 as c_int;
-                ok(pos)
-              }
-              none { err("Invalid year") }
-            }
-          }
-          'c' {
-            parse_type(s, pos, 'a', tm)
-                .chain { |pos| parse_char(s, pos, ' ') }
-                .chain { |pos| parse_type(s, pos, 'b', tm) }
-                .chain { |pos| parse_char(s, pos, ' ') }
-                .chain { |pos| parse_type(s, pos, 'e', tm) }
-                .chain { |pos| parse_char(s, pos, ' ') }
-                .chain { |pos| parse_type(s, pos, 'T', tm) }
-                .chain { |pos| parse_char(s, pos, ' ') }
-                .chain { |pos| parse_type(s, pos, 'Y', tm) }
-          }
-          'D' | 'x' {
-            parse_type(s, pos, 'm', tm)
-                .chain { |pos| parse_char(s, pos, '/') }
-                .chain { |pos| parse_type(s, pos, 'd', tm) }
-                .chain { |pos| parse_char(s, pos, '/') }
-                .chain { |pos| parse_type(s, pos, 'y', tm) }
-          }
-          'd' {
-            alt match_digits(s, pos, 2u, false) {
-              some(item) { let (v, pos) = item; tm.tm_mday = v; ok(pos) }
-              none { err("Invalid day of the month") }
-            }
-          }
-          'e' {
-            alt match_digits(s, pos, 2u, true) {
-              some(item) { let (v, pos) = item; tm.tm_mday = v; ok(pos) }
-              none { err("Invalid day of the month") }
-            }
-          }
-          'F' {
-            parse_type(s, pos, 'Y', tm)
-                .chain { |pos| parse_char(s, pos, '-') }
-                .chain { |pos| parse_type(s, pos, 'm', tm) }
-                .chain { |pos| parse_char(s, pos, '-') }
-                .chain { |pos| parse_type(s, pos, 'd', tm) }
-          }
-          'H' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) { let (v, pos) = item; tm.tm_hour = v; ok(pos) }
-              none { err("Invalid hour") }
-            }
-          }
-          'I' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) {
-                  let (v, pos) = item;
-                  tm.tm_hour = if v == 12 as c_int { 0 as c_int } else { v };
-                  ok(pos)
-              }
-              none { err("Invalid hour") }
-            }
-          }
-          'j' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 3u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_yday = v - 1 as c_int;
-                ok(pos)
-              }
-              none { err("Invalid year") }
-            }
-          }
-          'k' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, true) {
-              some(item) { let (v, pos) = item; tm.tm_hour = v; ok(pos) }
-              none { err("Invalid hour") }
-            }
-          }
-          'l' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, true) {
-              some(item) {
-                  let (v, pos) = item;
-                  tm.tm_hour = if v == 12 as c_int { 0 as c_int } else { v };
-                  ok(pos)
-              }
-              none { err("Invalid hour") }
-            }
-          }
-          'M' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) { let (v, pos) = item; tm.tm_min = v; ok(pos) }
-              none { err("Invalid minute") }
-            }
-          }
-          'm' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_mon = v - 1 as c_int;
-                ok(pos)
-              }
-              none { err("Invalid month") }
-            }
-          }
-          'n' { parse_char(s, pos, '\n') }
-          'P' {
-            alt match_strs(s, pos, [("am", 0 as c_int), ("pm", 12 as c_int)]) {
-              some(item) { let (v, pos) = item; tm.tm_hour += v; ok(pos) }
-              none { err("Invalid hour") }
-            }
-          }
-          'p' {
-            alt match_strs(s, pos, [("AM", 0 as c_int), ("PM", 12 as c_int)]) {
-              some(item) { let (v, pos) = item; tm.tm_hour += v; ok(pos) }
-              none { err("Invalid hour") }
-            }
-          }
-          'R' {
-            parse_type(s, pos, 'H', tm)
-                .chain { |pos| parse_char(s, pos, ':') }
-                .chain { |pos| parse_type(s, pos, 'M', tm) }
-          }
-          'r' {
-            parse_type(s, pos, 'I', tm)
-                .chain { |pos| parse_char(s, pos, ':') }
-                .chain { |pos| parse_type(s, pos, 'M', tm) }
-                .chain { |pos| parse_char(s, pos, ':') }
-                .chain { |pos| parse_type(s, pos, 'S', tm) }
-                .chain { |pos| parse_char(s, pos, ' ') }
-                .chain { |pos| parse_type(s, pos, 'p', tm) }
-          }
-          'S' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_sec = v;
-                ok(pos)
-              }
-              none { err("Invalid second") }
-            }
-          }
-          //'s' {}
-          'T' | 'X' {
-            parse_type(s, pos, 'H', tm)
-                .chain { |pos| parse_char(s, pos, ':') }
-                .chain { |pos| parse_type(s, pos, 'M', tm) }
-                .chain { |pos| parse_char(s, pos, ':') }
-                .chain { |pos| parse_type(s, pos, 'S', tm) }
-          }
-          't' { parse_char(s, pos, '\t') }
-          'u' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 1u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_wday = v;
-                ok(pos)
-              }
-              none { err("Invalid weekday") }
-            }
-          }
-          'v' {
-            parse_type(s, pos, 'e', tm)
-                .chain { |pos| parse_char(s, pos, '-') }
-                .chain { |pos| parse_type(s, pos, 'b', tm) }
-                .chain { |pos| parse_char(s, pos, '-') }
-                .chain { |pos| parse_type(s, pos, 'Y', tm) }
-          }
-          //'W' {}
-          'w' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 1u, false) {
-              some(item) { let (v, pos) = item; tm.tm_wday = v; ok(pos) }
-              none { err("Invalid weekday") }
-            }
-          }
-          //'X' {}
-          //'x' {}
-          'Y' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 4u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_year = v - 1900 as c_int;
-                ok(pos)
-              }
-              none { err("Invalid weekday") }
-            }
-          }
-          'y' {
-            // FIXME: range check.
-            alt match_digits(s, pos, 2u, false) {
-              some(item) {
-                let (v, pos) = item;
-                tm.tm_year = v - 1900 as c_int;
-                ok(pos)
-              }
-              none { err("Invalid weekday") }
-            }
-          }
-          'Z' {
-            if match_str(s, pos, "UTC") || match_str(s, pos, "GMT") {
-                tm.tm_gmtoff = 0 as c_long;
-                // FIXME: this should be "UTC"
-                tm.tm_zone = ptr::null();
-                ok(pos + 3u)
-            } else {
-                // It's odd, but to maintain compatibility with c's
-                // strptime we ignore the timezone.
-                let mut pos = pos;
-                let len = str::len(s);
-                while pos < len {
-                    let {ch, next} = str::char_range_at(s, pos);
-                    pos = next;
-                    if ch == ' ' { break; }
-                }
+impl month for month {
+    #[doc = "Returns the 1-based month number, from 1 to 12."]
+    fn number() -> int { self as int }
 
-                ok(pos)
-            }
-          }
-          'z' {
-            let {ch, next} = str::char_range_at(s, pos);
-
-            if ch == '+' || ch == '-' {
-                alt match_digits(s, next, 4u, false) {
-                  some(item) {
-                    let (v, pos) = item;
-                    if v == 0 as c_int {
-                        tm.tm_gmtoff = 0 as c_long;
-                        // FIXME: this should be UTC
-                        tm.tm_zone = ptr::null();
-                    }
+    #[doc = "Returns the 0-based month, as stored in `tm_mon`."]
+    fn to_tm_mon() -> c_int { (self as int - 1) as c_int }
 
-                    ok(pos)
-                  }
-                  none { err("Invalid zone offset") }
-                }
-            } else {
-                err("Invalid zone offset")
-            }
-          }
-          '%' { parse_char(s, pos, '%') }
-          ch {
-            err(#fmt("unknown formatting type: %?", str::from_char(ch)))
-          }
-        }
+    #[doc = "Returns the following month."]
+    fn succ() -> month { option::get(month_from_number(self as int % 12 + 1)) }
+
+    #[doc = "Returns the preceding month."]
+    fn pred() -> month {
+        option::get(month_from_number((self as int + 10) % 12 + 1))
     }
+}
 
-    io::with_str_reader(format) { |rdr|
-        let tm = {
-            mut tm_sec: 0 as c_int,
-            mut tm_min: 0 as c_int,
-            mut tm_hour: 0 as c_int,
-            mut tm_mday: 0 as c_int,
-            mut tm_mon: 0 as c_int,
-            mut tm_year: 0 as c_int,
-            mut tm_wday: 0 as c_int,
-            mut tm_yday: 0 as c_int,
-            mut tm_isdst: 0 as c_int,
-            mut tm_gmtoff: 0 as c_long,
-            mut tm_zone: ptr::null(),
-            mut tm_nsec: 0i32,
-        };
-        let mut pos = 0u;
-        let len = str::len(s);
-        let mut result = err("Invalid time");
-
-        while !rdr.eof() && pos < len {
-            let {ch, next} = str::char_range_at(s, pos);
-
-            alt rdr.read_char() {
-              '%' {
-                alt parse_type(s, pos, rdr.read_char(), tm) {
-                  ok(next) { pos = next; }
-                  err(e) { result = err(e); break; }
-                }
-              }
-              c {
-                if c != ch { break }
-                pos = next;
-              }
-            }
-        }
+#[doc = "
+Creates a date. The day is 1-based. Returns an error if the day is not
+in the month.
+"]
+fn date_from_ymd(year: int, mon: month, day: int) -> result<date, error> {
+    if day < 1 || day > month_length(year, mon.number()) {
+        err(out_of_range("Invalid day of the month"))
+    } else {
+        ok({ year: year, month: mon, day: day })
+    }
+}
 
-        if pos == len && rdr.eof() {
-            ok({
-                tm_sec: tm.tm_sec,
-                tm_min: tm.tm_min,
-                tm_hour: tm.tm_hour,
-                tm_mday: tm.tm_mday,
-                tm_mon: tm.tm_mon,
-                tm_year: tm.tm_year,
-                tm_wday: tm.tm_wday,
-                tm_yday: tm.tm_yday,
-                tm_isdst: tm.tm_isdst,
-                tm_gmtoff: tm.tm_gmtoff,
-                tm_zone: tm.tm_zone,
-                tm_nsec: tm.tm_nsec,
-            })
-        } else { result }
-    }
-}
-
-fn strftime(format: str, tm: tm) -> str {
-    fn parse_type(ch: char, tm: tm) -> str {
-        //FIXME: Implement missing types.
-        alt check ch {
-          'A' {
-            alt check tm.tm_wday as int {
-              0 { "Sunday" }
-              1 { "Monday" }
-              2 { "Tuesday" }
-              3 { "Wednesday" }
-              4 { "Thursday" }
-              5 { "Friday" }
-              6 { "Saturday" }
-            }
-          }
-          'a' {
-            alt check tm.tm_wday as int {
-              0 { "Sun" }
-              1 { "Mon" }
-              2 { "Tue" }
-              3 { "Wed" }
-              4 { "Thu" }
-              5 { "Fri" }
-              6 { "Sat" }
-            }
-          }
-          'B' {
-            alt check tm.tm_mon as int {
-              0 { "January" }
-              1 { "February" }
-              2 { "March" }
-              3 { "April" }
-              4 { "May" }
-              5 { "June" }
-              6 { "July" }
-              7 { "August" }
-              8 { "September" }
-              9 { "October" }
-              10 { "November" }
-              11 { "December" }
-            }
-          }
-          'b' | 'h' {
-            alt check tm.tm_mon as int {
-              0 { "Jan" }
-              1 { "Feb" }
-              2 { "Mar" }
-              3 { "Apr" }
-              4 { "May" }
-              5 { "Jun" }
-              6 { "Jul" }
-              7 { "Aug" }
-              8 { "Sep" }
-              9 { "Oct" }
-              10 { "Nov" }
-              11 { "Dec" }
-            }
-          }
-          'C' { #fmt("%02d", (tm.tm_year as int + 1900) / 100) }
-          'c' {
-            #fmt("%s %s %s %s %s",
-                parse_type('a', tm),
-                parse_type('b', tm),
-                parse_type('e', tm),
-                parse_type('T', tm),
-                parse_type('Y', tm))
-          }
-          'D' | 'x' {
-            #fmt("%s/%s/%s",
-                parse_type('m', tm),
-                parse_type('d', tm),
-                parse_type('y', tm))
-          }
-          'd' { #fmt("%02d", tm.tm_mday as int) }
-          'e' { #fmt("%2d", tm.tm_mday as int) }
-          'F' {
-            #fmt("%s-%s-%s",
-                parse_type('Y', tm),
-                parse_type('m', tm),
-                parse_type('d', tm))
-          }
-          //'G' {}
-          //'g' {}
-          'H' { #fmt("%02d", tm.tm_hour as int) }
-          'I' {
-            let mut h = tm.tm_hour as int;
-            if h == 0 { h = 12 }
-            if h > 12 { h -= 12 }
-            #fmt("%02d", h)
-          }
-          'j' { #fmt("%03d", tm.tm_yday as int + 1) }
-          'k' { #fmt("%2d", tm.tm_hour as int) }
-          'l' {
-            let mut h = tm.tm_hour as int;
-            if h == 0 { h = 12 }
-            if h > 12 { h -= 12 }
-            #fmt("%2d", h)
-          }
-          'M' { #fmt("%02d", tm.tm_min as int) }
-          'm' { #fmt("%02d", tm.tm_mon as int + 1) }
-          'n' { "\n" }
-          'P' { if tm.tm_hour as int < 12 { "am" } else { "pm" } }
-          'p' { if tm.tm_hour as int < 12 { "AM" } else { "PM" } }
-          'R' {
-            #fmt("%s:%s",
-                parse_type('H', tm),
-                parse_type('M', tm))
-          }
-          'r' {
-            #fmt("%s:%s:%s %s",
-                parse_type('I', tm),
-                parse_type('M', tm),
-                parse_type('S', tm),
-                parse_type('p', tm))
-          }
-          'S' { #fmt("%02d", tm.tm_sec as int) }
-          's' { #fmt("%d", tm.to_timespec().sec as int) }
-          'T' | 'X' {
-            #fmt("%s:%s:%s",
-                parse_type('H', tm),
-                parse_type('M', tm),
-                parse_type('S', tm))
-          }
-          't' { "\t" }
-          //'U' {}
-          'u' {
-            let i = tm.tm_wday as int;
-            int::str(if i == 0 { 7 } else { i })
-          }
-          //'V' {}
-          'v' {
-            #fmt("%s-%s-%s",
-                parse_type('e', tm),
-                parse_type('b', tm),
-                parse_type('Y', tm))
-          }
-          //'W' {}
-          'w' { int::str(tm.tm_wday as int) }
-          //'X' {}
-          //'x' {}
-          'Y' { int::str(tm.tm_year as int + 1900) }
-          'y' { #fmt("%02d", (tm.tm_year as int + 1900) % 100) }
-          'Z' {
-            if tm.tm_zone == ptr::null() {
-                ""
-            } else {
-                unsafe { str::unsafe::from_c_str(tm.tm_zone) }
-            }
-          }
-          'z' {
-            let gmtoff = tm.tm_gmtoff as i32;
-            let sign = if gmtoff > 0_i32 { '+' } else { '-' };
-            let mut m = i32::abs(gmtoff) / 60_i32;
-            let h = m / 60_i32;
-            m -= h * 60_i32;
-            #fmt("%c%02d%02d", sign, h as int, m as int)
-          }
-          //'+' {}
-          '%' { "%" }
-        }
+#[doc = "
+Parses a date in ISO 8601's expanded representation, with a sign and a
+year of exactly `digits` digits, see `date::to_iso_expanded`.
+"]
+fn date_from_iso_expanded(s: str, digits: uint) -> result<date, error> {
+    let len = str::len(s);
+    if len != digits + 7u || (s[0] != '+' as u8 && s[0] != '-' as u8) {
+        ret err(parse_error("Invalid year"));
+    }
+    if !str::all(str::slice(s, 1u, digits + 1u), char::is_digit) {
+        ret err(parse_error("Invalid year"));
     }
 
-    let mut buf = "";
+    strptime(s, "%Y-%m-%d").chain { |tm|
+        date_from_ymd(tm.year(), tm.month(), tm.day())
+    }
+}
 
-    io::with_str_reader(format) { |rdr|
-        while !rdr.eof() {
-            alt rdr.read_char() {
-                '%' { buf += parse_type(rdr.read_char(), tm); }
-                ch { str::push_char(buf, ch); }
-            }
-        }
+#[doc = "
+Creates a date from a year and a 1-based day of the year. Returns an
+error if the day is not in the year.
+"]
+fn date_from_ordinal(year: int, ordinal: int) -> result<date, error> {
+    if ordinal < 1 || ordinal > days_in_year(year) {
+        ret err(out_of_range("Invalid day of the year"));
     }
+    let mut m = 1;
+    while days_before_month(year, m + 1) < ordinal { m += 1; }
+    ok({
+        year: year,
+        month: option::get(month_from_number(m)),
+        day: ordinal - days_before_month(year, m)
+    })
+}
 
-    buf
+#[doc = "Returns the last day of the month, e.g. February 29th in 2008."]
+fn last_day_of_month(year: int, mon: month) -> date {
+    { year: year, month: mon, day: days_in_month(year, mon) }
 }
 
-impl tm for tm {
-    #[doc = "Convert time to the seconds from January 1, 1970"]
-    fn to_timespec() -> timespec {
-        let sec = if self.tm_gmtoff == 0 as c_long {
-            libtime::timegm(self) as i64
-        } else {
-            libtime::mktime(self) as i64
-        };
+#[doc = "
+Returns the `n`th occurrence of a weekday in a month, e.g. the third
+Thursday. Negative values count from the end of the month, so -1 is the
+last occurrence. Returns an error if the month has no such occurrence.
+"]
+fn nth_weekday_of_month(year: int, mon: month, wd: weekday, n: int)
+  -> result<date, error> {
+    let len = days_in_month(year, mon);
+    let day = if n > 0 {
+        let first = weekday_from_ymd(year, mon, 1) as int;
+        (wd as int - first + 7) % 7 + 1 + (n - 1) * 7
+    } else if n < 0 {
+        let last = weekday_from_ymd(year, mon, len) as int;
+        len - (last - wd as int + 7) % 7 + (n + 1) * 7
+    } else {
+        ret err(out_of_range("Invalid occurrence"));
+    };
 
-        { sec: sec, nsec: self.tm_nsec }
+    if day < 1 || day > len {
+        err(out_of_range("No such weekday in the month"))
+    } else {
+        ok({ year: year, month: mon, day: day })
     }
+}
 
-    #[doc = "Convert time to the local timezone"]
-    fn to_local() -> tm {
-        at(self.to_timespec())
+#[doc = "Parses a date of the form \"2009-02-13\"."]
+fn date_from_str(s: str) -> result<date, error> {
+    strptime(s, "%F").chain { |tm|
+        date_from_ymd(tm.year(), tm.month(), tm.day())
     }
+}
 
-    #[doc = "Convert time to the UTC"]
-    fn to_utc() -> tm {
-        at_utc(self.to_timespec())
+#[doc = "
+Creates a time of day. Returns an error if a field is out of range. A
+second of 60 is accepted for leap seconds.
+"]
+fn time_of_day_from_hms(hour: int, min: int, sec: int)
+  -> result<time_of_day, error> {
+    time_of_day_from_hms_nsec(hour, min, sec, 0_i32)
+}
+
+#[doc = "
+Creates a time of day. Returns an error if a field is out of range. A
+second of 60 is accepted for leap seconds.
+"]
+fn time_of_day_from_hms_nsec(hour: int, min: int, sec: int, nsec: i32)
+  -> result<time_of_day, error> {
+    if hour < 0 || hour > 23 { ret err(out_of_range("Invalid hour")); }
+    if min < 0 || min > 59 { ret err(out_of_range("Invalid minute")); }
+    if sec < 0 || sec > 60 { ret err(out_of_range("Invalid second")); }
+    if nsec < 0_i32 || nsec > 999999999_i32 {
+        ret err(out_of_range("Invalid nanosecond"));
+    }
+    ok({ hour: hour, minute: min, second: sec, nsec: nsec })
+}
+
+#[doc = "
+Parses a time of day of the form \"23:31:30\", optionally followed by a
+fraction of a second such as \".054321\".
+"]
+fn time_of_day_from_str(s: str) -> result<time_of_day, error> {
+    let len = str::len(s);
+    if len < 8u { ret err(parse_error("Invalid time of day")); }
+
+    strptime(str::slice(s, 0u, 8u), "%T").chain { |tm|
+        let mut nsec = 0_i32;
+        if len > 8u {
+            if s[8] != '.' as u8 || len == 9u || len > 18u {
+                ret err(parse_error("Invalid fractional seconds"));
+            }
+            let mut pos = 9u;
+            while pos < 18u {
+                nsec *= 10_i32;
+                if pos < len {
+                    if s[pos] < '0' as u8 || s[pos] > '9' as u8 {
+                        ret err(parse_error("Invalid fractional seconds"));
+                    }
+                    nsec += (s[pos] - ('0' as u8)) as i32;
+                }
+                pos += 1u;
+            }
+        }
+        time_of_day_from_hms_nsec(tm.hour(), tm.minute(), tm.second(), nsec)
+    }
+}
+
+impl date for date {
+    #[doc = "Formats the date as \"2009-02-13\"."]
+    fn to_str() -> str {
+        #fmt("%s-%02d-%02d", year_str(self.year), self.month.number(),
+             self.day)
     }
 
     #[doc = "
-    Return a string of the current time in the form
-    \"Thu Jan  1 00:00:00 1970\".
+    Formats the date in ISO 8601's expanded representation, with a sign
+    and a year of at least `digits` digits, e.g. \"+10000-01-01\" or
+    \"-0333-02-13\". The number of digits must be agreed by both sides.
     "]
-    fn ctime() -> str { self.strftime("%c") }
+    fn to_iso_expanded(digits: uint) -> str {
+        let sign = if self.year < 0 { "-" } else { "+" };
+        let mut year = int::str(int::abs(self.year));
+        while str::len(year) < digits { year = "0" + year; }
+        #fmt("%s%s-%02d-%02d", sign, year, self.month.number(), self.day)
+    }
 
-    #[doc = "Formats the time according to the format string."]
-    fn strftime(format: str) -> str { strftime(format, self) }
+    #[doc = "Returns the day of the week."]
+    fn weekday() -> weekday {
+        option::get(weekday_from_days_from_sunday(
+            weekday_from_days(self.days())))
+    }
+
+    #[doc = "Returns the 1-based day of the year, from 1 to 366."]
+    fn ordinal() -> int {
+        days_before_month(self.year, self.month.number()) + self.day
+    }
+
+    #[doc = "Returns the date as a (year, 1-based day of the year) pair."]
+    fn to_ordinal() -> (int, int) { (self.year, self.ordinal()) }
+
+    #[doc = "Returns the last day of the date's month."]
+    fn last_day_of_month() -> date {
+        last_day_of_month(self.year, self.month)
+    }
+
+    #[doc = "Returns the quarter of the year, from 1 to 4."]
+    fn quarter() -> int { (self.month.number() - 1) / 3 + 1 }
+
+    #[doc = "Returns the first day of the date's quarter."]
+    fn first_day_of_quarter() -> date {
+        let m = (self.quarter() - 1) * 3 + 1;
+        { year: self.year, month: option::get(month_from_number(m)), day: 1 }
+    }
+
+    #[doc = "Returns the last day of the date's quarter."]
+    fn last_day_of_quarter() -> date {
+        let m = self.quarter() * 3;
+        last_day_of_month(self.year, option::get(month_from_number(m)))
+    }
 
     #[doc = "
-    Returns a time string formatted according to RFC 822.
+    Returns the fiscal year of the date, for a fiscal year starting on the
+    first of `start`. A fiscal year is named after the calendar year in
+    which it ends, so with an October start, 2008-10-01 is in FY2009.
+    "]
+    fn fiscal_year(start: month) -> int {
+        if start != january && self.month.number() >= start.number() {
+            self.year + 1
+        } else {
+            self.year
+        }
+    }
 
-    local: \"Thu, 22 Mar 2012 07:53:18 PST\"
-    utc:   \"Thu, 22 Mar 2012 14:53:18 UTC\"
+    #[doc = "
+    Returns the quarter of the fiscal year, from 1 to 4, for a fiscal
+    year starting on the first of `start`.
     "]
-    fn rfc822() -> str {
-        if self.tm_gmtoff == 0 as c_long {
-            self.strftime("%a, %d %b %Y %T GMT")
+    fn fiscal_quarter(start: month) -> int {
+        (self.month.number() - start.number() + 12) % 12 / 3 + 1
+    }
+
+    #[doc = "
+    Returns the week of the month the date falls in, starting from 1.
+    Under `iso_weeks`, the days before the first Monday-to-Sunday week
+    that contains a Thursday belong to the last week of the previous
+    month, and the days after the last such week belong to week 1 of the
+    next month.
+    "]
+    fn week_of_month(conv: week_convention) -> int {
+        alt conv {
+          sunday_weeks {
+            let first = { day: 1 with self }.weekday() as int;
+            (self.day - 1 + first) / 7 + 1
+          }
+          iso_weeks {
+            let from_monday = (self.weekday() as int + 6) % 7;
+            let thursday = self.days() - (from_monday as i64) + 3_i64;
+            let (_, _, d) = civil_from_days(thursday);
+            (d - 1) / 7 + 1
+          }
+        }
+    }
+
+    #[doc = "
+    Returns the first date strictly after this one that falls on the given
+    weekday, e.g. \"next Monday\".
+    "]
+    fn next(wd: weekday) -> date {
+        let n = (wd as int - self.weekday() as int + 6) % 7 + 1;
+        self.add_days(n as i64)
+    }
+
+    #[doc = "
+    Returns the last date strictly before this one that falls on the given
+    weekday.
+    "]
+    fn prev(wd: weekday) -> date {
+        let n = (self.weekday() as int - wd as int + 6) % 7 + 1;
+        self.add_days(-(n as i64))
+    }
+
+    #[doc = "
+    Returns the date `n` months later, or earlier if `n` is negative. A
+    day past the end of the target month is clamped to its last day.
+    "]
+    fn add_months(n: int) -> date {
+        let m = (self.year * 12 + self.month.number() - 1) as i64 +
+            (n as i64);
+        let year = div_floor(m, 12_i64) as int;
+        let mon = option::get(month_from_number(
+            (m - div_floor(m, 12_i64) * 12_i64) as int + 1));
+        let len = days_in_month(year, mon);
+        {
+            year: year,
+            month: mon,
+            day: if self.day > len { len } else { self.day }
+        }
+    }
+
+    #[doc = "Returns the date `n` days later, or earlier if `n` is negative."]
+    fn add_days(n: i64) -> date {
+        let (y, m, d) = civil_from_days(self.days() + n);
+        { year: y, month: option::get(month_from_number(m)), day: d }
+    }
+
+    #[doc = "
+    Returns the number of days from `other` to this date, which is
+    negative if `other` is later.
+    "]
+    fn days_since(other: date) -> i64 { self.days() - other.days() }
+
+    #[doc = "Combines the date with a time of day into a UTC tm."]
+    fn and_time(t: time_of_day) -> tm {
+        result::get(civil_tm(self.year, self.month.number(), self.day,
+                             t.hour, t.minute, t.second, t.nsec))
+    }
+
+    // The number of days since 1970-01-01.
+    fn days() -> i64 {
+        days_from_civil(self.year, self.month.number(), self.day)
+    }
+}
+
+impl time_of_day for time_of_day {
+    #[doc = "
+    Formats the time as \"23:31:30\", followed by the nanoseconds when
+    they are non-zero.
+    "]
+    fn to_str() -> str {
+        let s = #fmt("%02d:%02d:%02d", self.hour, self.minute, self.second);
+        if self.nsec == 0_i32 {
+            s
         } else {
-            self.strftime("%a, %d %b %Y %T %Z")
+            s + #fmt(".%09d", self.nsec as int)
         }
     }
 
+    #[doc = "Returns the number of seconds since midnight."]
+    fn seconds_from_midnight() -> int {
+        self.hour * 3600 + self.minute * 60 + self.second
+    }
+
     #[doc = "
-    Returns a time string formatted according to RFC 822 with Zulu time.
+    Returns the time `n` seconds later, or earlier if `n` is negative,
+    wrapping around midnight. A leap second is folded into the next
+    minute.
+    "]
+    fn add_seconds(n: i64) -> time_of_day {
+        let secs = (self.seconds_from_midnight() as i64) + n;
+        let secs = (secs - div_floor(secs, 86400_i64) * 86400_i64) as int;
+        {
+            hour: secs / 3600,
+            minute: secs % 3600 / 60,
+            second: secs % 60,
+            nsec: self.nsec
+        }
+    }
 
-    local: \"Thu, 22 Mar 2012 07:53:18 -0700\"
-    utc:   \"Thu, 22 Mar 2012 14:53:18 -0000\"
+    #[doc = "Combines the time of day with a date into a UTC tm."]
+    fn on(d: date) -> tm { d.and_time(self) }
+}
+
+#[doc = "Returns the datetime of the specified time in the given zone."]
+fn datetime_at(clock: timespec, z: zone) -> datetime {
+    let gmtoff = alt z {
+      zone_utc { 0_i32 }
+      zone_local { at(clock).tm_gmtoff as i32 }
+      zone_offset(off) { off }
+    };
+    datetime_at_gmtoff(clock, gmtoff)
+}
+
+#[doc = "Returns the current datetime in the given zone."]
+fn datetime_now(z: zone) -> datetime {
+    datetime_at(get_time(), z)
+}
+
+#[doc = "
+Converts a tm into a datetime with the same fields and offset. Returns
+an error if a field is out of range.
+"]
+fn datetime_from_tm(tm: tm) -> result<datetime, error> {
+    civil_tm(tm.year(), tm.tm_mon as int + 1, tm.day(), tm.hour(),
+             tm.minute(), tm.second(), tm.tm_nsec).chain { |valid|
+        ok({
+            date: valid.date(),
+            time: valid.time_of_day(),
+            gmtoff: tm.tm_gmtoff as i32
+        })
+    }
+}
+
+#[doc = "
+Parses an RFC 3339 datetime such as \"2009-02-13T15:31:30-08:00\",
+keeping the offset it was written with.
+"]
+fn datetime_from_str(s: str) -> result<datetime, error> {
+    parse_rfc3339(s).chain { |tm| datetime_from_tm(tm) }
+}
+
+// Computes the datetime of a timespec at a fixed offset, without libc.
+fn datetime_at_gmtoff(clock: timespec, gmtoff: i32) -> datetime {
+    let secs = clock.sec + (gmtoff as i64);
+    let days = div_floor(secs, 86400_i64);
+    let (y, m, d) = civil_from_days(days);
+    let sod = (secs - days * 86400_i64) as int;
+    {
+        date: { year: y, month: option::get(month_from_number(m)), day: d },
+        time: {
+            hour: sod / 3600,
+            minute: sod % 3600 / 60,
+            second: sod % 60,
+            nsec: clock.nsec
+        },
+        gmtoff: gmtoff
+    }
+}
+
+impl datetime for datetime {
+    #[doc = "Returns the instant this datetime represents."]
+    fn to_timespec() -> timespec {
+        let days = self.date.days();
+        let secs = self.time.seconds_from_midnight() as i64;
+        {
+            sec: days * 86400_i64 + secs - (self.gmtoff as i64),
+            nsec: self.time.nsec
+        }
+    }
+
+    #[doc = "
+    Converts the datetime into a tm at the same offset, with the weekday
+    and day of the year filled in.
     "]
-    fn rfc822z() -> str {
-        self.strftime("%a, %d %b %Y %T %z")
+    fn to_tm() -> tm { at_gmtoff(self.to_timespec(), self.gmtoff as c_long) }
+
+    #[doc = "Returns the same instant expressed in another zone."]
+    fn in_zone(z: zone) -> datetime { datetime_at(self.to_timespec(), z) }
+
+    #[doc = "
+    Returns the datetime `n` seconds later, or earlier if `n` is negative,
+    at the same offset.
+    "]
+    fn add_seconds(n: i64) -> datetime {
+        let ts = self.to_timespec();
+        datetime_at_gmtoff({ sec: ts.sec + n with ts }, self.gmtoff)
+    }
+
+    #[doc = "
+    Returns the datetime with the same time of day `n` calendar days
+    later, or earlier if `n` is negative, at the same offset.
+    "]
+    fn add_days(n: i64) -> datetime {
+        { date: self.date.add_days(n) with self }
+    }
+
+    #[doc = "Formats the datetime according to RFC 3339."]
+    fn to_str() -> str { rfc3339_nsec(self.to_tm()) }
+}
+
+#[doc = "Returns the specified time as a `utc_tm`"]
+fn at_utc_tm(clock: timespec) -> utc_tm { utc_tm(at_utc(clock)) }
+
+#[doc = "Returns the specified time as a `local_tm`"]
+fn at_local_tm(clock: timespec) -> local_tm { local_tm(at(clock)) }
+
+impl utc_tm for utc_tm {
+    #[doc = "Returns the underlying tm."]
+    fn tm() -> tm { *self }
+
+    #[doc = "Convert time to the seconds from January 1, 1970"]
+    fn to_timespec() -> timespec { (*self).to_timespec() }
+
+    #[doc = "Convert time to the local timezone"]
+    fn to_local() -> local_tm { at_local_tm(self.to_timespec()) }
+
+    #[doc = "Formats the time according to the format string."]
+    fn strftime(format: str) -> str { strftime(format, *self) }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 822, e.g.
+    \"Thu, 22 Mar 2012 14:53:18 GMT\".
+    "]
+    fn rfc822() -> str { format_rfc822(*self, true, false) }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 3339, e.g.
+    \"2012-02-22T14:53:18Z\".
+    "]
+    fn rfc3339() -> str { format_rfc3339(*self, 'Z') }
+}
+
+impl local_tm for local_tm {
+    #[doc = "Returns the underlying tm."]
+    fn tm() -> tm { *self }
+
+    #[doc = "Convert time to the seconds from January 1, 1970"]
+    fn to_timespec() -> timespec { (*self).to_timespec() }
+
+    #[doc = "Convert time to UTC"]
+    fn to_utc() -> utc_tm { at_utc_tm(self.to_timespec()) }
+
+    #[doc = "Formats the time according to the format string."]
+    fn strftime(format: str) -> str { strftime(format, *self) }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 822, e.g.
+    \"Thu, 22 Mar 2012 07:53:18 PST\". The zone abbreviation is used even
+    when the local zone is at a zero offset from UTC.
+    "]
+    fn rfc822() -> str { format_rfc822(*self, false, false) }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 3339, e.g.
+    \"2012-02-22T07:53:18-07:00\". A zero offset is written as \"+00:00\".
+    "]
+    fn rfc3339() -> str { format_rfc3339(*self, '+') }
+}
+
+impl error for error {
+    #[doc = "Returns the message describing the error."]
+    fn to_str() -> str {
+        alt self {
+          parse_error(msg) { msg }
+          out_of_range(msg) { msg }
+          unknown_zone(name) { #fmt("Unknown zone %s", name) }
+          overflow { "Overflow" }
+          platform_error(msg) { msg }
+        }
+    }
+}
+
+#[doc = "Returns true if the year is a leap year in the Gregorian calendar."]
+fn is_leap_year(year: int) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+#[doc = "Returns the number of days in the month of the given year."]
+fn days_in_month(year: int, mon: month) -> int {
+    month_length(year, mon.number())
+}
+
+#[doc = "Returns the number of days in the year, 365 or 366."]
+fn days_in_year(year: int) -> int { days_before_month(year, 13) }
+
+#[doc = "
+Returns the day of the week of a date in the proleptic Gregorian
+calendar, computed without consulting libc.
+"]
+fn weekday_from_ymd(year: int, mon: month, day: int) -> weekday {
+    // Sakamoto's method.
+    let t = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let m = mon.number();
+    let y = (if m < 3 { year - 1 } else { year }) as i64;
+    let w = y + div_floor(y, 4_i64) - div_floor(y, 100_i64) +
+        div_floor(y, 400_i64) + (t[m - 1] + day) as i64;
+    option::get(weekday_from_days_from_sunday(
+        (w - div_floor(w, 7_i64) * 7_i64) as int))
+}
+
+#[doc = "
+Returns the calendar difference from `a` to `b`, such as 34 years, 2
+months and 5 days, as used for ages. A month is only counted once its day
+of the month is reached, so January 31st to February 28th 2009 is 0
+months and 28 days. The remaining days are counted from the day that
+many whole months after `a`, clamped to the last day of a shorter month,
+so January 31st to March 1st 2009 is 1 month and 1 day. If `b` is before
+`a`, every component is negative.
+"]
+fn calendar_diff(a: date, b: date) -> period {
+    if b.days() < a.days() {
+        let p = calendar_diff(b, a);
+        ret { years: -p.years, months: -p.months, days: -p.days };
+    }
+
+    let mut months = (b.year - a.year) * 12 +
+        (b.month.number() - a.month.number());
+    if b.day < a.day { months -= 1; }
+    let days = b.days_since(a.add_months(months)) as int;
+    { years: months / 12, months: months % 12, days: days }
+}
+
+#[doc = "
+Returns the number of months from `b` to `a` with Oracle's MONTHS_BETWEEN
+semantics: positive when `a` is later, an integer when both fall on the
+same day of the month or both on the last day of their months, and
+otherwise a fraction based on a 31-day month, including the difference in
+time of day. The civil fields are used as they are, ignoring the zone.
+"]
+fn months_between(a: tm, b: tm) -> f64 {
+    let months = ((a.year() - b.year()) * 12 + (a.month() as int) -
+                  (b.month() as int)) as f64;
+    let a_last = a.day() == days_in_month(a.year(), a.month());
+    let b_last = b.day() == days_in_month(b.year(), b.month());
+    if a.day() == b.day() || (a_last && b_last) {
+        ret months;
+    }
+
+    let secs = (a.time_of_day().seconds_from_midnight() -
+                b.time_of_day().seconds_from_midnight()) as f64;
+    months + (((a.day() - b.day()) as f64) + secs / 86400.0) / 31.0
+}
+
+#[doc = "
+Returns the number of whole months from `b` to `a`, which is positive
+when `a` is later and truncated towards zero, consistent with
+`calendar_diff(b, a)`.
+"]
+fn whole_months_between(a: date, b: date) -> int {
+    let p = calendar_diff(b, a);
+    p.years * 12 + p.months
+}
+
+impl period for period {
+    #[doc = "Formats the period as e.g. \"34 years, 2 months, 5 days\"."]
+    fn to_str() -> str {
+        fn unit(n: int, name: str) -> str {
+            #fmt("%d %s%s", n, name, if n == 1 || n == -1 { "" } else { "s" })
+        }
+
+        let mut parts = [];
+        if self.years != 0 { parts += [unit(self.years, "year")]; }
+        if self.months != 0 { parts += [unit(self.months, "month")]; }
+        if self.days != 0 || vec::is_empty(parts) {
+            parts += [unit(self.days, "day")];
+        }
+        str::connect(parts, ", ")
+    }
+}
+
+#[doc = "Creates an empty holiday calendar."]
+fn holiday_calendar() -> holiday_calendar { @{mut holidays: []} }
+
+impl holiday_rule for holiday_rule {
+    #[doc = "
+    Returns the date the rule gives in the year, or none if it gives no
+    date that year.
+    "]
+    fn date_in(year: int) -> option<date> {
+        alt self {
+          fixed_date(mon, day) {
+            alt date_from_ymd(year, mon, day) {
+              ok(d) { some(d) }
+              err(_) { none }
+            }
+          }
+          nth_weekday(mon, wd, n) {
+            alt nth_weekday_of_month(year, mon, wd, n) {
+              ok(d) { some(d) }
+              err(_) { none }
+            }
+          }
+          easter_relative(n) {
+            some(gregorian_easter(year).add_days(n as i64))
+          }
+          one_off(d) { if d.year == year { some(d) } else { none } }
+        }
+    }
+}
+
+impl holiday_calendar for holiday_calendar {
+    #[doc = "Adds a holiday to the calendar."]
+    fn add(name: str, rule: holiday_rule) {
+        self.holidays += [{ name: name, rule: rule }];
+    }
+
+    #[doc = "Returns the name of the first holiday falling on the date."]
+    fn holiday_on(d: date) -> option<str> {
+        for vec::each(self.holidays) { |h|
+            if h.rule.date_in(d.year) == some(d) {
+                ret some(h.name);
+            }
+        }
+        none
+    }
+
+    #[doc = "Returns true if a holiday falls on the date."]
+    fn is_holiday_date(d: date) -> bool { self.holiday_on(d) != none }
+
+    #[doc = "
+    Returns true if a holiday falls on the time's date, as expressed in
+    the time's own zone.
+    "]
+    fn is_holiday(t: tm) -> bool { self.is_holiday_date(t.date()) }
+
+    #[doc = "
+    Returns true if the date is a Monday to Friday that is not a
+    holiday.
+    "]
+    fn is_business_day(d: date) -> bool {
+        let wd = d.weekday();
+        wd != saturday && wd != sunday && !self.is_holiday_date(d)
+    }
+
+    #[doc = "Returns the holidays in a year, in date order."]
+    fn holidays_in(year: int) -> [(date, str)] {
+        let mut days = [];
+        for vec::each(self.holidays) { |h|
+            alt h.rule.date_in(year) {
+              some(d) { days += [(d, h.name)]; }
+              none { }
+            }
+        }
+        std::sort::merge_sort({ |a, b|
+            let (da, _) = a;
+            let (db, _) = b;
+            da.days() <= db.days()
+        }, days)
+    }
+}
+
+#[doc = "
+Returns the date of Easter Sunday in the Gregorian calendar, as observed
+by Western churches.
+"]
+fn gregorian_easter(year: int) -> date {
+    // The anonymous Gregorian algorithm.
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    { year: year, month: option::get(month_from_number(month)), day: day }
+}
+
+#[doc = "
+Returns the date of Easter Sunday computed in the Julian calendar, as
+observed by most Eastern Orthodox churches. The result is converted to
+the (proleptic) Gregorian calendar, like every other `date`.
+"]
+fn julian_easter(year: int) -> date {
+    // Meeus's Julian algorithm.
+    let a = year % 4;
+    let b = year % 7;
+    let c = year % 19;
+    let d = (19 * c + 15) % 30;
+    let e = (2 * a + 4 * b - d + 34) % 7;
+    let month = (d + e + 114) / 31;
+    let day = (d + e + 114) % 31 + 1;
+    let (y, m, d) = civil_from_days(days_from_julian(year, month, day));
+    { year: y, month: option::get(month_from_number(m)), day: d }
+}
+
+// The names of the days of the week, from Sunday, and of the months,
+// from January, shared by strftime and strptime, which index them
+// directly. The abbreviated names are the first three letters.
+fn day_names() -> [str] {
+    ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday",
+     "Saturday"]
+}
+
+fn month_names() -> [str] {
+    ["January", "February", "March", "April", "May", "June", "July",
+     "August", "September", "October", "November", "December"]
+}
+
+fn meridiems() -> [str] { ["AM", "PM"] }
+
+fn meridiems_lower() -> [str] { ["am", "pm"] }
+
+impl tm for tm {
+    #[doc = "
+    Convert time to the seconds from January 1, 1970. The fields are read
+    at the tm's offset from UTC, `tm_gmtoff`, whatever the local zone is.
+    Fields outside of their usual ranges are carried, like timegm, so a
+    tm_mon of 12 is January of the next year. Fails if the result is
+    outside of the range of a tm, see `checked_to_timespec`.
+    "]
+    fn to_timespec() -> timespec {
+        alt self.checked_to_timespec() {
+          ok(t) { t }
+          err(e) { fail e.to_str(); }
+        }
+    }
+
+    #[doc = "
+    Convert time to the seconds from January 1, 1970, like `to_timespec`,
+    or return an overflow error if the result, once the fields have been
+    carried, is outside of the range of times a tm can hold (see
+    `try_at_utc`).
+    "]
+    fn checked_to_timespec() -> result<timespec, error> {
+        let mon = self.tm_mon as i64;
+        let year = (self.tm_year as i64) + 1900_i64 + div_floor(mon, 12_i64);
+        if year < tm_min_year || year > tm_max_year {
+            ret err(overflow);
+        }
+
+        // The recorded offset is used rather than mktime, which would
+        // read the fields in the local zone whatever zone they came from.
+        let sec = civil_seconds(self) - (self.tm_gmtoff as i64);
+        let (sec, nsec) = carry_nanos(sec, self.tm_nsec as i64);
+        if sec < tm_min_sec || sec > tm_max_sec {
+            err(overflow)
+        } else {
+            ok({ sec: sec, nsec: nsec })
+        }
     }
 
-    #[doc = "
-    Returns a time string formatted according to ISO 8601.
+    #[doc = "Convert time to the local timezone"]
+    fn to_local() -> tm {
+        at(self.to_timespec())
+    }
+
+    #[doc = "Convert time to the UTC"]
+    fn to_utc() -> tm {
+        at_utc(self.to_timespec())
+    }
+
+    #[doc = "
+    Return a string of the current time in the form
+    \"Thu Jan  1 00:00:00 1970\".
+    "]
+    fn ctime() -> str { self.strftime("%c") }
+
+    #[doc = "Formats the time according to the format string."]
+    fn strftime(format: str) -> str { strftime(format, self) }
+
+    #[doc = "
+    Formats the time according to the format string, using the given era
+    for %EC, %Ey and %EY.
+    "]
+    fn strftime_with_era(format: str, sys: era_system) -> str {
+        strftime_with_era(format, self, sys)
+    }
+
+    #[doc = "
+    Formats the time according to the format string, writing %x and %X
+    in the locale's formats.
+    "]
+    fn strftime_with_locale(format: str, loc: locale) -> str {
+        strftime_with_locale(format, self, loc)
+    }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 822.
+
+    local: \"Thu, 22 Mar 2012 07:53:18 PST\"
+    utc:   \"Thu, 22 Mar 2012 14:53:18 UTC\"
+    "]
+    fn rfc822() -> str {
+        format_rfc822(self, true, false)
+    }
+
+    #[doc = "
+    Returns a time string formatted according to RFC 822 with Zulu time.
+
+    local: \"Thu, 22 Mar 2012 07:53:18 -0700\"
+    utc:   \"Thu, 22 Mar 2012 14:53:18 -0000\"
+    "]
+    fn rfc822z() -> str {
+        format_rfc822(self, false, true)
+    }
+
+    #[doc = "
+    Returns a time string formatted for an RFC 5322 Date header, with the
+    weekday worked out from the date and a numeric offset, so that a
+    hand-built tm gives a valid header.
+
+    local: \"Thu, 22 Mar 2012 07:53:18 -0700\"
+    utc:   \"Thu, 22 Mar 2012 14:53:18 +0000\"
+    "]
+    fn rfc5322() -> str {
+        format_rfc5322(self)
+    }
+
+    #[doc = "
+    Returns the time in UTC as a Netscape cookie Expires date, e.g.
+    \"Thu, 22-Mar-2012 14:53:18 GMT\", for a Set-Cookie header.
+    "]
+    fn cookie_expires() -> str {
+        format_cookie_expires(at_utc(self.to_timespec()))
+    }
+
+    #[doc = "
+    Returns a time string formatted according to ISO 8601.
+
+    local: \"2012-02-22T07:53:18-07:00\"
+    utc:   \"2012-02-22T14:53:18Z\"
+    "]
+    fn rfc3339() -> str {
+        format_rfc3339(self, 'Z')
+    }
+
+    #[doc = "
+    Converts the time to JSON. In the RFC 3339 form the offset is kept and
+    fractional seconds are written when `tm_nsec` is non-zero; in the
+    object form the time is stored as seconds and nanoseconds.
+    "]
+    fn to_json(fmt: json_format) -> json::json {
+        alt fmt {
+          json_rfc3339 { json::string(rfc3339_nsec(self)) }
+          json_sec_nsec { self.to_timespec().to_json(json_sec_nsec) }
+        }
+    }
+
+    #[doc = "
+    Encodes the time as 16 bytes: the 12-byte encoding of its timespec
+    followed by the offset from UTC in seconds as a big-endian i32.
+    "]
+    fn to_bytes() -> [u8] {
+        let mut buf = self.to_timespec().to_bytes();
+        write_be(buf, self.tm_gmtoff as u64, 4u);
+        buf
+    }
+
+    #[doc = "
+    Returns true if both times represent the same instant, regardless of
+    the zone they are expressed in.
+    "]
+    fn same_instant(other: tm) -> bool {
+        self.to_timespec() == other.to_timespec()
+    }
+
+    #[doc = "
+    Returns true if both times have the same calendar date and wall-clock
+    time, down to the nanosecond. The derived fields, the DST flag and the
+    zone are ignored, so 15:31 PST and 15:31 UTC are the same civil time.
+    "]
+    fn same_civil(other: tm) -> bool {
+        self.tm_year == other.tm_year &&
+        self.tm_mon == other.tm_mon &&
+        self.tm_mday == other.tm_mday &&
+        self.tm_hour == other.tm_hour &&
+        self.tm_min == other.tm_min &&
+        self.tm_sec == other.tm_sec &&
+        self.tm_nsec == other.tm_nsec
+    }
+
+    #[doc = "
+    Returns true if both times fall on the same calendar day when
+    expressed in the given zone.
+    "]
+    fn same_day(other: tm, z: zone) -> bool {
+        let a = at_zone(self.to_timespec(), z);
+        let b = at_zone(other.to_timespec(), z);
+        a.tm_year == b.tm_year && a.tm_yday == b.tm_yday
+    }
+
+    #[doc = "
+    Returns true if both times fall in the same Sunday-to-Saturday week
+    when expressed in the given zone.
+    "]
+    fn same_week(other: tm, z: zone) -> bool {
+        fn week(t: tm, z: zone) -> i64 {
+            let clock = t.to_timespec();
+            let gmtoff = at_zone(clock, z).tm_gmtoff as i64;
+            let day = div_floor(clock.sec + gmtoff, 86400_i64);
+            // 1970-01-01 was a Thursday, four days after a Sunday.
+            div_floor(day + 4_i64, 7_i64)
+        }
+        week(self, z) == week(other, z)
+    }
+
+    #[doc = "
+    Returns true if both times fall in the same calendar month when
+    expressed in the given zone.
+    "]
+    fn same_month(other: tm, z: zone) -> bool {
+        let a = at_zone(self.to_timespec(), z);
+        let b = at_zone(other.to_timespec(), z);
+        a.tm_year == b.tm_year && a.tm_mon == b.tm_mon
+    }
+
+    fn lt(other: tm) -> bool { cmp_tm(self, other) < 0 }
+    fn le(other: tm) -> bool { cmp_tm(self, other) <= 0 }
+    fn gt(other: tm) -> bool { cmp_tm(self, other) > 0 }
+    fn ge(other: tm) -> bool { cmp_tm(self, other) >= 0 }
+
+    #[doc = "Returns the full year, e.g. 2009."]
+    fn year() -> int { self.tm_year as int + 1900 }
+
+    #[doc = "
+    Returns the month. Fails if `tm_mon` is not in the range 0-11.
+    "]
+    fn month() -> month {
+        alt month_from_tm_mon(self.tm_mon) {
+          some(m) { m }
+          none { fail #fmt("Invalid month: %d", self.tm_mon as int) }
+        }
+    }
+
+    #[doc = "Returns the day of the month, from 1 to 31."]
+    fn day() -> int { self.tm_mday as int }
+
+    #[doc = "Returns the hour, from 0 to 23."]
+    fn hour() -> int { self.tm_hour as int }
+
+    #[doc = "Returns the minute, from 0 to 59."]
+    fn minute() -> int { self.tm_min as int }
+
+    #[doc = "Returns the second, from 0 to 60."]
+    fn second() -> int { self.tm_sec as int }
+
+    #[doc = "Returns the nanosecond within the second."]
+    fn nanosecond() -> i32 { self.tm_nsec }
+
+    #[doc = "Returns the calendar date, ignoring the time of day."]
+    fn date() -> date {
+        { year: self.year(), month: self.month(), day: self.day() }
+    }
+
+    #[doc = "
+    Returns the tm with `tm_wday` and `tm_yday` recomputed from its year,
+    month and day, as after setting them by hand. A month or day outside
+    of its usual range is carried, as by `to_timespec`. `strptime` and
+    `strftime` do this themselves for a valid date.
+    "]
+    fn fill_derived() -> tm {
+        let midnight = { tm_hour: 0 as c_int, tm_min: 0 as c_int,
+                         tm_sec: 0 as c_int with self };
+        let days = div_floor(civil_seconds(midnight), 86400_i64);
+        let (year, _, _) = civil_from_days(days);
+        {
+            tm_wday: weekday_from_days(days) as c_int,
+            tm_yday: (days - days_from_civil(year, 1, 1)) as c_int
+            with self
+        }
+    }
+
+    #[doc = "
+    Returns the last day of the time's month. See also `end_of_month`,
+    which returns the last instant of the month in a zone.
+    "]
+    fn last_day_of_month() -> date { self.date().last_day_of_month() }
+
+    #[doc = "Returns the quarter of the year, from 1 to 4."]
+    fn quarter() -> int { self.date().quarter() }
+
+    #[doc = "Returns the first day of the time's quarter."]
+    fn first_day_of_quarter() -> date { self.date().first_day_of_quarter() }
+
+    #[doc = "Returns the last day of the time's quarter."]
+    fn last_day_of_quarter() -> date { self.date().last_day_of_quarter() }
+
+    #[doc = "
+    Returns the fiscal year for a fiscal year starting on the first of
+    `start`. See `date::fiscal_year`.
+    "]
+    fn fiscal_year(start: month) -> int { self.date().fiscal_year(start) }
+
+    #[doc = "
+    Returns the fiscal quarter for a fiscal year starting on the first of
+    `start`.
+    "]
+    fn fiscal_quarter(start: month) -> int {
+        self.date().fiscal_quarter(start)
+    }
+
+    #[doc = "
+    Returns the week of the month, starting from 1. See
+    `date::week_of_month`.
+    "]
+    fn week_of_month(conv: week_convention) -> int {
+        self.date().week_of_month(conv)
+    }
+
+    #[doc = "
+    Returns the first date strictly after this time's date that falls on
+    the given weekday.
+    "]
+    fn next(wd: weekday) -> date { self.date().next(wd) }
+
+    #[doc = "
+    Returns the last date strictly before this time's date that falls on
+    the given weekday.
+    "]
+    fn prev(wd: weekday) -> date { self.date().prev(wd) }
+
+    #[doc = "Returns midnight at the start of the time's day in the zone."]
+    fn start_of_day(z: zone) -> tm {
+        let d = self.date_in(z);
+        at_zone(midnight_in_zone(d, z), z)
+    }
+
+    #[doc = "
+    Returns midnight at the start of the time's Sunday-to-Saturday week in
+    the zone.
+    "]
+    fn start_of_week(z: zone) -> tm {
+        let d = self.date_in(z);
+        let d = d.add_days(-(d.weekday() as i64));
+        at_zone(midnight_in_zone(d, z), z)
+    }
+
+    #[doc = "Returns midnight on the first of the time's month in the zone."]
+    fn start_of_month(z: zone) -> tm {
+        let d = { day: 1 with self.date_in(z) };
+        at_zone(midnight_in_zone(d, z), z)
+    }
+
+    #[doc = "Returns midnight on January 1st of the time's year in the zone."]
+    fn start_of_year(z: zone) -> tm {
+        let d = { month: january, day: 1 with self.date_in(z) };
+        at_zone(midnight_in_zone(d, z), z)
+    }
+
+    #[doc = "
+    Returns the last nanosecond of the time's day in the zone, which is
+    one nanosecond before the start of the next day.
+    "]
+    fn end_of_day(z: zone) -> tm {
+        let d = self.date_in(z).add_days(1_i64);
+        at_zone(before(midnight_in_zone(d, z)), z)
+    }
+
+    #[doc = "Returns the last nanosecond of the time's week in the zone."]
+    fn end_of_week(z: zone) -> tm {
+        let d = self.date_in(z);
+        let d = d.add_days(7_i64 - (d.weekday() as i64));
+        at_zone(before(midnight_in_zone(d, z)), z)
+    }
+
+    #[doc = "Returns the last nanosecond of the time's month in the zone."]
+    fn end_of_month(z: zone) -> tm {
+        let d = self.date_in(z).last_day_of_month().add_days(1_i64);
+        at_zone(before(midnight_in_zone(d, z)), z)
+    }
+
+    #[doc = "Returns the last nanosecond of the time's year in the zone."]
+    fn end_of_year(z: zone) -> tm {
+        let d = { month: december with self.date_in(z) };
+        let d = d.last_day_of_month().add_days(1_i64);
+        at_zone(before(midnight_in_zone(d, z)), z)
+    }
+
+    #[doc = "
+    Truncates the time to the start of the unit, zeroing the lower
+    fields. Seconds, minutes and hours are truncated in the time's own
+    zone; days, months and years start at midnight in the given zone.
+    "]
+    fn truncate(u: time_unit, z: zone) -> tm {
+        alt u {
+          unit_second { { tm_nsec: 0_i32 with self } }
+          unit_minute { { tm_sec: 0 as c_int, tm_nsec: 0_i32 with self } }
+          unit_hour {
+            {
+                tm_min: 0 as c_int,
+                tm_sec: 0 as c_int,
+                tm_nsec: 0_i32
+                with self
+            }
+          }
+          unit_day { self.start_of_day(z) }
+          unit_month { self.start_of_month(z) }
+          unit_year { self.start_of_year(z) }
+        }
+    }
+
+    #[doc = "
+    Describes the time relative to `now` for display, e.g. \"just now\",
+    \"3 hours ago\" or \"in 2 days\". Times 30 days or more away are
+    formatted as dates instead, like \"2009-02-13\".
+    "]
+    fn humanize_relative_to(now: tm) -> str {
+        self.humanize_relative_to_with(now, duration_days(30_i64), "%F")
+    }
+
+    #[doc = "
+    Describes the time relative to `now` like `humanize_relative_to`,
+    falling back to formatting the time with `format` when it is at least
+    `threshold` away from `now`.
+    "]
+    fn humanize_relative_to_with(now: tm, threshold: duration, format: str)
+      -> str {
+        let d = self.to_timespec().since(now.to_timespec());
+        let future = d.is_positive();
+        let secs = if future { d.secs } else { d.neg().secs };
+
+        if secs >= threshold.secs {
+            ret self.strftime(format);
+        }
+
+        let (n, unit) = if secs < 60_i64 {
+            ret "just now";
+        } else if secs < 3600_i64 {
+            (secs / 60_i64, "minute")
+        } else if secs < 86400_i64 {
+            (secs / 3600_i64, "hour")
+        } else {
+            (secs / 86400_i64, "day")
+        };
+
+        let amount = #fmt("%d %s%s", n as int, unit,
+                          if n == 1_i64 { "" } else { "s" });
+        if future { "in " + amount } else { amount + " ago" }
+    }
+
+    // The calendar date of this instant in the zone.
+    fn date_in(z: zone) -> date { at_zone(self.to_timespec(), z).date() }
+
+    #[doc = "Returns the wall-clock time of day, ignoring the date."]
+    fn time_of_day() -> time_of_day {
+        {
+            hour: self.hour(),
+            minute: self.minute(),
+            second: self.second(),
+            nsec: self.tm_nsec
+        }
+    }
+
+    #[doc = "
+    Returns the day of the week. Fails if `tm_wday` is not in the range
+    0-6.
+    "]
+    fn weekday() -> weekday {
+        alt weekday_from_days_from_sunday(self.tm_wday as int) {
+          some(d) { d }
+          none { fail #fmt("Invalid weekday: %d", self.tm_wday as int) }
+        }
+    }
+
+    #[doc = "
+    Returns a copy with the year (e.g. 2009) replaced. The offset and zone
+    are kept as they are. Returns an error if the result is invalid, e.g.
+    February 29th in a common year.
+    "]
+    fn with_year(year: int) -> result<tm, error> {
+        self.with_fields(year, self.month_num(), self.day(), self.hour(),
+                         self.minute(), self.second(), self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the month replaced."]
+    fn with_month(mon: month) -> result<tm, error> {
+        self.with_fields(self.year(), mon.number(), self.day(), self.hour(),
+                         self.minute(), self.second(), self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the 1-based day of the month replaced."]
+    fn with_day(day: int) -> result<tm, error> {
+        self.with_fields(self.year(), self.month_num(), day, self.hour(),
+                         self.minute(), self.second(), self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the hour (0-23) replaced."]
+    fn with_hour(hour: int) -> result<tm, error> {
+        self.with_fields(self.year(), self.month_num(), self.day(), hour,
+                         self.minute(), self.second(), self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the minute (0-59) replaced."]
+    fn with_minute(min: int) -> result<tm, error> {
+        self.with_fields(self.year(), self.month_num(), self.day(),
+                         self.hour(), min, self.second(), self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the second (0-60) replaced."]
+    fn with_second(sec: int) -> result<tm, error> {
+        self.with_fields(self.year(), self.month_num(), self.day(),
+                         self.hour(), self.minute(), sec, self.tm_nsec)
+    }
+
+    #[doc = "Returns a copy with the nanosecond replaced."]
+    fn with_nanosecond(nsec: i32) -> result<tm, error> {
+        self.with_fields(self.year(), self.month_num(), self.day(),
+                         self.hour(), self.minute(), self.second(), nsec)
+    }
+
+    // The 1-based month, as with_fields takes it.
+    fn month_num() -> int { self.tm_mon as int + 1 }
+
+    // Validates the fields and recomputes the derived ones, keeping the
+    // DST flag, offset and zone of this time.
+    fn with_fields(year: int, month: int, day: int, hour: int, min: int,
+                   sec: int, nsec: i32) -> result<tm, error> {
+        civil_tm(year, month, day, hour, min, sec, nsec).chain { |tm|
+            ok({
+                tm_isdst: self.tm_isdst,
+                tm_gmtoff: self.tm_gmtoff,
+                tm_zone: self.tm_zone
+                with tm
+            })
+        }
+    }
+
+    #[doc = "
+    Returns the time with out-of-range fields carried into the higher
+    ones, e.g. a `tm_sec` of 75 becomes 15 seconds into the next minute
+    and a `tm_mon` of 12 becomes January of the next year. Negative values
+    borrow in the same way. The weekday and day of the year are
+    recomputed; the DST flag, offset and zone are kept. This is pure
+    calendar arithmetic and does not consult the local timezone.
+    "]
+    fn normalize() -> tm {
+        let mut nsec = self.tm_nsec as i64;
+        let mut sec = self.tm_sec as i64 + carry(nsec, 1000000000_i64);
+        let mut min = self.tm_min as i64 + carry(sec, 60_i64);
+        let mut hour = self.tm_hour as i64 + carry(min, 60_i64);
+        let mday = self.tm_mday as i64 + carry(hour, 24_i64);
+        let mut mon = self.tm_mon as i64;
+        let year = self.tm_year as i64 + 1900_i64 + carry(mon, 12_i64);
+
+        // Count the day of the month from the first of the month, so
+        // that days past the end (or before the start) roll over.
+        let days = days_from_civil(year as int, mon as int + 1, 1) +
+            mday - 1_i64;
+        let (y, m, d) = civil_from_days(days);
+
+        {
+            tm_sec: sec as c_int,
+            tm_min: min as c_int,
+            tm_hour: hour as c_int,
+            tm_mday: d as c_int,
+            tm_mon: (m - 1) as c_int,
+            tm_year: (y - 1900) as c_int,
+            tm_wday: weekday_from_days(days) as c_int,
+            tm_yday: (days - days_from_civil(y, 1, 1)) as c_int,
+            tm_nsec: nsec as i32
+            with self
+        }
+    }
+
+    #[doc = "
+    Checks each field's range and the consistency of the calendar fields
+    (the day against the length of the month, and the weekday and day of
+    the year against the date), returning a description of every
+    violation found.
+    "]
+    fn validate() -> [str] {
+        let mut errs = [];
+
+        fn check(&errs: [str], name: str, v: int, lo: int, hi: int) -> bool {
+            if v < lo || v > hi {
+                errs += [#fmt("%s %d is not in the range %d-%d", name, v,
+                              lo, hi)];
+                false
+            } else {
+                true
+            }
+        }
+
+        check(errs, "tm_sec", self.tm_sec as int, 0, 60);
+        check(errs, "tm_min", self.tm_min as int, 0, 59);
+        check(errs, "tm_hour", self.tm_hour as int, 0, 23);
+        check(errs, "tm_nsec", self.tm_nsec as int, 0, 999999999);
+        check(errs, "tm_gmtoff", self.tm_gmtoff as int, -86399, 86399);
+        let wday_ok = check(errs, "tm_wday", self.tm_wday as int, 0, 6);
+
+        let year = self.year();
+        let yday_ok = check(errs, "tm_yday", self.tm_yday as int, 0,
+                            days_in_year(year) - 1);
+
+        if check(errs, "tm_mon", self.tm_mon as int, 0, 11) &&
+           check(errs, "tm_mday", self.tm_mday as int, 1,
+                 month_length(year, self.tm_mon as int + 1)) {
+            let days = days_from_civil(year, self.tm_mon as int + 1,
+                                       self.tm_mday as int);
+            let wday = weekday_from_days(days);
+            if wday_ok && wday != self.tm_wday as int {
+                errs += [#fmt("tm_wday %d does not match the date (%d)",
+                              self.tm_wday as int, wday)];
+            }
+            let yday = (days - days_from_civil(year, 1, 1)) as int;
+            if yday_ok && yday != self.tm_yday as int {
+                errs += [#fmt("tm_yday %d does not match the date (%d)",
+                              self.tm_yday as int, yday)];
+            }
+        }
+
+        errs
+    }
+
+    #[doc = "Returns true if `validate` finds no violations."]
+    fn is_valid() -> bool { vec::is_empty(self.validate()) }
+
+    #[doc = "Returns true if this time is strictly before `other`."]
+    fn is_before(other: tm) -> bool { self.lt(other) }
+
+    #[doc = "Returns true if this time is strictly after `other`."]
+    fn is_after(other: tm) -> bool { self.gt(other) }
+}
+
+impl timespec for timespec {
+    #[doc = "
+    Converts the timespec to JSON. In the RFC 3339 form the time is
+    rendered in UTC, with fractional seconds when `nsec` is non-zero.
+    "]
+    fn to_json(fmt: json_format) -> json::json {
+        alt fmt {
+          json_rfc3339 { json::string(rfc3339_nsec(at_utc(self))) }
+          json_sec_nsec {
+            let d = map::str_hash();
+            d.insert("sec", json::num(self.sec as float));
+            d.insert("nsec", json::num(self.nsec as float));
+            json::dict(d)
+          }
+        }
+    }
+
+    #[doc = "
+    Encodes the timespec as 12 bytes: the seconds as a big-endian i64
+    followed by the nanoseconds as a big-endian i32.
+    "]
+    fn to_bytes() -> [u8] {
+        let mut buf = [];
+        vec::reserve(buf, 12u);
+        write_be(buf, self.sec as u64, 8u);
+        write_be(buf, self.nsec as u64, 4u);
+        buf
+    }
+
+    #[doc = "Hashes the timespec, see `hash_timespec`."]
+    fn hash() -> uint { hash_timespec(self) }
+
+    fn lt(other: timespec) -> bool { cmp_timespec(self, other) < 0 }
+    fn le(other: timespec) -> bool { cmp_timespec(self, other) <= 0 }
+    fn gt(other: timespec) -> bool { cmp_timespec(self, other) > 0 }
+    fn ge(other: timespec) -> bool { cmp_timespec(self, other) >= 0 }
+
+    #[doc = "Returns the earlier of the two timespecs."]
+    fn min(other: timespec) -> timespec {
+        if self.le(other) { self } else { other }
+    }
+
+    #[doc = "Returns the later of the two timespecs."]
+    fn max(other: timespec) -> timespec {
+        if self.ge(other) { self } else { other }
+    }
+
+    #[doc = "
+    Constrains the timespec to the interval [`lo`, `hi`]. Fails if `lo` is
+    after `hi`.
+    "]
+    fn clamp(lo: timespec, hi: timespec) -> timespec {
+        assert lo.le(hi);
+        self.max(lo).min(hi)
+    }
+
+    #[doc = "Returns the timespec moved forward by the duration."]
+    fn add(d: duration) -> timespec {
+        let (sec, nsec) = carry_nanos(self.sec + d.secs,
+                                      (self.nsec + d.nanos) as i64);
+        { sec: sec, nsec: nsec }
+    }
+
+    #[doc = "Returns the timespec moved back by the duration."]
+    fn sub(d: duration) -> timespec { self.add(d.neg()) }
+
+    #[doc = "Returns the duration from `earlier` to this timespec."]
+    fn since(earlier: timespec) -> duration {
+        duration_from_parts(self.sec - earlier.sec,
+                            (self.nsec - earlier.nsec) as i64)
+    }
+
+    #[doc = "
+    Returns the milliseconds from `now` until this deadline, rounded up
+    so that a timer set for them doesn't fire early, or none if the
+    deadline has passed. Saturates at the largest u64.
+    "]
+    fn millis_until(now: timespec) -> option<u64> {
+        let d = self.since(now);
+        if !d.is_positive() {
+            ret none;
+        }
+        if d.secs as u64 >= 18446744073709551u64 {
+            ret some(18446744073709551615u64);
+        }
+        let ms = ((d.nanos as u64) + 999999u64) / 1000000u64;
+        some((d.secs as u64) * 1000u64 + ms)
+    }
+
+    #[doc = "
+    Returns the timeout until this deadline for `poll` and the other APIs
+    taking an int of milliseconds: 0 once the deadline has passed, so
+    that they return at once, and otherwise `millis_until` saturated at
+    the largest int.
+    "]
+    fn poll_timeout(now: timespec) -> c_int {
+        alt self.millis_until(now) {
+          none { 0 as c_int }
+          some(ms) if ms > 2147483647u64 { 2147483647 as c_int }
+          some(ms) { ms as c_int }
+        }
+    }
+
+    #[doc = "
+    Rounds the timespec down to a multiple of the duration since the
+    epoch, e.g. to the start of its 15 minute interval. Fails if the
+    duration is not positive.
+    "]
+    fn floor_to(d: duration) -> timespec {
+        self.sub(duration_nanos(self.rem_nanos(d) as i64))
+    }
+
+    #[doc = "
+    Rounds the timespec up to a multiple of the duration since the epoch.
+    Fails if the duration is not positive.
+    "]
+    fn ceil_to(d: duration) -> timespec {
+        let r = self.rem_nanos(d);
+        if r == 0u64 {
+            self
+        } else {
+            self.floor_to(d).add(d)
+        }
+    }
+
+    #[doc = "
+    Rounds the timespec to the nearest multiple of the duration since the
+    epoch, rounding halfway cases up. Fails if the duration is not
+    positive.
+    "]
+    fn round_to(d: duration) -> timespec {
+        let r = self.rem_nanos(d);
+        if r >= d.total_nanos() - r {
+            self.ceil_to(d)
+        } else {
+            self.floor_to(d)
+        }
+    }
+
+    #[doc = "
+    Returns the milliseconds since the epoch, rounded down. Returns an
+    error if the result does not fit in an i64.
+    "]
+    fn to_millis() -> result<i64, error> { self.to_units(1000_i64) }
+
+    #[doc = "
+    Returns the microseconds since the epoch, rounded down. Returns an
+    error if the result does not fit in an i64.
+    "]
+    fn to_micros() -> result<i64, error> { self.to_units(1000000_i64) }
+
+    #[doc = "
+    Returns the nanoseconds since the epoch. Returns an error if the
+    result does not fit in an i64, i.e. outside of the years 1677-2262.
+    "]
+    fn to_nanos() -> result<i64, error> { self.to_units(1000000000_i64) }
+
+    #[doc = "
+    Returns the key under which to store the time in a B-tree or a column
+    of integers: its nanoseconds since the epoch, which sort as the times
+    do. Returns an error for a time outside of the years 1677-2262, which
+    has no key. `timespec_from_key_i64` is the inverse.
+    "]
+    fn to_key_i64() -> result<i64, error> { self.to_nanos() }
+
+    #[doc = "
+    Returns the time as a JavaScript timestamp, the f64 count of
+    milliseconds since the epoch returned by `Date.getTime()`, rounded
+    down to a whole millisecond.
+    "]
+    fn to_js_millis() -> f64 {
+        (self.sec as f64) * 1000.0 + ((self.nsec / 1000000_i32) as f64)
+    }
+
+    #[doc = "
+    Returns the time as a fractional Julian day number, as returned by
+    SQLite's `julianday()`. Like SQLite, the time is first rounded down to
+    a whole millisecond.
+    "]
+    fn to_julianday_f64() -> f64 {
+        (self.to_js_millis() + julian_epoch_millis) / 86400000.0
+    }
+
+    #[doc = "
+    Returns the time as a version 1 UUID timestamp, the 60-bit count of
+    100 nanosecond intervals since 1582-10-15 00:00:00 UTC, rounding
+    down. Returns an error if the time cannot be represented.
+    "]
+    fn to_uuid_timestamp() -> result<u64, error> {
+        self.to_units(10000000_i64).chain {|ticks|
+            alt checked_add(ticks, uuid_epoch_ticks) {
+              some(v) if v >= 0_i64 && v < uuid_ticks_end {
+                ok(v as u64)
+              }
+              _ { err(out_of_range("Out of UUID range")) }
+            }
+        }
+    }
+
+    #[doc = "
+    Returns the first 8 bytes of a version 1 UUID generated at this time:
+    the time_low, time_mid and time_hi_and_version fields, with the
+    version bits set.
+    "]
+    fn to_uuid_time_bytes() -> result<[u8], error> {
+        self.to_uuid_timestamp().chain {|ts|
+            let mut buf = [];
+            vec::reserve(buf, 8u);
+            write_be(buf, ts & 0xffffffffu64, 4u);
+            write_be(buf, (ts >> 32u) & 0xffffu64, 2u);
+            write_be(buf, (ts >> 48u) & 0x0fffu64 | 0x1000u64, 2u);
+            ok(buf)
+        }
+    }
+
+    #[doc = "
+    Returns the time as a ULID timestamp, the 48-bit count of milliseconds
+    since the epoch, rounding down. Returns an error if the time cannot be
+    represented.
+    "]
+    fn to_ulid_timestamp() -> result<u64, error> {
+        alt self.to_millis() {
+          ok(ms) if ms >= 0_i64 && ms < ulid_millis_end { ok(ms as u64) }
+          _ { err(out_of_range("Out of ULID range")) }
+        }
+    }
+
+    #[doc = "
+    Returns the first 10 characters of a ULID generated at this time: the
+    timestamp in Crockford's base 32, so that ULIDs sort by time.
+    "]
+    fn to_ulid_prefix() -> result<str, error> {
+        self.to_ulid_timestamp().chain {|ms|
+            let mut buf = "";
+            str::reserve(buf, 10u);
+            let mut i = 0u;
+            while i < 10u {
+                let digit = ((ms >> (45u - 5u * i)) & 31u64) as uint;
+                str::push_char(buf, ulid_digits[digit] as char);
+                i += 1u;
+            }
+            ok(buf)
+        }
+    }
+
+    // Converts to a count of 1/per_sec second units, rounding down.
+    fn to_units(per_sec: i64) -> result<i64, error> {
+        let frac = (self.nsec as i64) / (1000000000_i64 / per_sec);
+        // Just above i64_min units, the whole seconds alone are below it,
+        // so a negative time counts back from the next second instead.
+        let (sec, frac) = if self.sec < 0_i64 && frac > 0_i64 {
+            (self.sec + 1_i64, frac - per_sec)
+        } else {
+            (self.sec, frac)
+        };
+        alt checked_mul(sec, per_sec) {
+          some(v) {
+            alt checked_add(v, frac) {
+              some(v) { ok(v) }
+              none { err(overflow) }
+            }
+          }
+          none { err(overflow) }
+        }
+    }
+
+    // The remainder, in nanoseconds, of the timespec divided by the
+    // duration, computed without overflowing.
+    fn rem_nanos(d: duration) -> u64 {
+        let m = d.total_nanos();
+        let sec = (self.sec - div_floor(self.sec, m as i64) * (m as i64))
+            as u64;
+        let r = mul_mod(sec, 1000000000u64 % m, m);
+        (r + (self.nsec as u64) % m) % m
+    }
+}
+
+#[doc = "Returns the timespec `n` milliseconds after the epoch."]
+fn timespec_from_millis(n: i64) -> timespec {
+    let (sec, nsec) = carry_nanos(div_floor(n, 1000_i64),
+                                  (n - div_floor(n, 1000_i64) * 1000_i64)
+                                  * 1000000_i64);
+    { sec: sec, nsec: nsec }
+}
+
+#[doc = "Returns the timespec `n` microseconds after the epoch."]
+fn timespec_from_micros(n: i64) -> timespec {
+    let (sec, nsec) = carry_nanos(div_floor(n, 1000000_i64),
+                                  (n - div_floor(n, 1000000_i64) *
+                                   1000000_i64) * 1000_i64);
+    { sec: sec, nsec: nsec }
+}
+
+#[doc = "Returns the timespec `n` nanoseconds after the epoch."]
+fn timespec_from_nanos(n: i64) -> timespec {
+    let (sec, nsec) = carry_nanos(0_i64, n);
+    { sec: sec, nsec: nsec }
+}
+
+#[doc = "Returns the timespec stored under a key from `timespec::to_key_i64`."]
+fn timespec_from_key_i64(key: i64) -> timespec { timespec_from_nanos(key) }
+
+#[doc = "
+Converts a JavaScript timestamp, as returned by `Date.getTime()`, into a
+timespec. A fractional millisecond is kept, rounded to the nearest
+nanosecond. Returns an error for NaN, infinities and values outside of
+the range of a JavaScript Date (100,000,000 days either side of the
+epoch).
+"]
+fn timespec_from_js_millis(ms: f64) -> result<timespec, error> {
+    if !f64::is_finite(ms) || f64::abs(ms) > 8.64e15 {
+        ret err(out_of_range("Invalid JavaScript time"));
+    }
+
+    let whole = f64::floor(ms);
+    let nanos = f64::floor((ms - whole) * 1e6 + 0.5) as i64;
+    let t = timespec_from_millis(whole as i64);
+    let (sec, nsec) = carry_nanos(t.sec, (t.nsec as i64) + nanos);
+    ok({ sec: sec, nsec: nsec })
+}
+
+#[doc = "
+Converts a fractional Julian day number, as stored by SQLite, into a
+timespec. Like SQLite, the day is rounded to the nearest millisecond.
+Returns an error for values outside of the range SQLite accepts, from
+0000-01-01 to 9999-12-31.
+"]
+fn timespec_from_julianday_f64(jd: f64) -> result<timespec, error> {
+    if !f64::is_finite(jd) || jd < 0.0 || jd > 5373484.5 {
+        ret err(out_of_range("Invalid Julian day"));
+    }
+
+    let ms = f64::floor(jd * 86400000.0 + 0.5) - julian_epoch_millis;
+    ok(timespec_from_millis(ms as i64))
+}
+
+// Milliseconds from the start of Julian day 0 to the unix epoch.
+const julian_epoch_millis: f64 = 210866760000000.0;
+
+#[doc = "
+Converts a version 1 UUID timestamp, the 60-bit count of 100 nanosecond
+intervals since 1582-10-15 00:00:00 UTC, into a timespec.
+"]
+fn timespec_from_uuid_timestamp(ts: u64) -> result<timespec, error> {
+    if ts >= uuid_ticks_end as u64 {
+        ret err(out_of_range("Out of UUID range"));
+    }
+
+    let v = (ts as i64) - uuid_epoch_ticks;
+    let sec = div_floor(v, 10000000_i64);
+    let (sec, nsec) = carry_nanos(sec, (v - sec * 10000000_i64) * 100_i64);
+    ok({ sec: sec, nsec: nsec })
+}
+
+#[doc = "
+Extracts the time a version 1 UUID was generated from its 16 bytes.
+Returns an error if the UUID is not a version 1 UUID.
+"]
+fn timespec_from_uuid(bytes: [u8]) -> result<timespec, error> {
+    if vec::len(bytes) != 16u {
+        ret err(parse_error("Expected 16 bytes"));
+    }
+    if bytes[6] >> 4u != 1u8 {
+        ret err(parse_error("Not a version 1 UUID"));
+    }
+
+    let ts = read_be(bytes, 0u, 4u) |
+        read_be(bytes, 4u, 2u) << 32u |
+        (read_be(bytes, 6u, 2u) & 0x0fffu64) << 48u;
+    timespec_from_uuid_timestamp(ts)
+}
+
+#[doc = "
+Converts a ULID timestamp, the 48-bit count of milliseconds since the
+epoch, into a timespec.
+"]
+fn timespec_from_ulid_timestamp(ms: u64) -> result<timespec, error> {
+    if ms >= ulid_millis_end as u64 {
+        ret err(out_of_range("Out of ULID range"));
+    }
+    ok(timespec_from_millis(ms as i64))
+}
+
+#[doc = "
+Extracts the time a ULID was generated from its first 10 characters.
+Accepts a whole ULID or just its time prefix, in either case.
+"]
+fn timespec_from_ulid(s: str) -> result<timespec, error> {
+    let len = str::len(s);
+    if len != 10u && len != 26u {
+        ret err(parse_error("Expected a ULID"));
+    }
+
+    let mut ms = 0u64;
+    let mut i = 0u;
+    while i < 10u {
+        let c = s[i];
+        let c = if c >= 'a' as u8 && c <= 'z' as u8 { c - 32u8 } else { c };
+        alt str::find_char(ulid_digits, c as char) {
+          some(digit) { ms = ms << 5u | digit as u64; }
+          none { ret err(parse_error("Invalid ULID")); }
+        }
+        i += 1u;
+    }
+    timespec_from_ulid_timestamp(ms)
+}
+
+// Crockford's base 32 digits, which ULIDs are written in.
+const ulid_digits: str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+// ULID timestamps are 48 bits wide.
+const ulid_millis_end: i64 = 281474976710656_i64;
+
+// 100ns intervals from the start of the Gregorian calendar to the epoch.
+const uuid_epoch_ticks: i64 = 122192928000000000_i64;
+
+// UUID timestamps are 60 bits wide.
+const uuid_ticks_end: i64 = 1152921504606846976_i64;
+
+#[doc = "
+Converts a file time from a struct stat, given as the seconds field
+(`st_mtime`) and, where the platform provides it, the nanoseconds field
+(`st_mtim.tv_nsec` or `st_mtimespec.tv_nsec`), into a timespec.
+"]
+fn timespec_from_stat(sec: i64, nsec: option<i64>) -> timespec {
+    let (sec, nsec) = carry_nanos(sec, option::get_default(nsec, 0_i64));
+    { sec: sec, nsec: nsec }
+}
+
+#[doc = "
+Converts a file time from a struct stat into a tm in the local timezone,
+see `timespec_from_stat`.
+"]
+fn tm_from_stat(sec: i64, nsec: option<i64>) -> tm {
+    at(timespec_from_stat(sec, nsec))
+}
+
+#[doc = "
+Returns the access, modification and status change times of the file at
+`path`. The times have nanosecond precision where the platform records
+it, and whole seconds otherwise. Built with `--cfg time_freestanding`,
+this always returns an error.
+"]
+fn get_file_times(path: str) -> result<file_times, error> {
+    alt sys::stat_times(path) {
+      some(times) { ok(times) }
+      none { err(platform_error(#fmt("Could not stat %s", path))) }
+    }
+}
+
+#[doc = "
+Sets the access and modification times of the file at `path`. Times are
+kept to the nanosecond on Linux, and truncated to the microsecond or the
+second on platforms whose system calls are less precise. To set the
+times from a tm, convert it with `tm::to_timespec` first. Built with
+`--cfg time_freestanding`, this always returns an error.
+"]
+fn set_file_times(path: str, atime: timespec, mtime: timespec)
+    -> result<(), error> {
+    if sys::set_times(path, atime, mtime) {
+        ok(())
+    } else {
+        err(platform_error(#fmt("Could not set the times of %s",
+                                path)))
+    }
+}
+
+#[doc = "
+Returns the time at which the current process was started, or
+`platform_error` if it is not available, as when /proc is missing on
+Linux or when built with `--cfg time_freestanding`.
+"]
+fn process_start_time() -> result<timespec, error> {
+    alt sys::process_start() {
+      some(t) { ok(t) }
+      none {
+        err(platform_error("The process start time is not available"))
+      }
+    }
+}
+
+#[doc = "
+Returns how long the current process has been running, or
+`platform_error` if its start time is not available.
+"]
+fn process_uptime() -> result<duration, error> {
+    process_start_time().chain {|start| ok(get_time().since(start)) }
+}
+
+// Carries nanoseconds into seconds, so that the nanoseconds returned are
+// in the range [0, 1000000000).
+fn carry_nanos(sec: i64, nsec: i64) -> (i64, i32) {
+    let mut nsec = nsec;
+    let sec = sec + carry(nsec, 1000000000_i64);
+    (sec, nsec as i32)
+}
+
+const i64_max: i64 = 9223372036854775807_i64;
+const i64_min: i64 = -9223372036854775807_i64 - 1_i64;
+
+// Multiplies, returning none on overflow.
+fn checked_mul(a: i64, b: i64) -> option<i64> {
+    if a == 0_i64 || b == 0_i64 { ret some(0_i64); }
+    if (a == -1_i64 && b == i64_min) || (b == -1_i64 && a == i64_min) {
+        ret none;
+    }
+    let r = a * b;
+    if r / b != a { none } else { some(r) }
+}
+
+// Adds, returning none on overflow.
+fn checked_add(a: i64, b: i64) -> option<i64> {
+    if (b > 0_i64 && a > i64_max - b) || (b < 0_i64 && a < i64_min - b) {
+        none
+    } else {
+        some(a + b)
+    }
+}
+
+// Computes (a * b) % m without overflowing, for a, b < m < 2^63.
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    let mut a = a;
+    let mut b = b;
+    let mut r = 0u64;
+    while b > 0u64 {
+        if b & 1u64 == 1u64 { r = (r + a) % m; }
+        a = (a + a) % m;
+        b >>= 1u64;
+    }
+    r
+}
+
+#[doc = "
+Converts JSON produced by `timespec::to_json` back into a timespec.
+Either representation is accepted.
+"]
+fn timespec_from_json(j: json::json) -> result<timespec, error> {
+    alt j {
+      json::string(s) {
+        parse_rfc3339(s).chain { |tm| tm.checked_to_timespec() }
+      }
+      json::dict(d) {
+        alt (d.find("sec"), d.find("nsec")) {
+          (some(json::num(sec)), some(json::num(nsec))) {
+            // 2^63 is exactly representable, unlike the largest i64, and
+            // NaN fails the integral check.
+            if float::floor(sec) != sec || sec < -9223372036854775808.0 ||
+               sec >= 9223372036854775808.0 {
+                err(parse_error("Invalid sec"))
+            } else if nsec < 0.0 || nsec >= 1000000000.0 {
+                err(parse_error("Invalid nsec"))
+            } else {
+                ok({ sec: sec as i64, nsec: nsec as i32 })
+            }
+          }
+          _ {
+            err(parse_error("Expected an object with numeric sec and nsec"))
+          }
+        }
+      }
+      _ { err(parse_error("Expected a string or an object")) }
+    }
+}
+
+#[doc = "
+Converts JSON produced by `tm::to_json` back into a tm. A string keeps
+the offset it was written with; an object is converted to UTC.
+"]
+fn tm_from_json(j: json::json) -> result<tm, error> {
+    alt j {
+      json::string(s) {
+        parse_rfc3339(s).chain { |tm|
+            timespec_from_json(j).chain { |ts|
+                ok(at_gmtoff(ts, tm.tm_gmtoff))
+            }
+        }
+      }
+      _ { timespec_from_json(j).chain { |ts| ok(at_utc(ts)) } }
+    }
+}
+
+#[doc = "
+Decodes a timespec from the 12-byte big-endian encoding produced by
+`timespec::to_bytes`.
+"]
+fn timespec_from_bytes(bytes: [u8]) -> result<timespec, error> {
+    if vec::len(bytes) != 12u {
+        ret err(parse_error("Expected 12 bytes"));
+    }
+
+    let sec = read_be(bytes, 0u, 8u) as i64;
+    let nsec = read_be(bytes, 8u, 4u) as i32;
+    if nsec < 0_i32 || nsec >= 1000000000_i32 {
+        err(parse_error("Invalid nsec"))
+    } else {
+        ok({ sec: sec, nsec: nsec })
+    }
+}
+
+#[doc = "
+Decodes a tm from the 16-byte big-endian encoding produced by
+`tm::to_bytes`. The result is expressed in the stored offset.
+"]
+fn tm_from_bytes(bytes: [u8]) -> result<tm, error> {
+    if vec::len(bytes) != 16u {
+        ret err(parse_error("Expected 16 bytes"));
+    }
+
+    timespec_from_bytes(vec::slice(bytes, 0u, 12u)).chain { |ts|
+        let gmtoff = read_be(bytes, 12u, 4u) as i32;
+        if gmtoff <= -86400_i32 || gmtoff >= 86400_i32 {
+            err(parse_error("Invalid zone offset"))
+        } else {
+            ok(at_gmtoff(ts, gmtoff as c_long))
+        }
+    }
+}
+
+#[doc = "Hashes a timespec, for use as a hashmap key."]
+fn hash_timespec(t: timespec) -> uint {
+    let sec = t.sec as u64;
+    let mut h = 5381u;
+    h = (h << 5u) + h ^ (sec as uint);
+    h = (h << 5u) + h ^ ((sec >> 32u) as uint);
+    h = (h << 5u) + h ^ (t.nsec as uint);
+    h
+}
+
+#[doc = "Returns true if the two timespecs are the same instant."]
+fn eq_timespec(a: timespec, b: timespec) -> bool {
+    a.sec == b.sec && a.nsec == b.nsec
+}
+
+#[doc = "
+Hashes a tm by the instant it represents, so that the same instant in
+two different zones hashes the same.
+"]
+fn hash_tm(t: tm) -> uint { hash_timespec(t.to_timespec()) }
+
+#[doc = "Returns true if the two tms represent the same instant."]
+fn eq_tm(a: tm, b: tm) -> bool { a.same_instant(b) }
+
+#[doc = "
+Compares two timespecs, returning a negative number, zero, or a positive
+number if `a` is before, the same as, or after `b`.
+"]
+fn cmp_timespec(a: timespec, b: timespec) -> int {
+    if a.sec < b.sec { -1 }
+    else if a.sec > b.sec { 1 }
+    else if a.nsec < b.nsec { -1 }
+    else if a.nsec > b.nsec { 1 }
+    else { 0 }
+}
+
+#[doc = "
+Returns true if `a` is not after `b`. Suitable as the comparison
+function for `std::sort`.
+"]
+fn le_timespec(a: timespec, b: timespec) -> bool { cmp_timespec(a, b) <= 0 }
+
+#[doc = "Compares two tms by the instants they represent."]
+fn cmp_tm(a: tm, b: tm) -> int {
+    cmp_timespec(a.to_timespec(), b.to_timespec())
+}
+
+#[doc = "
+Returns true if `a` is not after `b`. Suitable as the comparison
+function for `std::sort`.
+"]
+fn le_tm(a: tm, b: tm) -> bool { cmp_tm(a, b) <= 0 }
+
+#[doc = "
+Searches a vector of timespecs sorted in ascending order for `key`,
+returning its index if found.
+"]
+fn bsearch_timespec(v: [timespec], key: timespec) -> option<uint> {
+    let mut lo = 0u;
+    let mut hi = vec::len(v);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2u;
+        let c = cmp_timespec(v[mid], key);
+        if c == 0 {
+            ret some(mid);
+        } else if c < 0 {
+            lo = mid + 1u;
+        } else {
+            hi = mid;
+        }
+    }
+    none
+}
+
+#[doc = "Creates a hashmap keyed on timespecs."]
+fn timespec_hash<V: copy>() -> hashmap<timespec, V> {
+    map::hashmap(hash_timespec, eq_timespec)
+}
+
+#[doc = "Creates a hashmap keyed on the instants represented by tms."]
+fn tm_hash<V: copy>() -> hashmap<tm, V> {
+    map::hashmap(hash_tm, eq_tm)
+}
+
+#[doc = "
+Serializes a timespec as a record of its `sec` and `nsec` fields, in the
+form expected by the `std::serialization` serializers (e.g. ebml).
+"]
+fn serialize_timespec<S: serializer>(s: S, v: timespec) {
+    s.emit_rec {||
+        s.emit_rec_field("sec", 0u) {|| s.emit_i64(v.sec) }
+        s.emit_rec_field("nsec", 1u) {|| s.emit_i32(v.nsec) }
+    }
+}
+
+#[doc = "Deserializes a timespec written by `serialize_timespec`."]
+fn deserialize_timespec<D: deserializer>(d: D) -> timespec {
+    d.read_rec {||
+        {
+            sec: d.read_rec_field("sec", 0u) {|| d.read_i64() },
+            nsec: d.read_rec_field("nsec", 1u) {|| d.read_i32() }
+        }
+    }
+}
+
+#[doc = "
+Serializes a tm as a record of its timespec and its offset from UTC. The
+zone abbreviation is not serialized.
+"]
+fn serialize_tm<S: serializer>(s: S, v: tm) {
+    s.emit_rec {||
+        s.emit_rec_field("timespec", 0u) {||
+            serialize_timespec(s, v.to_timespec())
+        }
+        s.emit_rec_field("gmtoff", 1u) {|| s.emit_i64(v.tm_gmtoff as i64) }
+    }
+}
+
+#[doc = "
+Deserializes a tm written by `serialize_tm`, expressed in the offset it
+was written with.
+"]
+fn deserialize_tm<D: deserializer>(d: D) -> tm {
+    d.read_rec {||
+        let ts = d.read_rec_field("timespec", 0u) {||
+            deserialize_timespec(d)
+        };
+        let gmtoff = d.read_rec_field("gmtoff", 1u) {|| d.read_i64() };
+        at_gmtoff(ts, gmtoff as c_long)
+    }
+}
+
+// Divides, rounding towards negative infinity.
+fn div_floor(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    if a % b != 0_i64 && (a < 0_i64) != (b < 0_i64) { q - 1_i64 } else { q }
+}
+
+// The number of days in a 1-based month.
+fn month_length(year: int, month: int) -> int {
+    days_before_month(year, month + 1) - days_before_month(year, month)
+}
+
+// Whether a tm's month and day of the month are in range, so that its
+// weekday and day of the year follow from them.
+fn has_valid_date(tm: tm) -> bool {
+    tm.tm_mon >= 0 as c_int && tm.tm_mon <= 11 as c_int &&
+        tm.tm_mday >= 1 as c_int &&
+        tm.tm_mday as int <= month_length(tm.year(), tm.tm_mon as int + 1)
+}
+
+// The number of days in the year before the first of a 1-based month,
+// from a table of cumulative month lengths, shifted by a day after
+// February in leap years. Month 13 gives the length of the year.
+fn days_before_month(year: int, month: int) -> int {
+    let days = alt check month {
+      1 { 0 }
+      2 { 31 }
+      3 { 59 }
+      4 { 90 }
+      5 { 120 }
+      6 { 151 }
+      7 { 181 }
+      8 { 212 }
+      9 { 243 }
+      10 { 273 }
+      11 { 304 }
+      12 { 334 }
+      13 { 365 }
+    };
+    if month > 2 && is_leap_year(year) { days + 1 } else { days }
+}
+
+// The number of days from 1970-01-01 to the given proleptic Gregorian
+// date, with a 1-based month and day.
+fn days_from_civil(year: int, month: int, day: int) -> i64 {
+    let y = (if month <= 2 { year - 1 } else { year }) as i64;
+    let era = div_floor(y, 400_i64);
+    let yoe = y - era * 400_i64;
+    let mp = ((month + 9) % 12) as i64;
+    let doy = (153_i64 * mp + 2_i64) / 5_i64 + (day as i64) - 1_i64;
+    let doe = yoe * 365_i64 + yoe / 4_i64 - yoe / 100_i64 + doy;
+    era * 146097_i64 + doe - 719468_i64
+}
+
+// The proleptic Gregorian (year, month, day) of a count of days since
+// 1970-01-01, with a 1-based month and day.
+fn civil_from_days(days: i64) -> (int, int, int) {
+    let z = days + 719468_i64;
+    let era = div_floor(z, 146097_i64);
+    let doe = z - era * 146097_i64;
+    let yoe = (doe - doe / 1460_i64 + doe / 36524_i64 - doe / 146096_i64)
+        / 365_i64;
+    let doy = doe - (365_i64 * yoe + yoe / 4_i64 - yoe / 100_i64);
+    let mp = (5_i64 * doy + 2_i64) / 153_i64;
+    let day = (doy - (153_i64 * mp + 2_i64) / 5_i64 + 1_i64) as int;
+    let month = (if mp < 10_i64 { mp + 3_i64 } else { mp - 9_i64 }) as int;
+    let year = (yoe + era * 400_i64) as int + (if month <= 2 { 1 } else { 0 });
+    (year, month, day)
+}
+
+// The Thai Buddhist era, and the years it is ahead of the Gregorian
+// calendar.
+const buddhist_era_name: str = "พ.ศ.";
+const buddhist_era_offset: int = 543;
+
+// The seconds since the epoch of a tm's civil fields, read as UTC. Like
+// timegm, fields outside of their usual ranges are carried, so a tm_mon
+// of 12 is January of the next year.
+fn civil_seconds(tm: tm) -> i64 {
+    let mon = tm.tm_mon as i64;
+    let year = (tm.tm_year as i64) + 1900_i64 + div_floor(mon, 12_i64);
+    let month = (mon - div_floor(mon, 12_i64) * 12_i64) as int + 1;
+    let days = days_from_civil(year as int, month, 1) +
+        (tm.tm_mday as i64) - 1_i64;
+    days * 86400_i64 + (tm.tm_hour as i64) * 3600_i64 +
+        (tm.tm_min as i64) * 60_i64 + (tm.tm_sec as i64)
+}
+
+// The first and last years that fit in tm_year, and in an int on 32-bit
+// platforms, and the first and last seconds of those years.
+const tm_min_year: i64 = -2147481748_i64;
+const tm_max_year: i64 = 2147483647_i64;
+const tm_min_sec: i64 = -67768040609740800_i64;
+const tm_max_sec: i64 = 67767976233532799_i64;
+
+// The number of days from 1970-01-01 (Gregorian) to the given date in
+// the proleptic Julian calendar, with a 1-based month and day.
+fn days_from_julian(year: int, month: int, day: int) -> i64 {
+    let y = (if month <= 2 { year - 1 } else { year }) as i64;
+    let era = div_floor(y, 4_i64);
+    let yoe = y - era * 4_i64;
+    let mp = ((month + 9) % 12) as i64;
+    let doy = (153_i64 * mp + 2_i64) / 5_i64 + (day as i64) - 1_i64;
+    era * 1461_i64 + yoe * 365_i64 + doy - 719470_i64
+}
+
+// The proleptic Julian (year, month, day) of a count of days since
+// 1970-01-01 (Gregorian), with a 1-based month and day.
+fn julian_from_days(days: i64) -> (int, int, int) {
+    let z = days + 719470_i64;
+    let era = div_floor(z, 1461_i64);
+    let doe = z - era * 1461_i64;
+    let yoe = (doe - doe / 1460_i64) / 365_i64;
+    let doy = doe - 365_i64 * yoe;
+    let mp = (5_i64 * doy + 2_i64) / 153_i64;
+    let day = (doy - (153_i64 * mp + 2_i64) / 5_i64 + 1_i64) as int;
+    let month = (if mp < 10_i64 { mp + 3_i64 } else { mp - 9_i64 }) as int;
+    let year = (yoe + era * 4_i64) as int + (if month <= 2 { 1 } else { 0 });
+    (year, month, day)
+}
+
+// The weekday, with Sunday as 0, of a count of days since 1970-01-01.
+fn weekday_from_days(days: i64) -> int {
+    // 1970-01-01 was a Thursday.
+    (days + 4_i64 - div_floor(days + 4_i64, 7_i64) * 7_i64) as int
+}
+
+// The instant one nanosecond earlier.
+fn before(t: timespec) -> timespec {
+    if t.nsec == 0_i32 {
+        { sec: t.sec - 1_i64, nsec: 999999999_i32 }
+    } else {
+        { nsec: t.nsec - 1_i32 with t }
+    }
+}
+
+// Reduces `v` into the range [0, base) and returns how many multiples of
+// `base` were taken out of it, which is negative if `v` was negative.
+fn carry(&v: i64, base: i64) -> i64 {
+    let c = div_floor(v, base);
+    v -= c * base;
+    c
+}
+
+// Appends the low `n` bytes of `v` to `buf`, most significant first.
+fn write_be(&buf: [u8], v: u64, n: uint) {
+    let mut i = n;
+    while i > 0u {
+        i -= 1u;
+        vec::push(buf, (v >> (i * 8u)) as u8);
+    }
+}
+
+// Reads `n` bytes starting at `pos` as a big-endian integer.
+fn read_be(bytes: [u8], pos: uint, n: uint) -> u64 {
+    let mut v = 0u64;
+    let mut i = 0u;
+    while i < n {
+        v = (v << 8u) | (bytes[pos + i] as u64);
+        i += 1u;
+    }
+    v
+}
+
+#[doc = "Conversions between Gregorian dates and other calendars."]
+mod calendar {
+    export hebrew, japanese, chinese, ethiopian, julian;
+
+    #[doc = "
+    The proleptic Julian calendar, and the switch from it to the
+    Gregorian calendar. Countries adopted the Gregorian calendar at
+    different times, so historical dates are read against an adoption
+    date: the first day of the Gregorian calendar, after which the days
+    skipped by the reform never happened.
+
+    Before 1752 the British year began on March 25th; such dates must be
+    adjusted to a January year before being converted.
+    "]
+    mod julian {
+        export julian_date, historical_date, from_gregorian, to_gregorian,
+               is_leap_year, days_in_month, papal_adoption,
+               british_adoption, from_historical, to_historical;
+
+        #[doc = "A date in the Julian calendar, with a 1-based month."]
+        type julian_date = {year: int, month: int, day: int};
+
+        #[doc = "
+        A date as it was written at the time, in the Julian calendar (old
+        style) before the adoption date and in the Gregorian calendar (new
+        style) from it.
+        "]
+        type historical_date = {
+            year: int,
+            month: int,
+            day: int,
+            old_style: bool,
+        };
+
+        #[doc = "
+        Returns the date the Gregorian calendar began in Catholic Europe,
+        1582-10-15, which followed 1582-10-04 in the Julian calendar.
+        "]
+        fn papal_adoption() -> date { ymd(1582, october, 15) }
+
+        #[doc = "
+        Returns the date the Gregorian calendar began in Britain and its
+        colonies, 1752-09-14, which followed 1752-09-02 in the Julian
+        calendar.
+        "]
+        fn british_adoption() -> date { ymd(1752, september, 14) }
+
+        #[doc = "Returns true if the year is a leap year: every fourth."]
+        fn is_leap_year(year: int) -> bool {
+            year - div_floor(year as i64, 4_i64) as int * 4 == 0
+        }
+
+        #[doc = "
+        Returns the number of days in the month of the given year, or 0 if
+        the month is out of range.
+        "]
+        fn days_in_month(year: int, month: int) -> int {
+            if month == 2 {
+                if is_leap_year(year) { 29 } else { 28 }
+            } else if month >= 1 && month <= 12 {
+                month_length(2001, month)
+            } else {
+                0
+            }
+        }
+
+        #[doc = "Converts a Gregorian date to the Julian calendar."]
+        fn from_gregorian(d: date) -> julian_date {
+            let (y, m, day) = julian_from_days(d.days_since(unix()));
+            { year: y, month: m, day: day }
+        }
+
+        #[doc = "
+        Converts a Julian date to the Gregorian calendar. Returns an error
+        if the month or day is out of range.
+        "]
+        fn to_gregorian(j: julian_date) -> result<date, error> {
+            if j.month < 1 || j.month > 12 {
+                ret err(out_of_range("Invalid month"));
+            }
+            if j.day < 1 || j.day > days_in_month(j.year, j.month) {
+                ret err(out_of_range("Invalid day of the month"));
+            }
+            ok(unix().add_days(days_from_julian(j.year, j.month, j.day)))
+        }
+
+        #[doc = "
+        Converts a date as written at the time into the Gregorian
+        calendar: dates before `adoption` are read as Julian dates, and
+        dates from it as Gregorian dates. Returns an error for the dates
+        the reform skipped.
+        "]
+        fn from_historical(year: int, month: int, day: int, adoption: date)
+            -> result<date, error> {
+            let n = adoption.month.number();
+            let new_style = year > adoption.year ||
+                (year == adoption.year &&
+                 (month > n || (month == n && day >= adoption.day)));
+            if new_style {
+                ret alt month_from_number(month) {
+                  some(mon) { date_from_ymd(year, mon, day) }
+                  none { err(out_of_range("Invalid month")) }
+                };
+            }
+
+            to_gregorian({ year: year, month: month, day: day }).chain {|d|
+                if d.days_since(adoption) >= 0_i64 {
+                    err(out_of_range("Date skipped by the calendar reform"))
+                } else {
+                    ok(d)
+                }
+            }
+        }
+
+        #[doc = "
+        Converts a Gregorian date into the date written at the time, in
+        the Julian calendar if it is before `adoption`.
+        "]
+        fn to_historical(d: date, adoption: date) -> historical_date {
+            if d.days_since(adoption) >= 0_i64 {
+                { year: d.year, month: d.month.number(), day: d.day,
+                  old_style: false }
+            } else {
+                let j = from_gregorian(d);
+                { year: j.year, month: j.month, day: j.day,
+                  old_style: true }
+            }
+        }
+
+        fn ymd(year: int, mon: month, day: int) -> date {
+            { year: year, month: mon, day: day }
+        }
+
+        fn unix() -> date { ymd(1970, january, 1) }
+    }
+
+    #[doc = "
+    The Ethiopian calendar, of twelve 30 day months and a thirteenth
+    month, Pagume, of five days, or six in leap years. The year begins
+    on 1 Meskerem, in September, and is 7 or 8 years behind the Gregorian
+    year.
+    "]
+    mod ethiopian {
+        export ethiopian_date, from_gregorian, to_gregorian, is_leap_year,
+               days_in_month;
+
+        #[doc = "A date in the Ethiopian calendar, with a 1-based month."]
+        type ethiopian_date = {year: int, month: int, day: int};
+
+        // The days from 1970-01-01 to 1 Meskerem of year 1, August 29,
+        // 8 AD in the Julian calendar.
+        const epoch: i64 = -716367_i64;
+
+        #[doc = "Returns true if Pagume has six days in the year."]
+        fn is_leap_year(year: int) -> bool {
+            year - div_floor(year as i64, 4_i64) as int * 4 == 3
+        }
+
+        #[doc = "
+        Returns the number of days in the month of the given year, or 0 if
+        the month is out of range.
+        "]
+        fn days_in_month(year: int, month: int) -> int {
+            if month >= 1 && month <= 12 {
+                30
+            } else if month == 13 {
+                if is_leap_year(year) { 6 } else { 5 }
+            } else {
+                0
+            }
+        }
+
+        #[doc = "Converts a Gregorian date to the Ethiopian calendar."]
+        fn from_gregorian(d: date) -> ethiopian_date {
+            let days = d.days_since(unix());
+            let year = div_floor(4_i64 * (days - epoch) + 1463_i64, 1461_i64)
+                as int;
+            let month = ((days - days_from_ethiopian(year, 1, 1)) / 30_i64)
+                as int + 1;
+            let day = (days - days_from_ethiopian(year, month, 1)) as int + 1;
+            { year: year, month: month, day: day }
+        }
+
+        #[doc = "
+        Converts an Ethiopian date to the Gregorian calendar. Returns an
+        error if the month or day is out of range.
+        "]
+        fn to_gregorian(e: ethiopian_date) -> result<date, error> {
+            if e.month < 1 || e.month > 13 {
+                ret err(out_of_range("Invalid month"));
+            }
+            if e.day < 1 || e.day > days_in_month(e.year, e.month) {
+                ret err(out_of_range("Invalid day of the month"));
+            }
+            ok(unix().add_days(days_from_ethiopian(e.year, e.month, e.day)))
+        }
+
+        impl ethiopian_date for ethiopian_date {
+            #[doc = "Returns the name of the month."]
+            fn month_name() -> str {
+                alt self.month {
+                  1 { "Meskerem" }
+                  2 { "Tikimt" }
+                  3 { "Hidar" }
+                  4 { "Tahsas" }
+                  5 { "Tir" }
+                  6 { "Yekatit" }
+                  7 { "Megabit" }
+                  8 { "Miyazya" }
+                  9 { "Ginbot" }
+                  10 { "Sene" }
+                  11 { "Hamle" }
+                  12 { "Nehase" }
+                  13 { "Pagume" }
+                  _ { fail "Invalid month"; }
+                }
+            }
+
+            #[doc = "Formats the date, e.g. \"6 Yekatit 2001\"."]
+            fn to_str() -> str {
+                #fmt("%d %s %d", self.day, self.month_name(), self.year)
+            }
+        }
+
+        // The days from 1970-01-01 to the given Ethiopian date.
+        fn days_from_ethiopian(year: int, month: int, day: int) -> i64 {
+            let y = year as i64;
+            epoch - 1_i64 + 365_i64 * (y - 1_i64) + div_floor(y, 4_i64) +
+                30_i64 * ((month - 1) as i64) + (day as i64)
+        }
+
+        fn unix() -> date { result::get(date_from_ymd(1970, january, 1)) }
+    }
+
+    #[doc = "
+    The Chinese lunisolar calendar, as computed since 1929: months begin
+    on the day of the new moon in Beijing time, the winter solstice falls
+    in the eleventh month, and in a year of thirteen months the first
+    month without a major solar term is a leap month. Dates from 1901 to
+    2100 are supported.
+
+    The new moons and solar terms are computed astronomically, following
+    Reingold and Dershowitz's Calendrical Calculations, rather than read
+    from a table.
+    "]
+    mod chinese {
+        export chinese_date, from_gregorian;
+
+        #[doc = "
+        A date in the Chinese calendar. `year` is the year of the 60 year
+        `cycle`, both 1-based; cycle 78 began in 1984. `leap_month` is
+        true if the month is the leap month repeating `month`.
+        "]
+        type chinese_date = {
+            cycle: int,
+            year: int,
+            month: int,
+            leap_month: bool,
+            day: int,
+        };
+
+        // The days from 1970-01-01 to the Chinese epoch, 2637 BC.
+        const epoch: i64 = -1682262_i64;
+
+        const synodic_month: float = 29.530588861;
+        const tropical_year: float = 365.242189;
+
+        #[doc = "
+        Converts a Gregorian date to the Chinese calendar. Returns an
+        error if the date is outside of the supported range.
+        "]
+        fn from_gregorian(d: date) -> result<chinese_date, error> {
+            if d.year < 1901 || d.year > 2100 {
+                ret err(out_of_range("Date out of supported range"));
+            }
+
+            let unix = result::get(date_from_ymd(1970, january, 1));
+            let days = d.days_since(unix);
+
+            let s1 = winter_solstice_on_or_before(days);
+            let s2 = winter_solstice_on_or_before(s1 + 370_i64);
+            let m12 = new_moon_on_or_after(s1 + 1_i64);
+            let next_m11 = new_moon_before(s2 + 1_i64);
+            let m = new_moon_before(days + 1_i64);
+            let leap_year = months_between(m12, next_m11) == 12;
+
+            let mut n = months_between(m12, m);
+            if leap_year && prior_leap_month(m12, m) { n -= 1; }
+            let month = (n + 11) % 12 + 1;
+            let leap_month = leap_year && no_major_solar_term(m) &&
+                !prior_leap_month(m12, new_moon_before(m));
+
+            let elapsed = float::floor(1.5 - (month as float) / 12.0 +
+                                       ((days - epoch) as float) /
+                                       tropical_year) as i64;
+            ok({
+                cycle: div_floor(elapsed - 1_i64, 60_i64) as int + 1,
+                year: (elapsed - 1_i64 - div_floor(elapsed - 1_i64, 60_i64)
+                       * 60_i64) as int + 1,
+                month: month,
+                leap_month: leap_month,
+                day: (days - m) as int + 1,
+            })
+        }
+
+        // The number of whole months between two new moon days.
+        fn months_between(a: i64, b: i64) -> int {
+            float::floor(((b - a) as float) / synodic_month + 0.5) as int
+        }
+
+        // True if a leap month falls between the month starting on day
+        // `first` and the month starting on day `m`, inclusive.
+        fn prior_leap_month(first: i64, m: i64) -> bool {
+            let mut m = m;
+            while m >= first {
+                if no_major_solar_term(m) { ret true; }
+                m = new_moon_before(m);
+            }
+            false
+        }
+
+        // True if the month starting on day `m` contains no major solar
+        // term, one of the twelve multiples of 30 degrees of the sun's
+        // longitude.
+        fn no_major_solar_term(m: i64) -> bool {
+            major_solar_term(m) ==
+                major_solar_term(new_moon_on_or_after(m + 1_i64))
+        }
+
+        // The major solar term in effect at the start of a day.
+        fn major_solar_term(day: i64) -> int {
+            let l = solar_longitude(midnight(day));
+            (float::floor(l / 30.0) as int + 1) % 12 + 1
+        }
+
+        // The day of the winter solstice on or before `day`.
+        fn winter_solstice_on_or_before(day: i64) -> i64 {
+            let approx = estimate_prior_solar_longitude(
+                270.0, midnight(day + 1_i64));
+            let mut d = float::floor(approx) as i64 - 1_i64;
+            loop {
+                let l = solar_longitude(midnight(d + 1_i64));
+                if l > 270.0 && l < 300.0 { ret d; }
+                d += 1_i64;
+            }
+        }
+
+        // An estimate of the last moment at or before `t` when the sun
+        // was at longitude `l`.
+        fn estimate_prior_solar_longitude(l: float, t: float) -> float {
+            let rate = tropical_year / 360.0;
+            let tau = t - rate * mod360(solar_longitude(t) - l);
+            let delta = mod360(solar_longitude(tau) - l + 180.0) - 180.0;
+            float::min(t, tau - rate * delta)
+        }
+
+        // The day in Beijing of the first new moon on or after `day`.
+        fn new_moon_on_or_after(day: i64) -> i64 {
+            let t = midnight(day);
+            let mut k = lunation(t) - 1_i64;
+            while new_moon(k) < t { k += 1_i64; }
+            local_day(new_moon(k))
+        }
+
+        // The day in Beijing of the last new moon before `day`.
+        fn new_moon_before(day: i64) -> i64 {
+            let t = midnight(day);
+            let mut k = lunation(t) + 2_i64;
+            while new_moon(k) >= t { k -= 1_i64; }
+            local_day(new_moon(k))
+        }
+
+        // Moments are fractional days since 1970-01-01 00:00 UT. Beijing
+        // has kept UTC+8 since 1929, and its local mean time before.
+        fn zone(day: i64) -> float {
+            if day >= -14975_i64 { 8.0 / 24.0 } else { 1397.0 / 4320.0 }
+        }
+
+        fn midnight(day: i64) -> float { (day as float) - zone(day) }
+
+        fn local_day(t: float) -> i64 {
+            let day = float::floor(t) as i64;
+            float::floor(t + zone(day)) as i64
+        }
+
+        // The approximate number of new moons since January 6, 2000.
+        fn lunation(t: float) -> i64 {
+            float::floor((t + 2440587.5 - 2451550.09766) / synodic_month)
+                as i64
+        }
+
+        // The moment of the `k`th new moon since January 6, 2000, from
+        // Meeus's Astronomical Algorithms, chapter 49.
+        fn new_moon(k: i64) -> float {
+            let k = k as float;
+            let t = k / 1236.85;
+            let t2 = t * t, t3 = t2 * t, t4 = t3 * t;
+            let jde = 2451550.09766 + synodic_month * k + 0.00015437 * t2 -
+                0.000000150 * t3 + 0.00000000073 * t4;
+            let e = 1.0 - 0.002516 * t - 0.0000074 * t2;
+            let sm = radians(2.5534 + 29.10535670 * k - 0.0000014 * t2 -
+                             0.00000011 * t3);
+            let mm = radians(201.5643 + 385.81693528 * k + 0.0107582 * t2 +
+                             0.00001238 * t3 - 0.000000058 * t4);
+            let f = radians(160.7108 + 390.67050284 * k - 0.0016118 * t2 -
+                            0.00000227 * t3 + 0.000000011 * t4);
+            let o = radians(124.7746 - 1.56375588 * k + 0.0020672 * t2 +
+                            0.00000215 * t3);
+
+            let mut c = -0.40720 * sin(mm) + 0.17241 * e * sin(sm) +
+                0.01608 * sin(2.0 * mm) + 0.01039 * sin(2.0 * f) +
+                0.00739 * e * sin(mm - sm) - 0.00514 * e * sin(mm + sm) +
+                0.00208 * e * e * sin(2.0 * sm) -
+                0.00111 * sin(mm - 2.0 * f) - 0.00057 * sin(mm + 2.0 * f) +
+                0.00056 * e * sin(2.0 * mm + sm) - 0.00042 * sin(3.0 * mm) +
+                0.00042 * e * sin(sm + 2.0 * f) +
+                0.00038 * e * sin(sm - 2.0 * f) -
+                0.00024 * e * sin(2.0 * mm - sm) - 0.00017 * sin(o) -
+                0.00007 * sin(mm + 2.0 * sm) +
+                0.00004 * sin(2.0 * mm - 2.0 * f) + 0.00004 * sin(3.0 * sm) +
+                0.00003 * sin(mm + sm - 2.0 * f) +
+                0.00003 * sin(2.0 * mm + 2.0 * f) -
+                0.00003 * sin(mm + sm + 2.0 * f) +
+                0.00003 * sin(mm - sm + 2.0 * f) -
+                0.00002 * sin(mm - sm - 2.0 * f) -
+                0.00002 * sin(3.0 * mm + sm) + 0.00002 * sin(4.0 * mm);
+
+            // The planetary arguments.
+            let planets = [
+                (299.77, 0.107408, 325.0), (251.88, 0.016321, 165.0),
+                (251.83, 26.651886, 164.0), (349.42, 36.412478, 126.0),
+                (84.66, 18.206239, 110.0), (141.74, 53.303771, 62.0),
+                (207.14, 2.453732, 60.0), (154.84, 7.306860, 56.0),
+                (34.52, 27.261239, 47.0), (207.19, 0.121824, 42.0),
+                (291.34, 1.844379, 40.0), (161.72, 24.198154, 37.0),
+                (239.56, 25.513099, 35.0), (331.55, 3.592518, 23.0)
+            ];
+            let mut i = 0u;
+            for vec::each(planets) {|p|
+                let (a, b, coeff) = p;
+                let mut arg = a + b * k;
+                if i == 0u { arg -= 0.009173 * t2; }
+                c += coeff * 0.000001 * sin(radians(arg));
+                i += 1u;
+            }
+
+            let tt = jde + c - 2440587.5;
+            tt - delta_t(tt)
+        }
+
+        // The apparent longitude of the sun in degrees at moment `t`, from
+        // Meeus's Astronomical Algorithms, chapter 25.
+        fn solar_longitude(t: float) -> float {
+            let c = (t + delta_t(t) + 2440587.5 - 2451545.0) / 36525.0;
+            let l0 = 280.46646 + 36000.76983 * c + 0.0003032 * c * c;
+            let m = radians(357.52911 + 35999.05029 * c -
+                            0.0001537 * c * c);
+            let eq = (1.914602 - 0.004817 * c - 0.000014 * c * c) * sin(m) +
+                (0.019993 - 0.000101 * c) * sin(2.0 * m) +
+                0.000289 * sin(3.0 * m);
+            let o = radians(125.04 - 1934.136 * c);
+            mod360(l0 + eq - 0.00569 - 0.00478 * sin(o))
+        }
+
+        // The difference between terrestrial and universal time in days
+        // at moment `t`, from Espenak and Meeus's polynomials.
+        fn delta_t(t: float) -> float {
+            let y = 1970.0 + t / 365.2425;
+            let secs = if y < 1920.0 {
+                let u = y - 1900.0;
+                -2.79 + 1.494119 * u - 0.0598939 * u * u +
+                    0.0061966 * u * u * u - 0.000197 * u * u * u * u
+            } else if y < 1941.0 {
+                let u = y - 1920.0;
+                21.20 + 0.84493 * u - 0.076100 * u * u +
+                    0.0020936 * u * u * u
+            } else if y < 1961.0 {
+                let u = y - 1950.0;
+                29.07 + 0.407 * u - u * u / 233.0 + u * u * u / 2547.0
+            } else if y < 1986.0 {
+                let u = y - 1975.0;
+                45.45 + 1.067 * u - u * u / 260.0 - u * u * u / 718.0
+            } else if y < 2005.0 {
+                let u = y - 2000.0;
+                63.86 + 0.3345 * u - 0.060374 * u * u +
+                    0.0017275 * u * u * u + 0.000651814 * u * u * u * u +
+                    0.00002373599 * u * u * u * u * u
+            } else if y < 2050.0 {
+                let u = y - 2000.0;
+                62.92 + 0.32217 * u + 0.005589 * u * u
+            } else {
+                let u = (y - 1820.0) / 100.0;
+                -20.0 + 32.0 * u * u - 0.5628 * (2150.0 - y)
+            };
+            secs / 86400.0
+        }
+
+        fn sin(x: float) -> float { float::sin(x) }
+
+        fn radians(deg: float) -> float { deg * float::consts::pi / 180.0 }
+
+        fn mod360(x: float) -> float { x - 360.0 * float::floor(x / 360.0) }
+    }
+
+    #[doc = "
+    Japanese eras (wareki), as used by the %EC, %Ey and %EY conversions
+    of `strftime` and `strptime`. Dates before the Meiji era have no era.
+    "]
+    mod japanese {
+        export era, eras, era_of, era_year_str, to_gregorian_year,
+               match_era, match_era_year;
+
+        #[doc = "An era, named by its kanji, and the date it began."]
+        type era = {name: str, start: date};
+
+        #[doc = "Returns the eras since Meiji, oldest first."]
+        fn eras() -> [era] {
+            [
+                { name: "明治", start: ymd(1868, october, 23) },
+                { name: "大正", start: ymd(1912, july, 30) },
+                { name: "昭和", start: ymd(1926, december, 25) },
+                { name: "平成", start: ymd(1989, january, 8) },
+                { name: "令和", start: ymd(2019, may, 1) },
+            ]
+        }
+
+        #[doc = "
+        Returns the era of the date and the 1-based year within it, or
+        none if the date is before the Meiji era. Era years are counted
+        from the Gregorian year the era began, so 2009 is Heisei 21.
+        "]
+        fn era_of(d: date) -> option<(era, int)> {
+            let mut found = none;
+            for vec::each(eras()) {|e|
+                if d.days_since(e.start) >= 0_i64 {
+                    found = some((e, d.year - e.start.year + 1));
+                }
+            }
+            found
+        }
+
+        #[doc = "
+        Formats a year of an era, e.g. \"平成21年\". The first year of an
+        era is written 元年.
+        "]
+        fn era_year_str(e: era, year: int) -> str {
+            if year == 1 {
+                e.name + "元年"
+            } else {
+                #fmt("%s%d年", e.name, year)
+            }
+        }
+
+        #[doc = "
+        Returns the Gregorian year of a year of the named era, or none if
+        there is no such era.
+        "]
+        fn to_gregorian_year(name: str, year: int) -> option<int> {
+            for vec::each(eras()) {|e|
+                if e.name == name { ret some(e.start.year + year - 1); }
+            }
+            none
+        }
+
+        #[doc = "
+        Matches an era name at `pos`, returning the era and the position
+        after it.
+        "]
+        fn match_era(s: str, pos: uint) -> option<(era, uint)> {
+            for vec::each(eras()) {|e|
+                let end = pos + str::len(e.name);
+                if end <= str::len(s) && str::slice(s, pos, end) == e.name {
+                    ret some((e, end));
+                }
+            }
+            none
+        }
+
+        #[doc = "
+        Matches a year of an era at `pos`, either 元 or one or two
+        digits, returning the year and the position after it.
+        "]
+        fn match_era_year(s: str, pos: uint) -> option<(int, uint)> {
+            let len = str::len(s);
+            let first = str::len("元");
+            if pos + first <= len && str::slice(s, pos, pos + first) == "元" {
+                ret some((1, pos + first));
+            }
+
+            let mut end = pos;
+            while end < len && end < pos + 2u &&
+                  char::is_digit(s[end] as char) {
+                end += 1u;
+            }
+            if end == pos { ret none; }
+            int::from_str(str::slice(s, pos, end)).chain {|y|
+                if y >= 1 { some((y, end)) } else { none }
+            }
+        }
+
+        fn ymd(year: int, mon: month, day: int) -> date {
+            { year: year, month: mon, day: day }
+        }
+    }
+
+    #[doc = "
+    The Hebrew calendar. Months are numbered from Nisan, as in the Torah,
+    so the year begins on 1 Tishri, the seventh month, and the leap month
+    Adar II is the thirteenth.
+    "]
+    mod hebrew {
+        export hebrew_date, from_gregorian, to_gregorian, is_leap_year,
+               months_in_year, days_in_year, days_in_month;
+
+        #[doc = "A date in the Hebrew calendar, with a 1-based day."]
+        type hebrew_date = {year: int, month: int, day: int};
+
+        // The days from 1970-01-01 to 1 Tishri of year 1.
+        const epoch: i64 = -2092590_i64;
+
+        #[doc = "Returns true if the year has thirteen months."]
+        fn is_leap_year(year: int) -> bool { (7 * year + 1) % 19 < 7 }
+
+        #[doc = "Returns the number of months in the year, 12 or 13."]
+        fn months_in_year(year: int) -> int {
+            if is_leap_year(year) { 13 } else { 12 }
+        }
+
+        #[doc = "Returns the number of days in the year."]
+        fn days_in_year(year: int) -> int {
+            (new_year(year + 1) - new_year(year)) as int
+        }
+
+        #[doc = "
+        Returns the number of days in the month of the given year, or 0 if
+        the year has no such month.
+        "]
+        fn days_in_month(year: int, month: int) -> int {
+            alt month {
+              2 | 4 | 6 | 10 { 29 }
+              13 { if is_leap_year(year) { 29 } else { 0 } }
+              12 { if is_leap_year(year) { 30 } else { 29 } }
+              8 { if days_in_year(year) % 10 == 5 { 30 } else { 29 } }
+              9 { if days_in_year(year) % 10 == 3 { 29 } else { 30 } }
+              1 | 3 | 5 | 7 | 11 { 30 }
+              _ { 0 }
+            }
+        }
+
+        #[doc = "Converts a Gregorian date to the Hebrew calendar."]
+        fn from_gregorian(d: date) -> hebrew_date {
+            let days = days_of(d);
+            let mut year = ((days - epoch) * 98496_i64 / 35975351_i64)
+                as int;
+            while new_year(year + 1) <= days { year += 1; }
+
+            let mut month =
+                if days < days_from_hebrew(year, 1, 1) { 7 } else { 1 };
+            while days > days_from_hebrew(year, month,
+                                          days_in_month(year, month)) {
+                month += 1;
+            }
+            let day = (days - days_from_hebrew(year, month, 1)) as int + 1;
+            { year: year, month: month, day: day }
+        }
+
+        #[doc = "
+        Converts a Hebrew date to the Gregorian calendar. Returns an error
+        if the month or day is out of range.
+        "]
+        fn to_gregorian(h: hebrew_date) -> result<date, error> {
+            if h.year < 1 { ret err(out_of_range("Invalid year")); }
+            if days_in_month(h.year, h.month) == 0 {
+                ret err(out_of_range("Invalid month"));
+            }
+            if h.day < 1 || h.day > days_in_month(h.year, h.month) {
+                ret err(out_of_range("Invalid day of the month"));
+            }
+
+            let unix = result::get(date_from_ymd(1970, january, 1));
+            ok(unix.add_days(days_from_hebrew(h.year, h.month, h.day)))
+        }
+
+        impl hebrew_date for hebrew_date {
+            #[doc = "
+            Returns the name of the month. In leap years the twelfth
+            month is Adar I.
+            "]
+            fn month_name() -> str {
+                alt self.month {
+                  1 { "Nisan" }
+                  2 { "Iyyar" }
+                  3 { "Sivan" }
+                  4 { "Tammuz" }
+                  5 { "Av" }
+                  6 { "Elul" }
+                  7 { "Tishri" }
+                  8 { "Marheshvan" }
+                  9 { "Kislev" }
+                  10 { "Tevet" }
+                  11 { "Shevat" }
+                  12 if is_leap_year(self.year) { "Adar I" }
+                  12 { "Adar" }
+                  13 { "Adar II" }
+                  _ { fail "Invalid month"; }
+                }
+            }
+
+            #[doc = "Formats the date, e.g. \"19 Shevat 5769\"."]
+            fn to_str() -> str {
+                #fmt("%d %s %d", self.day, self.month_name(), self.year)
+            }
+        }
+
+        // The days from 1970-01-01 to the given Gregorian date.
+        fn days_of(d: date) -> i64 {
+            d.days_since(result::get(date_from_ymd(1970, january, 1)))
+        }
+
+        // The days from 1970-01-01 to the given Hebrew date.
+        fn days_from_hebrew(year: int, month: int, day: int) -> i64 {
+            let mut n = new_year(year) + (day as i64) - 1_i64;
+            let mut m = 7;
+            if month < 7 {
+                while m <= months_in_year(year) {
+                    n += days_in_month(year, m) as i64;
+                    m += 1;
+                }
+                m = 1;
+            }
+            while m < month {
+                n += days_in_month(year, m) as i64;
+                m += 1;
+            }
+            n
+        }
+
+        // The days from 1970-01-01 to 1 Tishri of the year.
+        fn new_year(year: int) -> i64 {
+            epoch + elapsed_days(year) + new_year_delay(year)
+        }
+
+        // The days from the epoch to the molad of Tishri of the year,
+        // postponed by a day when it falls on a Sunday, Wednesday or
+        // Friday.
+        fn elapsed_days(year: int) -> i64 {
+            let months = div_floor(235_i64 * (year as i64) - 234_i64,
+                                   19_i64);
+            let parts = 12084_i64 + 13753_i64 * months;
+            let day = 29_i64 * months + div_floor(parts, 25920_i64);
+            if (3_i64 * (day + 1_i64)) % 7_i64 < 3_i64 {
+                day + 1_i64
+            } else {
+                day
+            }
+        }
+
+        // Further postpones the new year to keep the length of the year
+        // or the one before it valid.
+        fn new_year_delay(year: int) -> i64 {
+            let last = elapsed_days(year - 1);
+            let this = elapsed_days(year);
+            let next = elapsed_days(year + 1);
+            if next - this == 356_i64 {
+                2_i64
+            } else if this - last == 382_i64 {
+                1_i64
+            } else {
+                0_i64
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_at_utc() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+
+        assert utc.tm_sec == 30 as c_int;
+        assert utc.tm_min == 31 as c_int;
+        assert utc.tm_hour == 23 as c_int;
+        assert utc.tm_mday == 13 as c_int;
+        assert utc.tm_mon == 1 as c_int;
+        assert utc.tm_year == 109 as c_int;
+        assert utc.tm_wday == 5 as c_int;
+        assert utc.tm_yday == 43 as c_int;
+        assert utc.tm_isdst == 0 as c_int;
+        assert utc.tm_gmtoff == 0 as c_long;
+        assert utc.tm_zone == "UTC";
+        assert utc.tm_nsec == 54321_i32;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_at() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let local = at(time);
+
+        assert local.tm_sec == 30 as c_int;
+        assert local.tm_min == 31 as c_int;
+        assert local.tm_hour == 15 as c_int;
+        assert local.tm_mday == 13 as c_int;
+        assert local.tm_mon == 1 as c_int;
+        assert local.tm_year == 109 as c_int;
+        assert local.tm_wday == 5 as c_int;
+        assert local.tm_yday == 43 as c_int;
+        assert local.tm_isdst == 0 as c_int;
+        assert local.tm_gmtoff == -28800 as c_long;
+        assert local.tm_zone == "PST";
+        assert local.tm_nsec == 54321_i32;
+    }
+
+    #[test]
+    fn test_to_timespec() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+
+        assert utc.to_timespec() == time;
+        assert utc.to_local().to_timespec() == time;
+    }
+
+    #[test]
+    fn test_conversions() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert local.to_local() == local;
+        assert local.to_utc() == utc;
+        assert local.to_utc().to_local() == local;
+        assert utc.to_utc() == utc;
+        assert utc.to_local() == local;
+        assert utc.to_local().to_utc() == utc;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_strptime() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        alt strptime("", "") {
+          ok(tm) {
+            assert tm.tm_sec == 0 as c_int;
+            assert tm.tm_min == 0 as c_int;
+            assert tm.tm_hour == 0 as c_int;
+            assert tm.tm_mday == 0 as c_int;
+            assert tm.tm_mon == 0 as c_int;
+            assert tm.tm_year == 0 as c_int;
+            assert tm.tm_wday == 0 as c_int;
+            assert tm.tm_isdst == -1 as c_int;
+            assert tm.tm_gmtoff == 0 as c_long;
+            assert tm.tm_zone == "";
+            assert tm.tm_nsec == 0_i32;
+          }
+          err(_) {}
+        }
+
+        let format = "%a %b %e %T %Y";
+        assert strptime("", format) ==
+            err(parse_error("Input ended while parsing %a at offset 0"));
+        assert strptime("Fri Feb 13 15:31:30", format) ==
+            err(parse_error("Input ended while expecting \" \" at offset 19"));
+        assert strptime("2009-02-13 15:31:", "%F %H:%M:%S") ==
+            err(parse_error("Input ended while parsing %S at offset 17"));
+        assert strptime("2009-02-13 15:31", "%F %T") ==
+            err(parse_error("Input ended while parsing %T at offset 16"));
+        assert strptime("2009-02-13 15:3", "%F %T") ==
+            err(parse_error("Invalid minute"));
+        assert strptime("Fri", "%a%EY") ==
+            err(parse_error("Input ended while parsing %EY at offset 3"));
+        assert strptime("Fri Feb", format) ==
+            err(parse_error("Input ended while expecting \" \" at offset 7"));
+        assert strptime("Fri Feb 13 15:31:30 2009 x", format) ==
+            err(parse_error("Invalid time"));
+
+        alt strptime("Fri Feb 13 15:31:30 2009", format) {
+          err(e) { fail e.to_str() }
+          ok(tm) {
+            assert tm.tm_sec == 30 as c_int;
+            assert tm.tm_min == 31 as c_int;
+            assert tm.tm_hour == 15 as c_int;
+            assert tm.tm_mday == 13 as c_int;
+            assert tm.tm_mon == 1 as c_int;
+            assert tm.tm_year == 109 as c_int;
+            assert tm.tm_wday == 5 as c_int;
+            assert tm.tm_yday == 43 as c_int;
+            assert tm.tm_isdst == -1 as c_int;
+            assert tm.tm_gmtoff == 0 as c_long;
+            assert tm.tm_zone == "";
+            assert tm.tm_nsec == 0_i32;
+          }
+        }
+
+        fn test(s: str, format: str) -> bool {
+            alt strptime(s, format) {
+              ok(tm) { tm.strftime(format) == s }
+              err(e) { fail e.to_str() }
+            }
+        }
+
+        vec::iter([
+            "Sunday",
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday"
+        ]) { |day| assert test(day, "%A"); }
+
+        vec::iter([
+            "Sun",
+            "Mon",
+            "Tue",
+            "Wed",
+            "Thu",
+            "Fri",
+            "Sat"
+        ]) { |day| assert test(day, "%a"); }
+
+        vec::iter([
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December"
+        ]) { |day| assert test(day, "%B"); }
+
+        vec::iter([
+            "Jan",
+            "Feb",
+            "Mar",
+            "Apr",
+            "May",
+            "Jun",
+            "Jul",
+            "Aug",
+            "Sep",
+            "Oct",
+            "Nov",
+            "Dec"
+        ]) { |day| assert test(day, "%b"); }
+
+        assert test("19", "%C");
+        assert test("Fri Feb 13 23:31:30 2009", "%c");
+        assert test("02/13/09", "%D");
+        assert test("03", "%d");
+        assert test("13", "%d");
+        assert test(" 3", "%e");
+        assert test("13", "%e");
+        assert test("2009-02-13", "%F");
+        assert test("03", "%H");
+        assert test("13", "%H");
+        assert test("03", "%I"); // FIXME: flesh out
+        assert test("11", "%I"); // FIXME: flesh out
+        assert test("044", "%j");
+        assert test(" 3", "%k");
+        assert test("13", "%k");
+        assert test(" 1", "%l");
+        assert test("11", "%l");
+        assert test("03", "%M");
+        assert test("13", "%M");
+        assert test("\n", "%n");
+        assert test("am", "%P");
+        assert test("pm", "%P");
+        assert test("AM", "%p");
+        assert test("PM", "%p");
+        assert test("23:31", "%R");
+        assert test("11:31:30 AM", "%r");
+        assert test("11:31:30 PM", "%r");
+        assert test("03", "%S");
+        assert test("13", "%S");
+        assert test("15:31:30", "%T");
+        assert test("\t", "%t");
+        assert test("1", "%u");
+        assert test("7", "%u");
+        assert test("13-Feb-2009", "%v");
+        assert test("0", "%w");
+        assert test("6", "%w");
+        assert test("2009", "%Y");
+        assert test("09", "%y");
+        assert test("UTC", "%Z");
+        assert test("GMT", "%Z");
+        os::setenv("TZ", "America/Los_Angeles");
+        assert test("PST", "%Z");
+        assert test("PDT", "%Z");
+        let pst = result::get(strptime("PST", "%Z"));
+        assert pst.tm_gmtoff == -28800 as c_long;
+        assert pst.tm_isdst == 0 as c_int;
+        let est = result::get(strptime("EST", "%Z"));
+        assert est.tm_zone == "EST";
+        assert est.tm_gmtoff == -18000 as c_long;
+        assert result::get(strptime("CST", "%Z")).tm_zone == "";
+        assert result::get(strptime("XYZ", "%Z")).tm_zone == "";
+        assert result::get(strptime("-0000", "%z")).tm_gmtoff == 0 as c_long;
+        assert result::get(strptime("-0800", "%z")).tm_gmtoff ==
+            -28800 as c_long;
+        assert result::get(strptime("+0530", "%z")).tm_gmtoff ==
+            19800 as c_long;
+        assert strptime("+2400", "%z") ==
+            err(parse_error("Invalid zone offset"));
+        assert strptime("-0860", "%z") ==
+            err(parse_error("Invalid zone offset"));
+        let pacific = result::get(strptime("2009-02-13 15:31:30 -0800",
+                                           "%F %T %z"));
+        assert pacific.to_timespec() == { sec: 1234567890_i64, nsec: 0_i32 };
+        assert test("%", "%%");
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_ctime() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc   = at_utc(time);
+        let local = at(time);
+
+        assert utc.ctime()   == "Fri Feb 13 23:31:30 2009";
+        assert local.ctime() == "Fri Feb 13 15:31:30 2009";
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_strftime() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert local.strftime("") == "";
+        assert local.strftime("%A") == "Friday";
+        assert local.strftime("%a") == "Fri";
+        assert local.strftime("%B") == "February";
+        assert local.strftime("%b") == "Feb";
+        assert { tm_wday: 7 as c_int with utc }.strftime("%a") == "?";
+        assert { tm_mon: -1 as c_int with utc }.strftime("%B") == "?";
+        assert local.strftime("%C") == "20";
+        assert local.strftime("%c") == "Fri Feb 13 15:31:30 2009";
+        assert local.strftime("%D") == "02/13/09";
+        assert local.strftime("%d") == "13";
+        assert local.strftime("%e") == "13";
+        assert local.strftime("%F") == "2009-02-13";
+        // assert local.strftime("%G") == "2009";
+        // assert local.strftime("%g") == "09";
+        assert local.strftime("%H") == "15";
+        assert local.strftime("%I") == "03";
+        assert local.strftime("%j") == "044";
+        assert local.strftime("%k") == "15";
+        assert local.strftime("%l") == " 3";
+        assert local.strftime("%M") == "31";
+        assert local.strftime("%m") == "02";
+        assert local.strftime("%n") == "\n";
+        assert local.strftime("%P") == "pm";
+        assert local.strftime("%p") == "PM";
+        assert local.strftime("%R") == "15:31";
+        assert local.strftime("%r") == "03:31:30 PM";
+        assert local.strftime("%S") == "30";
+        assert local.strftime("%s") == "1234567890";
+        assert local.strftime("%T") == "15:31:30";
+        assert local.strftime("%t") == "\t";
+        // assert local.strftime("%U") == "06";
+        assert local.strftime("%u") == "5";
+        // assert local.strftime("%V") == "07";
+        assert local.strftime("%v") == "13-Feb-2009";
+        // assert local.strftime("%W") == "06";
+        assert local.strftime("%w") == "5";
+        // handle "%X"
+        // handle "%x"
+        assert local.strftime("%Y") == "2009";
+        assert local.strftime("%y") == "09";
+
+        assert local.strftime("%Z") == "PST";
+
+        assert local.strftime("%z") == "-0800";
+        assert local.strftime("%%") == "%";
+
+        assert local.rfc822() == "Fri, 13 Feb 2009 15:31:30 PST";
+
+        assert local.ctime() == "Fri Feb 13 15:31:30 2009";
+        assert local.rfc822z() == "Fri, 13 Feb 2009 15:31:30 -0800";
+        assert local.rfc3339() == "2009-02-13T15:31:30-08:00";
+
+        assert utc.ctime() == "Fri Feb 13 23:31:30 2009";
+        assert utc.rfc822() == "Fri, 13 Feb 2009 23:31:30 GMT";
+        assert utc.rfc822z() == "Fri, 13 Feb 2009 23:31:30 -0000";
+        assert utc.rfc3339() == "2009-02-13T23:31:30Z";
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_json() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert json::to_str(time.to_json(json_rfc3339)) ==
+            "\"2009-02-13T23:31:30.000054321Z\"";
+        assert json::to_str(local.to_json(json_rfc3339)) ==
+            "\"2009-02-13T15:31:30.000054321-08:00\"";
+
+        assert timespec_from_json(time.to_json(json_rfc3339)) == ok(time);
+        assert timespec_from_json(time.to_json(json_sec_nsec)) == ok(time);
+        assert timespec_from_json(local.to_json(json_rfc3339)) == ok(time);
+        assert tm_from_json(utc.to_json(json_rfc3339)) == ok(utc);
+        assert tm_from_json(utc.to_json(json_sec_nsec)) == ok(utc);
+
+        let local2 = result::get(tm_from_json(local.to_json(json_rfc3339)));
+        assert local2.tm_hour == 15 as c_int;
+        assert local2.tm_wday == 5 as c_int;
+        assert local2.tm_yday == 43 as c_int;
+        assert local2.tm_gmtoff == -28800 as c_long;
+
+        assert timespec_from_json(json::string("2009-02-13T23:31:30Z")) ==
+            ok({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert timespec_from_json(json::string("2009-02-13")) ==
+            err(parse_error("Invalid RFC 3339 time"));
+        assert timespec_from_json(json::string("2009-02-14T05:01:30+05:30"))
+            == ok({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert timespec_from_json(json::null) ==
+            err(parse_error("Expected a string or an object"));
+
+        let sec_nsec = fn@(sec: float, nsec: float) -> json::json {
+            let d = map::str_hash();
+            d.insert("sec", json::num(sec));
+            d.insert("nsec", json::num(nsec));
+            json::dict(d)
+        };
+        assert timespec_from_json(sec_nsec(-1.0, 0.0)) ==
+            ok({ sec: -1_i64, nsec: 0_i32 });
+        assert timespec_from_json(sec_nsec(1.5, 0.0)) ==
+            err(parse_error("Invalid sec"));
+        assert timespec_from_json(sec_nsec(1e19, 0.0)) ==
+            err(parse_error("Invalid sec"));
+        assert timespec_from_json(sec_nsec(-1e19, 0.0)) ==
+            err(parse_error("Invalid sec"));
+        assert timespec_from_json(sec_nsec(0.0, 1e9)) ==
+            err(parse_error("Invalid nsec"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_bytes() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert time.to_bytes() == [0u8, 0u8, 0u8, 0u8, 0x49u8, 0x96u8,
+                                   0x02u8, 0xd2u8, 0u8, 0u8, 0xd4u8, 0x31u8];
+        assert timespec_from_bytes(time.to_bytes()) == ok(time);
+
+        let before = { sec: -1_i64, nsec: 0_i32 };
+        assert timespec_from_bytes(before.to_bytes()) == ok(before);
+
+        assert vec::len(local.to_bytes()) == 16u;
+        assert tm_from_bytes(utc.to_bytes()) == ok(utc);
+
+        let local2 = result::get(tm_from_bytes(local.to_bytes()));
+        assert local2.to_timespec() == time;
+        assert local2.tm_hour == 15 as c_int;
+        assert local2.tm_gmtoff == -28800 as c_long;
+
+        assert timespec_from_bytes([]) ==
+            err(parse_error("Expected 12 bytes"));
+        assert tm_from_bytes(time.to_bytes()) ==
+            err(parse_error("Expected 16 bytes"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_serialization() {
+        import std::ebml;
+        import std::ebml::serializer;
+        import std::ebml::ebml_deserializer;
+
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let local = at(time);
+
+        let buf = io::mem_buffer();
+        let w = ebml::writer(buf as io::writer);
+        serialize_timespec(w, time);
+        serialize_tm(w, local);
+
+        let doc = ebml::new_doc(@io::mem_buffer_buf(buf));
+        let d = ebml_deserializer(doc);
+        assert deserialize_timespec(d) == time;
+
+        let local2 = deserialize_tm(d);
+        assert local2.to_timespec() == time;
+        assert local2.tm_hour == 15 as c_int;
+        assert local2.tm_gmtoff == -28800 as c_long;
+    }
+
+    #[test]
+    fn test_hash() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert hash_timespec(time) == time.hash();
+        assert hash_timespec(time) != hash_timespec({ nsec: 0_i32 with time });
+        assert hash_tm(utc) == hash_tm(local);
+        assert eq_tm(utc, local);
+        assert !eq_timespec(time, { sec: 0_i64 with time });
+
+        let seen = timespec_hash();
+        assert seen.insert(time, 1);
+        assert !seen.insert({ sec: 1234567890_i64, nsec: 54321_i32 }, 2);
+        assert seen.size() == 1u;
+        assert seen.get(time) == 2;
+
+        let by_tm = tm_hash();
+        by_tm.insert(utc, "utc");
+        assert by_tm.contains_key(local);
+    }
+
+    #[test]
+    fn test_ordering() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let a = { sec: -1_i64, nsec: 999999999_i32 };
+        let b = { sec: 0_i64, nsec: 0_i32 };
+        let c = { sec: 0_i64, nsec: 1_i32 };
+        let d = { sec: 1234567890_i64, nsec: 54321_i32 };
+
+        assert cmp_timespec(a, b) < 0;
+        assert cmp_timespec(c, b) > 0;
+        assert cmp_timespec(d, d) == 0;
+        assert a.lt(b) && b.le(b) && c.gt(b) && c.ge(c);
+        assert !b.lt(a) && !b.gt(c);
+
+        assert at(d).gt(at_utc(b));
+        assert at(d).le(at_utc(d)) && at(d).ge(at_utc(d));
+        assert cmp_tm(at(d), at_utc(d)) == 0;
+
+        let sorted = std::sort::merge_sort(le_timespec, [d, b, a, c]);
+        assert sorted == [a, b, c, d];
+        assert std::sort::merge_sort(le_tm, [at(d), at_utc(a)]) ==
+            [at_utc(a), at(d)];
+
+        assert bsearch_timespec(sorted, c) == some(2u);
+        assert bsearch_timespec(sorted, a) == some(0u);
+        assert bsearch_timespec(sorted, { sec: 5_i64, nsec: 0_i32 }) == none;
+        assert bsearch_timespec([], a) == none;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_same_instant_civil() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        let local = at(time);
+
+        assert utc != local;
+        assert utc.same_instant(local);
+        assert !utc.same_civil(local);
+
+        let stale = { tm_wday: 0 as c_int, tm_yday: 0 as c_int with local };
+        assert stale != local;
+        assert stale.same_civil(local);
+        assert stale.same_instant(local);
+
+        let shifted = { tm_gmtoff: 0 as c_long with local };
+        assert shifted.same_civil(local);
+        assert !shifted.same_instant(local);
+        assert !local.same_civil({ tm_nsec: 0_i32 with local });
+    }
+
+    #[test]
+    fn test_min_max_clamp() {
+        let a = { sec: 1_i64, nsec: 5_i32 };
+        let b = { sec: 1_i64, nsec: 6_i32 };
+        let c = { sec: 2_i64, nsec: 0_i32 };
+
+        assert a.min(b) == a && b.min(a) == a;
+        assert a.max(b) == b && b.max(a) == b;
+        assert a.min(a) == a && a.max(a) == a;
+
+        assert a.clamp(b, c) == b;
+        assert b.clamp(a, c) == b;
+        let d = { sec: 3_i64, nsec: 0_i32 };
+        assert d.clamp(a, c) == c;
+        assert c.clamp(a, a) == a;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_same_day_week_month() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        // Fri Feb 13 2009 23:31:30 UTC, 15:31:30 PST.
+        let a = at_utc({ sec: 1234567890_i64, nsec: 0_i32 });
+        // Sat Feb 14 2009 03:00:00 UTC, Fri 19:00:00 PST.
+        let b = at_utc({ sec: 1234580400_i64, nsec: 0_i32 });
+        // Sun Feb 15 2009 03:00:00 UTC, Sat 19:00:00 PST.
+        let c = at_utc({ sec: 1234666800_i64, nsec: 0_i32 });
+        // Sun Mar 1 2009 07:00:00 UTC, Sat Feb 28 23:00:00 PST.
+        let d = at_utc({ sec: 1235890800_i64, nsec: 0_i32 });
+
+        assert !a.same_day(b, zone_utc);
+        assert a.same_day(b, zone_local);
+        assert a.same_day(b, zone_offset(-28800_i32));
+        assert !a.same_day(c, zone_local);
+
+        assert a.same_week(b, zone_utc);
+        assert !a.same_week(c, zone_utc);
+        assert a.same_week(c, zone_local);
+
+        assert !a.same_month(d, zone_utc);
+        assert a.same_month(d, zone_local);
+
+        // Weeks before the epoch.
+        let e = at_utc({ sec: -86400_i64 * 4_i64, nsec: 0_i32 });
+        let f = at_utc({ sec: -86400_i64 * 5_i64, nsec: 0_i32 });
+        assert !e.same_week(f, zone_utc);
+    }
+
+    #[test]
+    fn test_is_before_after() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let deadline = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+        let later = at_utc({ sec: 1234567890_i64, nsec: 54322_i32 });
+
+        assert deadline.is_before(later);
+        assert !deadline.is_after(later);
+        assert later.is_after(deadline);
+        assert !deadline.is_before(deadline.to_utc());
+        assert !deadline.is_after(deadline.to_utc());
+    }
+
+    #[test]
+    fn test_from_ymd() {
+        let tm = result::get(tm_from_ymd_hms_nsec(2009, february, 13, 23, 31,
+                                                  30, 54321_i32));
+        assert tm.tm_sec == 30 as c_int;
+        assert tm.tm_min == 31 as c_int;
+        assert tm.tm_hour == 23 as c_int;
+        assert tm.tm_mday == 13 as c_int;
+        assert tm.tm_mon == 1 as c_int;
+        assert tm.tm_year == 109 as c_int;
+        assert tm.tm_wday == 5 as c_int;
+        assert tm.tm_yday == 43 as c_int;
+        assert tm.tm_gmtoff == 0 as c_long;
+        assert tm.tm_nsec == 54321_i32;
+        assert tm.to_timespec() == { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert tm.same_civil(at_utc(tm.to_timespec()));
+
+        assert result::get(tm_from_ymd_hms(2009, february, 13, 23, 31, 30))
+            .to_timespec() == { sec: 1234567890_i64, nsec: 0_i32 };
+
+        let epoch = result::get(tm_from_ymd(1970, january, 1));
+        assert epoch.tm_wday == 4 as c_int;
+        assert epoch.to_timespec() == { sec: 0_i64, nsec: 0_i32 };
+
+        let leap = result::get(tm_from_ymd(2008, december, 31));
+        assert leap.tm_yday == 365 as c_int;
+        assert leap.tm_wday == 3 as c_int;
+        assert result::get(tm_from_ymd(1969, december, 31)).tm_wday ==
+            3 as c_int;
+        assert result::get(tm_from_ymd(2000, february, 29)).tm_wday ==
+            2 as c_int;
+
+        let invalid_day = err(out_of_range("Invalid day of the month"));
+        assert tm_from_ymd(2009, february, 29) == invalid_day;
+        assert tm_from_ymd(1900, february, 29) == invalid_day;
+        assert tm_from_ymd(2009, april, 31) == invalid_day;
+        assert tm_from_ymd_hms(2009, may, 1, 24, 0, 0) ==
+            err(out_of_range("Invalid hour"));
+        assert tm_from_ymd_hms(2009, may, 1, 0, 60, 0) ==
+            err(out_of_range("Invalid minute"));
+        assert tm_from_ymd_hms(2009, may, 1, 0, 0, 61) ==
+            err(out_of_range("Invalid second"));
+        assert tm_from_ymd_hms_nsec(2009, may, 1, 0, 0, 0, 1000000000_i32) ==
+            err(out_of_range("Invalid nanosecond"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_with_setters() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+
+        let tm = result::get(local.with_hour(9));
+        assert tm.tm_hour == 9 as c_int;
+        assert tm.tm_min == 31 as c_int;
+        assert tm.tm_gmtoff == local.tm_gmtoff;
+        assert tm.tm_zone == local.tm_zone;
+        assert local.tm_hour == 15 as c_int;
+
+        let tm = result::get(local.with_day(1));
+        assert tm.tm_mday == 1 as c_int;
+        assert tm.tm_wday == 0 as c_int;
+        assert tm.tm_yday == 31 as c_int;
+
+        let tm = result::get(local.with_year(2012));
+        assert tm.tm_year == 112 as c_int;
+        assert tm.tm_wday == 1 as c_int;
+
+        assert result::get(local.with_month(december)).tm_mon == 11 as c_int;
+        assert result::get(local.with_minute(0)).tm_min == 0 as c_int;
+        assert result::get(local.with_second(60)).tm_sec == 60 as c_int;
+        assert result::get(local.with_nanosecond(0_i32)).tm_nsec == 0_i32;
+
+        assert local.with_day(29) ==
+            err(out_of_range("Invalid day of the month"));
+        assert local.with_month(april).chain { |t| t.with_day(31) } ==
+            err(out_of_range("Invalid day of the month"));
+        assert local.with_hour(-1) == err(out_of_range("Invalid hour"));
+        assert local.with_minute(60) == err(out_of_range("Invalid minute"));
+        assert local.with_second(61) == err(out_of_range("Invalid second"));
+        assert local.with_nanosecond(-1_i32) ==
+            err(out_of_range("Invalid nanosecond"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_accessors() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+        assert local.year() == 2009;
+        assert local.month() == february;
+        assert local.day() == 13;
+        assert local.hour() == 15;
+        assert local.minute() == 31;
+        assert local.second() == 30;
+        assert local.nanosecond() == 54321_i32;
+
+        let epoch = at_utc({ sec: 0_i64, nsec: 0_i32 });
+        assert epoch.year() == 1970;
+        assert epoch.month() == january;
+        assert epoch.day() == 1;
+    }
+
+    #[test]
+    fn test_weekday() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+        assert local.weekday() == friday;
+        assert local.weekday().num_days_from_sunday() == 5;
+        assert at_utc({ sec: 0_i64, nsec: 0_i32 }).weekday() == thursday;
+
+        assert sunday.succ() == monday;
+        assert saturday.succ() == sunday;
+        assert sunday.pred() == saturday;
+        assert wednesday.pred() == tuesday;
+        assert sunday.num_days_from_sunday() == 0;
+        assert saturday.num_days_from_sunday() == 6;
+
+        assert weekday_from_days_from_sunday(3) == some(wednesday);
+        assert weekday_from_days_from_sunday(7) == none;
+        assert weekday_from_days_from_sunday(-1) == none;
+    }
+
+    #[test]
+    fn test_month() {
+        assert january.number() == 1;
+        assert december.number() == 12;
+        assert january.to_tm_mon() == 0 as c_int;
+        assert december.to_tm_mon() == 11 as c_int;
+
+        assert month_from_number(2) == some(february);
+        assert month_from_number(0) == none;
+        assert month_from_number(13) == none;
+        assert month_from_tm_mon(0 as c_int) == some(january);
+        assert month_from_tm_mon(12 as c_int) == none;
+
+        assert january.succ() == february;
+        assert december.succ() == january;
+        assert january.pred() == december;
+        assert march.pred() == february;
+
+        let tm = result::get(tm_from_ymd(2009, july, 4));
+        assert tm.month() == july;
+        assert tm.month().number() == 7;
+    }
+
+    #[test]
+    fn test_date_and_time_of_day() {
+        let d = result::get(date_from_ymd(2009, february, 13));
+        assert d.to_str() == "2009-02-13";
+        assert d.weekday() == friday;
+        assert date_from_str("2009-02-13") == ok(d);
+        assert date_from_ymd(2009, february, 29) ==
+            err(out_of_range("Invalid day of the month"));
+        assert date_from_str("2009-02-30") ==
+            err(out_of_range("Invalid day of the month"));
+
+        assert d.add_days(16_i64).to_str() == "2009-03-01";
+        assert d.add_days(-44_i64).to_str() == "2008-12-31";
+        assert d.add_days(-14289_i64).to_str() == "1969-12-31";
+        assert result::get(date_from_ymd(2008, february, 28))
+            .add_days(1_i64).to_str() == "2008-02-29";
+        assert d.add_days(365_i64).days_since(d) == 365_i64;
+        assert d.days_since(d.add_days(3_i64)) == -3_i64;
+
+        let t = result::get(time_of_day_from_hms(23, 31, 30));
+        assert t.to_str() == "23:31:30";
+        assert t.seconds_from_midnight() == 84690;
+        assert time_of_day_from_str("23:31:30") == ok(t);
+        assert time_of_day_from_str("23:31:30.000054321") ==
+            time_of_day_from_hms_nsec(23, 31, 30, 54321_i32);
+        assert time_of_day_from_str("23:31:30.5") ==
+            time_of_day_from_hms_nsec(23, 31, 30, 500000000_i32);
+        assert result::get(time_of_day_from_str("23:31:30.5")).to_str() ==
+            "23:31:30.500000000";
+        assert time_of_day_from_str("23:31:30.") ==
+            err(parse_error("Invalid fractional seconds"));
+        assert time_of_day_from_hms(24, 0, 0) ==
+            err(out_of_range("Invalid hour"));
+
+        assert t.add_seconds(1800_i64).to_str() == "00:01:30";
+        assert t.add_seconds(-84690_i64).to_str() == "00:00:00";
+        assert t.add_seconds(-84691_i64).to_str() == "23:59:59";
+
+        let tm = d.and_time(t);
+        assert tm.to_timespec() == { sec: 1234567890_i64, nsec: 0_i32 };
+        assert t.on(d) == tm;
+        assert tm.date() == d;
+        assert tm.time_of_day() == t;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_datetime() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+
+        let utc = datetime_at(time, zone_utc);
+        assert utc.date.to_str() == "2009-02-13";
+        assert utc.time.to_str() == "23:31:30.000054321";
+        assert utc.gmtoff == 0_i32;
+        assert utc.to_timespec() == time;
+        assert utc.to_tm() == at_utc(time);
+
+        let local = datetime_at(time, zone_local);
+        assert local.time.hour == 15;
+        assert local.gmtoff == -28800_i32;
+        assert local.to_timespec() == time;
+        assert local.to_str() == "2009-02-13T15:31:30.000054321-08:00";
+        assert local.in_zone(zone_utc) == utc;
+        assert utc.in_zone(zone_offset(-28800_i32)) == local;
+
+        let ist = datetime_at(time, zone_offset(19800_i32));
+        assert ist.date.to_str() == "2009-02-14";
+        assert ist.time.to_str() == "05:01:30.000054321";
+
+        assert datetime_from_str(local.to_str()) == ok(local);
+        assert datetime_from_tm(at(time)) == ok(local);
+        assert datetime_from_tm({ tm_mday: 30 as c_int with at(time) }) ==
+            err(out_of_range("Invalid day of the month"));
+
+        assert local.add_seconds(30600_i64).to_str() ==
+            "2009-02-14T00:01:30.000054321-08:00";
+        assert local.add_days(-14_i64).to_str() ==
+            "2009-01-30T15:31:30.000054321-08:00";
+
+        let before = datetime_at({ sec: -1_i64, nsec: 0_i32 }, zone_utc);
+        assert before.to_str() == "1969-12-31T23:59:59Z";
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_utc_local_tm() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc_tm(time);
+        let local = at_local_tm(time);
+
+        assert utc.tm() == at_utc(time);
+        assert local.tm() == at(time);
+        assert utc.to_timespec() == time;
+        assert local.to_timespec() == time;
+        assert utc.to_local().tm() == local.tm();
+        assert local.to_utc().tm() == utc.tm();
+
+        assert utc.rfc822() == "Fri, 13 Feb 2009 23:31:30 GMT";
+        assert utc.rfc3339() == "2009-02-13T23:31:30Z";
+        assert local.rfc3339() == "2009-02-13T15:31:30-08:00";
+        assert local.strftime("%H") == "15";
+
+        // A local zone at UTC+0 still formats as a local time.
+        os::setenv("TZ", "Europe/London");
+        let london = at_local_tm(time);
+        assert london.rfc822() == "Fri, 13 Feb 2009 23:31:30 GMT";
+        assert london.rfc3339() == "2009-02-13T23:31:30+00:00";
+    }
+
+    #[test]
+    fn test_normalize() {
+        let tm = result::get(tm_from_ymd_hms(2009, february, 13, 23, 31, 30));
+
+        let t = { tm_sec: 75 as c_int with tm }.normalize();
+        assert t.same_civil(result::get(tm_from_ymd_hms(2009, february, 13,
+                                                        23, 32, 15)));
+
+        let t = { tm_mon: 12 as c_int with tm }.normalize();
+        assert t.same_civil(result::get(tm_from_ymd_hms(2010, january, 13,
+                                                        23, 31, 30)));
+        assert t.tm_wday == 3 as c_int;
+        assert t.tm_yday == 12 as c_int;
+
+        let t = { tm_mday: 29 as c_int, tm_hour: 24 as c_int with tm }
+            .normalize();
+        assert t.same_civil(result::get(tm_from_ymd_hms(2009, march, 2,
+                                                        0, 31, 30)));
+        assert t.tm_yday == 60 as c_int;
+
+        let t = { tm_mday: 0 as c_int, tm_min: -32 as c_int with tm }
+            .normalize();
+        assert t.same_civil(result::get(tm_from_ymd_hms(2009, january, 31,
+                                                        22, 28, 30)));
+
+        let t = { tm_nsec: 1500000000_i32, tm_mon: -1 as c_int with tm }
+            .normalize();
+        assert t.same_civil(result::get(tm_from_ymd_hms_nsec(
+            2008, december, 13, 23, 31, 31, 500000000_i32)));
+
+        assert tm.normalize() == tm;
+    }
+
+    #[test]
+    fn test_validate() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert at(time).is_valid();
+        assert at_utc(time).validate() == [];
+        assert result::get(tm_from_ymd(2008, february, 29)).is_valid();
+
+        assert !empty_tm().is_valid();
+        assert empty_tm().validate() ==
+            ["tm_mday 0 is not in the range 1-31"];
+
+        let tm = at_utc(time);
+        let bad = { tm_mday: 29 as c_int with tm };
+        assert bad.validate() == ["tm_mday 29 is not in the range 1-28"];
+
+        let bad = { tm_sec: 61 as c_int, tm_mon: 12 as c_int with tm };
+        assert bad.validate() == ["tm_sec 61 is not in the range 0-60",
+                                  "tm_mon 12 is not in the range 0-11"];
+
+        let bad = { tm_mday: 14 as c_int with tm };
+        assert bad.validate() == ["tm_wday 5 does not match the date (6)",
+                                  "tm_yday 43 does not match the date (44)"];
+
+        let bad = { tm_yday: 365 as c_int with tm };
+        assert bad.validate() == ["tm_yday 365 is not in the range 0-364"];
+    }
+
+    #[test]
+    fn test_calendar_helpers() {
+        assert is_leap_year(2008);
+        assert is_leap_year(2000);
+        assert is_leap_year(-4);
+        assert !is_leap_year(2009);
+        assert !is_leap_year(1900);
+
+        assert days_in_month(2008, february) == 29;
+        assert days_in_month(2009, february) == 28;
+        assert days_in_month(2009, april) == 30;
+        assert days_in_month(2009, december) == 31;
+
+        assert days_in_year(2008) == 366;
+        assert days_in_year(2009) == 365;
+    }
+
+    #[test]
+    fn test_weekday_from_ymd() {
+        assert weekday_from_ymd(2009, february, 13) == friday;
+        assert weekday_from_ymd(1970, january, 1) == thursday;
+        assert weekday_from_ymd(2000, february, 29) == tuesday;
+        assert weekday_from_ymd(1969, december, 31) == wednesday;
+        assert weekday_from_ymd(1, january, 1) == monday;
+        assert weekday_from_ymd(0, march, 1) == wednesday;
+
+        // strptime fills in the weekday from a complete date.
+        let tm = result::get(strptime("2009-02-13", "%F"));
+        assert tm.tm_wday == 5 as c_int;
+        assert result::get(strptime("13 Feb", "%d %b")).tm_wday ==
+            2 as c_int;
+        assert result::get(strptime("Feb 2009", "%b %Y")).tm_wday ==
+            0 as c_int;
+    }
+
+    #[test]
+    fn test_ordinal() {
+        let d = result::get(date_from_ymd(2009, february, 13));
+        assert d.ordinal() == 44;
+        assert d.to_ordinal() == (2009, 44);
+        assert date_from_ordinal(2009, 44) == ok(d);
+        assert date_from_ordinal(2008, 366) == date_from_ymd(2008, december,
+                                                            31);
+        assert date_from_ordinal(2009, 60) == date_from_ymd(2009, march, 1);
+        assert date_from_ordinal(2009, 366) ==
+            err(out_of_range("Invalid day of the year"));
+        assert date_from_ordinal(2009, 0) ==
+            err(out_of_range("Invalid day of the year"));
+
+        // strptime computes the day of the year from a date, and the date
+        // from a day of the year.
+        assert result::get(strptime("2009-02-13", "%F")).tm_yday ==
+            43 as c_int;
+        let tm = result::get(strptime("2009 044", "%Y %j"));
+        assert tm.tm_mon == 1 as c_int;
+        assert tm.tm_mday == 13 as c_int;
+        assert tm.tm_wday == 5 as c_int;
+        assert strptime("2009 366", "%Y %j") ==
+            err(out_of_range("Invalid day of the year"));
+    }
+
+    #[test]
+    fn test_quarter() {
+        let d = result::get(date_from_ymd(2009, february, 13));
+        assert d.quarter() == 1;
+        assert d.first_day_of_quarter().to_str() == "2009-01-01";
+        assert d.last_day_of_quarter().to_str() == "2009-03-31";
+
+        let d = result::get(date_from_ymd(2008, december, 1));
+        assert d.quarter() == 4;
+        assert d.first_day_of_quarter().to_str() == "2008-10-01";
+        assert d.last_day_of_quarter().to_str() == "2008-12-31";
+
+        let tm = result::get(tm_from_ymd(2009, june, 30));
+        assert tm.quarter() == 2;
+        assert tm.first_day_of_quarter().to_str() == "2009-04-01";
+        assert tm.last_day_of_quarter().to_str() == "2009-06-30";
+        assert result::get(tm_from_ymd(2009, july, 1)).quarter() == 3;
+    }
+
+    #[test]
+    fn test_fiscal_year() {
+        let d = result::get(date_from_ymd(2009, february, 13));
+        assert d.fiscal_year(january) == 2009;
+        assert d.fiscal_quarter(january) == 1;
+        assert d.fiscal_year(april) == 2009;
+        assert d.fiscal_quarter(april) == 4;
+        assert d.fiscal_year(october) == 2009;
+        assert d.fiscal_quarter(october) == 2;
+
+        let d = result::get(date_from_ymd(2008, october, 1));
+        assert d.fiscal_year(october) == 2009;
+        assert d.fiscal_quarter(october) == 1;
+        assert d.fiscal_year(april) == 2009;
+        assert d.fiscal_quarter(april) == 3;
+
+        let tm = result::get(tm_from_ymd(2009, march, 31));
+        assert tm.fiscal_year(april) == 2009;
+        assert tm.fiscal_quarter(april) == 4;
+        let tm = result::get(tm_from_ymd(2009, april, 1));
+        assert tm.fiscal_year(april) == 2010;
+        assert tm.fiscal_quarter(april) == 1;
+    }
+
+    #[test]
+    fn test_week_of_month() {
+        fn week(y: int, m: month, d: int, conv: week_convention) -> int {
+            result::get(date_from_ymd(y, m, d)).week_of_month(conv)
+        }
+
+        // February 2009 starts on a Sunday.
+        assert week(2009, february, 1, sunday_weeks) == 1;
+        assert week(2009, february, 7, sunday_weeks) == 1;
+        assert week(2009, february, 8, sunday_weeks) == 2;
+        assert week(2009, february, 13, sunday_weeks) == 2;
+        assert week(2009, february, 28, sunday_weeks) == 4;
+
+        // Its first day belongs to the last ISO week of January.
+        assert week(2009, february, 1, iso_weeks) == 5;
+        assert week(2009, february, 2, iso_weeks) == 1;
+        assert week(2009, february, 13, iso_weeks) == 2;
+
+        // March 2009 starts on a Sunday and ends on a Tuesday.
+        assert week(2009, march, 31, sunday_weeks) == 5;
+        assert week(2009, march, 31, iso_weeks) == 1;
+
+        // January 2009 starts on a Thursday.
+        assert week(2009, january, 1, iso_weeks) == 1;
+        assert week(2009, january, 1, sunday_weeks) == 1;
+        assert week(2009, january, 4, sunday_weeks) == 2;
+
+        let tm = result::get(tm_from_ymd(2009, february, 13));
+        assert tm.week_of_month(sunday_weeks) == 2;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_start_end_of() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        // Fri Feb 13 2009 23:31:30 UTC, 15:31:30 PST.
+        let t = at_utc({ sec: 1234567890_i64, nsec: 54321_i32 });
+
+        assert t.start_of_day(zone_utc).rfc3339() == "2009-02-13T00:00:00Z";
+        assert t.start_of_day(zone_local).rfc3339() ==
+            "2009-02-13T00:00:00-08:00";
+        assert t.start_of_day(zone_offset(19800_i32)).rfc3339() ==
+            "2009-02-14T00:00:00+05:30";
+        assert t.start_of_week(zone_utc).rfc3339() == "2009-02-08T00:00:00Z";
+        assert t.start_of_month(zone_utc).rfc3339() == "2009-02-01T00:00:00Z";
+        assert t.start_of_year(zone_local).rfc3339() ==
+            "2009-01-01T00:00:00-08:00";
+
+        let end = t.end_of_day(zone_utc);
+        assert end.rfc3339() == "2009-02-13T23:59:59Z";
+        assert end.tm_nsec == 999999999_i32;
+        assert t.end_of_day(zone_local).rfc3339() ==
+            "2009-02-13T23:59:59-08:00";
+        assert t.end_of_week(zone_utc).rfc3339() == "2009-02-14T23:59:59Z";
+        assert t.end_of_month(zone_utc).rfc3339() == "2009-02-28T23:59:59Z";
+        assert t.end_of_year(zone_utc).rfc3339() == "2009-12-31T23:59:59Z";
+
+        // The local day containing a DST change is 23 hours long.
+        let dst = at({ sec: 1236542400_i64, nsec: 0_i32 });
+        assert dst.start_of_day(zone_local).rfc3339() ==
+            "2009-03-08T00:00:00-08:00";
+        assert dst.end_of_day(zone_local).rfc3339() ==
+            "2009-03-08T23:59:59-07:00";
+    }
+
+    #[test]
+    fn test_next_prev_weekday() {
+        // A Friday.
+        let d = result::get(date_from_ymd(2009, february, 13));
+
+        assert d.next(monday).to_str() == "2009-02-16";
+        assert d.next(saturday).to_str() == "2009-02-14";
+        assert d.next(friday).to_str() == "2009-02-20";
+        assert d.prev(thursday).to_str() == "2009-02-12";
+        assert d.prev(saturday).to_str() == "2009-02-07";
+        assert d.prev(friday).to_str() == "2009-02-06";
+
+        let tm = result::get(tm_from_ymd(2009, february, 28));
+        assert tm.next(sunday).to_str() == "2009-03-01";
+        assert tm.prev(sunday).to_str() == "2009-02-22";
+    }
+
+    #[test]
+    fn test_nth_weekday_of_month() {
+        fn nth(y: int, m: month, wd: weekday, n: int) -> str {
+            result::get(nth_weekday_of_month(y, m, wd, n)).to_str()
+        }
+
+        // Thanksgiving, the fourth Thursday of November.
+        assert nth(2009, november, thursday, 4) == "2009-11-26";
+        // Options expiry, the third Friday.
+        assert nth(2009, february, friday, 3) == "2009-02-20";
+        // Memorial Day, the last Monday of May.
+        assert nth(2009, may, monday, -1) == "2009-05-25";
+        assert nth(2009, february, sunday, 1) == "2009-02-01";
+        assert nth(2009, february, saturday, -1) == "2009-02-28";
+        assert nth(2009, february, saturday, -4) == "2009-02-07";
+        assert nth(2008, february, friday, 5) == "2008-02-29";
+
+        assert nth_weekday_of_month(2009, february, friday, 5) ==
+            err(out_of_range("No such weekday in the month"));
+        assert nth_weekday_of_month(2009, february, friday, -5) ==
+            err(out_of_range("No such weekday in the month"));
+        assert nth_weekday_of_month(2009, february, friday, 0) ==
+            err(out_of_range("Invalid occurrence"));
+    }
+
+    #[test]
+    fn test_last_day_of_month() {
+        assert last_day_of_month(2008, february).to_str() == "2008-02-29";
+        assert last_day_of_month(2009, february).to_str() == "2009-02-28";
+        assert last_day_of_month(1900, february).to_str() == "1900-02-28";
+        assert last_day_of_month(2000, february).to_str() == "2000-02-29";
+        assert last_day_of_month(2009, april).to_str() == "2009-04-30";
+
+        let d = result::get(date_from_ymd(2009, december, 5));
+        assert d.last_day_of_month().to_str() == "2009-12-31";
+
+        let tm = result::get(tm_from_ymd_hms(2008, february, 3, 12, 0, 0));
+        assert tm.last_day_of_month().to_str() == "2008-02-29";
+        assert tm.end_of_month(zone_utc).rfc3339() == "2008-02-29T23:59:59Z";
+    }
+
+    #[test]
+    fn test_holiday_calendar() {
+        let cal = holiday_calendar();
+        cal.add("New Year's Day", fixed_date(january, 1));
+        cal.add("Leap Day", fixed_date(february, 29));
+        cal.add("Thanksgiving", nth_weekday(november, thursday, 4));
+        cal.add("Memorial Day", nth_weekday(may, monday, -1));
+        cal.add("Good Friday", easter_relative(-2));
+        cal.add("Easter Monday", easter_relative(1));
+        cal.add("Jubilee", one_off(result::get(date_from_ymd(2012, june,
+                                                             5))));
+
+        fn d(y: int, m: month, day: int) -> date {
+            result::get(date_from_ymd(y, m, day))
+        }
+
+        assert cal.holiday_on(d(2009, january, 1)) == some("New Year's Day");
+        assert cal.holiday_on(d(2009, november, 26)) == some("Thanksgiving");
+        assert cal.holiday_on(d(2009, may, 25)) == some("Memorial Day");
+        assert cal.holiday_on(d(2009, april, 10)) == some("Good Friday");
+        assert cal.holiday_on(d(2009, april, 13)) == some("Easter Monday");
+        assert cal.holiday_on(d(2012, june, 5)) == some("Jubilee");
+        assert cal.holiday_on(d(2013, june, 5)) == none;
+        assert cal.holiday_on(d(2009, february, 13)) == none;
+
+        assert cal.is_holiday(result::get(tm_from_ymd(2008, february, 29)));
+        assert !cal.is_holiday(result::get(tm_from_ymd(2009, march, 1)));
+
+        assert cal.is_business_day(d(2009, february, 13));
+        assert !cal.is_business_day(d(2009, february, 14));
+        assert !cal.is_business_day(d(2009, april, 10));
+
+        let days = cal.holidays_in(2009);
+        assert vec::len(days) == 5u;
+        assert days[0] == (d(2009, january, 1), "New Year's Day");
+        assert days[1] == (d(2009, april, 10), "Good Friday");
+        assert days[4] == (d(2009, november, 26), "Thanksgiving");
+    }
+
+    #[test]
+    fn test_easter() {
+        assert gregorian_easter(2009).to_str() == "2009-04-12";
+        assert gregorian_easter(2008).to_str() == "2008-03-23";
+        assert gregorian_easter(2011).to_str() == "2011-04-24";
+        assert gregorian_easter(1818).to_str() == "1818-03-22";
+        assert gregorian_easter(2038).to_str() == "2038-04-25";
+
+        assert julian_easter(2009).to_str() == "2009-04-19";
+        assert julian_easter(2008).to_str() == "2008-04-27";
+        assert julian_easter(2011).to_str() == "2011-04-24";
+        assert julian_easter(2012).to_str() == "2012-04-15";
+        assert julian_easter(2009).weekday() == sunday;
+    }
+
+    #[test]
+    fn test_calendar_diff() {
+        fn d(y: int, m: month, day: int) -> date {
+            result::get(date_from_ymd(y, m, day))
+        }
+
+        assert d(2009, january, 31).add_months(1) == d(2009, february, 28);
+        assert d(2008, march, 31).add_months(-1) == d(2008, february, 29);
+        assert d(2009, november, 15).add_months(3) == d(2010, february, 15);
+        assert d(2009, january, 15).add_months(-13) == d(2007, december, 15);
+
+        let p = calendar_diff(d(1974, december, 8), d(2009, february, 13));
+        assert p == { years: 34, months: 2, days: 5 };
+        assert p.to_str() == "34 years, 2 months, 5 days";
+
+        assert calendar_diff(d(2009, january, 31), d(2009, march, 1)) ==
+            { years: 0, months: 1, days: 1 };
+        assert calendar_diff(d(2009, january, 31), d(2009, february, 28)) ==
+            { years: 0, months: 0, days: 28 };
+        assert calendar_diff(d(2008, february, 29), d(2009, february, 28)) ==
+            { years: 0, months: 11, days: 30 };
+        assert calendar_diff(d(2008, february, 29), d(2009, march, 1)) ==
+            { years: 1, months: 0, days: 1 };
+        assert calendar_diff(d(2009, february, 13), d(2009, february, 13))
+            .to_str() == "0 days";
+        assert calendar_diff(d(2009, february, 13), d(2008, february, 12)) ==
+            { years: -1, months: 0, days: -1 };
+        assert calendar_diff(d(2009, february, 13), d(2010, march, 14))
+            .to_str() == "1 year, 1 month, 1 day";
+    }
+
+    #[test]
+    fn test_months_between() {
+        fn t(y: int, m: month, d: int) -> tm {
+            result::get(tm_from_ymd(y, m, d))
+        }
+
+        assert months_between(t(2009, march, 13), t(2009, february, 13)) ==
+            1.0;
+        assert months_between(t(2009, february, 13), t(2009, march, 13)) ==
+            -1.0;
+        assert months_between(t(2009, february, 28), t(2009, january, 31)) ==
+            1.0;
+        assert months_between(t(2008, february, 29), t(2007, february, 28)) ==
+            12.0;
+
+        // Oracle gives 1.03225806 for MONTHS_BETWEEN('02-02-1995',
+        // '01-01-1995').
+        let m = months_between(t(1995, february, 2), t(1995, january, 1));
+        assert m > 1.0322580 && m < 1.0322581;
+
+        let noon = result::get(tm_from_ymd_hms(1995, february, 2, 12, 0, 0));
+        let m = months_between(noon, t(1995, january, 1));
+        assert m > 1.0483870 && m < 1.0483871;
+
+        fn d(y: int, m: month, day: int) -> date {
+            result::get(date_from_ymd(y, m, day))
+        }
+        assert whole_months_between(d(2009, march, 12), d(2009, february, 13))
+            == 0;
+        assert whole_months_between(d(2009, march, 13), d(2009, february, 13))
+            == 1;
+        assert whole_months_between(d(2009, february, 13), d(2010, march, 14))
+            == -13;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_truncate() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let t = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+
+        let s = t.truncate(unit_second, zone_local);
+        assert s.tm_nsec == 0_i32 && s.tm_sec == 30 as c_int;
+        assert s.to_timespec() == { sec: 1234567890_i64, nsec: 0_i32 };
+        assert t.truncate(unit_minute, zone_local).rfc3339() ==
+            "2009-02-13T15:31:00-08:00";
+        assert t.truncate(unit_hour, zone_local).rfc3339() ==
+            "2009-02-13T15:00:00-08:00";
+        assert t.truncate(unit_day, zone_local).rfc3339() ==
+            "2009-02-13T00:00:00-08:00";
+        assert t.truncate(unit_day, zone_utc).rfc3339() ==
+            "2009-02-13T00:00:00Z";
+        assert t.truncate(unit_month, zone_local).rfc3339() ==
+            "2009-02-01T00:00:00-08:00";
+        assert t.truncate(unit_year, zone_utc).rfc3339() ==
+            "2009-01-01T00:00:00Z";
+    }
+
+    #[test]
+    fn test_round_to() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let quarter = duration_minutes(15_i64);
+
+        // 23:31:30.000054321 UTC.
+        assert t.floor_to(quarter) == { sec: 1234567800_i64, nsec: 0_i32 };
+        assert t.ceil_to(quarter) == { sec: 1234568700_i64, nsec: 0_i32 };
+        assert t.round_to(quarter) == { sec: 1234567800_i64, nsec: 0_i32 };
+        assert t.floor_to(duration_seconds(1_i64)) ==
+            { sec: 1234567890_i64, nsec: 0_i32 };
+        assert t.round_to(duration_millis(1_i64)) ==
+            { sec: 1234567890_i64, nsec: 0_i32 };
+        assert t.ceil_to(duration_micros(1_i64)) ==
+            { sec: 1234567890_i64, nsec: 55000_i32 };
+        assert t.round_to(duration_millis(1500_i64)) ==
+            { sec: 1234567890_i64, nsec: 0_i32 };
+        assert t.round_to(duration_days(1_i64)) ==
+            { sec: 1234569600_i64, nsec: 0_i32 };
+
+        // Exact multiples are unchanged, and halfway rounds up.
+        let hour = { sec: 1234566000_i64, nsec: 0_i32 };
+        assert hour.ceil_to(quarter) == hour;
+        assert hour.floor_to(quarter) == hour;
+        let half = { sec: 1234566450_i64, nsec: 0_i32 };
+        assert half.round_to(quarter) == { sec: 1234566900_i64, nsec: 0_i32 };
+
+        // Before the epoch.
+        let before = { sec: -1_i64, nsec: 500000000_i32 };
+        assert before.floor_to(duration_seconds(1_i64)) ==
+            { sec: -1_i64, nsec: 0_i32 };
+        assert before.round_to(duration_seconds(1_i64)) ==
+            { sec: 0_i64, nsec: 0_i32 };
+        assert before.floor_to(quarter) == { sec: -900_i64, nsec: 0_i32 };
+
+        assert duration_millis(-1500_i64) ==
+            { secs: -2_i64, nanos: 500000000_i32 };
+        assert t.add(duration_millis(-1500_i64)).since(t) ==
+            duration_millis(-1500_i64);
+        assert t.sub(duration_hours(1_i64)) ==
+            { sec: 1234564290_i64, nsec: 54321_i32 };
+    }
+
+    #[test]
+    fn test_humanize() {
+        let now = at_utc({ sec: 1234567890_i64, nsec: 0_i32 });
+        fn rel(now: tm, secs: i64) -> str {
+            at_utc({ sec: 1234567890_i64 + secs, nsec: 0_i32 })
+                .humanize_relative_to(now)
+        }
+
+        assert rel(now, 0_i64) == "just now";
+        assert rel(now, -59_i64) == "just now";
+        assert rel(now, 30_i64) == "just now";
+        assert rel(now, -60_i64) == "1 minute ago";
+        assert rel(now, -150_i64) == "2 minutes ago";
+        assert rel(now, 3600_i64 * 3_i64 + 59_i64) == "in 3 hours";
+        assert rel(now, -3600_i64 * 3_i64) == "3 hours ago";
+        assert rel(now, 86400_i64 * 2_i64) == "in 2 days";
+        assert rel(now, -86400_i64) == "1 day ago";
+        assert rel(now, -86400_i64 * 29_i64) == "29 days ago";
+        assert rel(now, -86400_i64 * 30_i64) == "2009-01-14";
+        assert rel(now, 86400_i64 * 365_i64) == "2010-02-13";
+
+        let then = at_utc({ sec: 1234567890_i64 - 7200_i64, nsec: 0_i32 });
+        assert then.humanize_relative_to_with(now, duration_hours(1_i64),
+                                              "%T") == "21:31:30";
+    }
+
+    #[test]
+    fn test_epoch_units() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert t.to_millis() == ok(1234567890000_i64);
+        assert t.to_micros() == ok(1234567890000054_i64);
+        assert t.to_nanos() == ok(1234567890000054321_i64);
+
+        let before = { sec: -2_i64, nsec: 999999999_i32 };
+        assert before.to_millis() == ok(-1001_i64);
+        assert before.to_nanos() == ok(-1000000001_i64);
+
+        assert timespec_from_millis(1234567890000_i64) ==
+            { sec: 1234567890_i64, nsec: 0_i32 };
+        assert timespec_from_micros(1234567890000054_i64) ==
+            { sec: 1234567890_i64, nsec: 54000_i32 };
+        assert timespec_from_nanos(1234567890000054321_i64) == t;
+        assert timespec_from_millis(-1001_i64) ==
+            { sec: -2_i64, nsec: 999000000_i32 };
+        assert timespec_from_nanos(-1_i64) ==
+            { sec: -1_i64, nsec: 999999999_i32 };
+
+        let far = { sec: 10000000000_i64, nsec: 0_i32 };
+        assert far.to_millis() == ok(10000000000000_i64);
+        assert far.to_nanos() == err(overflow);
+        let edge = { sec: 9223372036_i64, nsec: 854775807_i32 };
+        assert edge.to_nanos() == ok(i64_max);
+        let past_edge = { nsec: 854775808_i32 with edge };
+        assert past_edge.to_nanos() == err(overflow);
+        let low = { sec: -9223372037_i64, nsec: 145224192_i32 };
+        assert low.to_nanos() == ok(i64_min);
+        assert low.to_millis() == ok(-9223372036855_i64);
+        let past_low = { nsec: 145224191_i32 with low };
+        assert past_low.to_nanos() == err(overflow);
+    }
+
+    #[test]
+    fn test_js_millis() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert t.to_js_millis() == 1234567890000.0;
+        let late = { nsec: 999000000_i32 with t };
+        assert late.to_js_millis() == 1234567890999.0;
+        let before = { sec: -1_i64, nsec: 500000000_i32 };
+        assert before.to_js_millis() == -500.0;
+
+        assert timespec_from_js_millis(1234567890123.0) ==
+            ok({ sec: 1234567890_i64, nsec: 123000000_i32 });
+        assert timespec_from_js_millis(-500.0) ==
+            ok({ sec: -1_i64, nsec: 500000000_i32 });
+        assert timespec_from_js_millis(1.5) ==
+            ok({ sec: 0_i64, nsec: 1500000_i32 });
+        assert timespec_from_js_millis(8.64e15) ==
+            ok({ sec: 8640000000000_i64, nsec: 0_i32 });
+        assert timespec_from_js_millis(8.64e15 + 1.0) ==
+            err(out_of_range("Invalid JavaScript time"));
+        assert timespec_from_js_millis(float::NaN as f64) ==
+            err(out_of_range("Invalid JavaScript time"));
+        assert timespec_from_js_millis(float::infinity as f64) ==
+            err(out_of_range("Invalid JavaScript time"));
+    }
+
+    #[test]
+    fn test_julianday() {
+        let epoch = { sec: 0_i64, nsec: 0_i32 };
+        assert epoch.to_julianday_f64() == 2440587.5;
+
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert t.to_julianday_f64() == 2454875.5 + 84690.0 / 86400.0;
+
+        assert timespec_from_julianday_f64(2440587.5) == ok(epoch);
+        assert timespec_from_julianday_f64(2440588.0) ==
+            ok({ sec: 43200_i64, nsec: 0_i32 });
+        assert timespec_from_julianday_f64(t.to_julianday_f64()) ==
+            ok({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert timespec_from_julianday_f64(0.0) ==
+            ok({ sec: -210866760000_i64, nsec: 0_i32 });
+
+        assert timespec_from_julianday_f64(-1.0) ==
+            err(out_of_range("Invalid Julian day"));
+        assert timespec_from_julianday_f64(5373485.0) ==
+            err(out_of_range("Invalid Julian day"));
+        assert timespec_from_julianday_f64(float::NaN as f64) ==
+            err(out_of_range("Invalid Julian day"));
+    }
+
+    #[test]
+    fn test_uuid_timestamp() {
+        let epoch = { sec: 0_i64, nsec: 0_i32 };
+        assert epoch.to_uuid_timestamp() == ok(122192928000000000u64);
+        assert epoch.to_uuid_time_bytes() ==
+            ok([0x13u8, 0x81u8, 0x40u8, 0x00u8, 0x1du8, 0xd2u8, 0x11u8,
+                0xb2u8]);
+
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let ts = result::get(t.to_uuid_timestamp());
+        assert ts == 134538606900000543u64;
+        assert timespec_from_uuid_timestamp(ts) ==
+            ok({ sec: 1234567890_i64, nsec: 54300_i32 });
+        assert timespec_from_uuid_timestamp(0u64) ==
+            ok({ sec: -12219292800_i64, nsec: 0_i32 });
+
+        let uuid = result::get(t.to_uuid_time_bytes()) +
+            [0x80u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+        assert timespec_from_uuid(uuid) ==
+            ok({ sec: 1234567890_i64, nsec: 54300_i32 });
+
+        let v4 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0x40u8, 0u8,
+                  0x80u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+        assert timespec_from_uuid(v4) ==
+            err(parse_error("Not a version 1 UUID"));
+        assert timespec_from_uuid([1u8]) ==
+            err(parse_error("Expected 16 bytes"));
+
+        let before = { sec: -12219292801_i64, nsec: 0_i32 };
+        assert before.to_uuid_timestamp() ==
+            err(out_of_range("Out of UUID range"));
+        assert timespec_from_uuid_timestamp(1152921504606846976u64) ==
+            err(out_of_range("Out of UUID range"));
+    }
+
+    #[test]
+    fn test_stat_times() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        assert timespec_from_stat(1234567890_i64, some(54321_i64)) ==
+            { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert timespec_from_stat(1234567890_i64, none) ==
+            { sec: 1234567890_i64, nsec: 0_i32 };
+        assert timespec_from_stat(-1_i64, some(1500000000_i64)) ==
+            { sec: 0_i64, nsec: 500000000_i32 };
+
+        let t = tm_from_stat(1234567890_i64, some(54321_i64));
+        assert t.strftime("%F %T") == "2009-02-13 15:31:30";
+        assert t.tm_nsec == 54321_i32;
+
+        let times = result::get(get_file_times("."));
+        assert times.modified.sec > 0_i64;
+        assert times.modified.nsec >= 0_i32;
+        assert get_file_times("/nonexistent/path") ==
+            err(platform_error("Could not stat /nonexistent/path"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_freestanding))]
+    fn test_set_file_times() {
+        let path = "test_set_file_times.tmp";
+        result::get(io::file_writer(path, [io::create, io::truncate]))
+            .write_str("time");
+
+        let atime = { sec: 1000000000_i64, nsec: 0_i32 };
+        let mtime = { sec: 1234567890_i64, nsec: 0_i32 };
+        assert set_file_times(path, atime, mtime) == ok(());
+
+        let times = result::get(get_file_times(path));
+        assert times.accessed == atime;
+        assert times.modified == mtime;
+        os::remove_file(path);
+
+        assert set_file_times("/nonexistent/path", atime, mtime) ==
+            err(platform_error(
+                "Could not set the times of /nonexistent/path"));
+    }
+
+    #[test]
+    #[ignore(cfg(time_freestanding))]
+    fn test_process_start_time() {
+        let start = result::get(process_start_time());
+        let now = get_time();
+        assert start.sec > 0_i64;
+        assert start.le(now);
+
+        let uptime = result::get(process_uptime());
+        assert uptime.secs >= 0_i64;
+        assert uptime.secs <= now.sec - start.sec + 1_i64;
+    }
+
+    #[test]
+    fn test_difftime() {
+        let a = { sec: 1234567890_i64, nsec: 500000000_i32 };
+        let b = { sec: 1234567880_i64, nsec: 750000000_i32 };
+        assert difftime(a, b) == 9.75;
+        assert difftime(b, a) == -9.75;
+        assert difftime(a, a) == 0.0;
+
+        assert difftime_exact(a, b) == duration_millis(9750_i64);
+        assert difftime_exact(b, a) ==
+            { secs: -10_i64, nanos: 250000000_i32 };
+    }
+
+    #[test]
+    fn test_pre_epoch() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let t = at_utc({ sec: -1_i64, nsec: 500_i32 });
+        assert t.strftime("%F %T") == "1969-12-31 23:59:59";
+        assert t.tm_wday == 3 as c_int;
+        assert t.tm_yday == 364 as c_int;
+        assert t.tm_nsec == 500_i32;
+        assert t.to_timespec() == { sec: -1_i64, nsec: 500_i32 };
+
+        let first = at_utc({ sec: -62135596800_i64, nsec: 0_i32 });
+        assert first.strftime("%F %T") == "0001-01-01 00:00:00";
+        assert first.strftime("%C %y") == "00 01";
+        assert first.tm_wday == 1 as c_int;
+        assert first.to_timespec().sec == -62135596800_i64;
+
+        let moon = at_utc({ sec: -14182940_i64, nsec: 0_i32 });
+        assert moon.strftime("%a %F %T %C %y") ==
+            "Sun 1969-07-20 20:17:40 19 69";
+
+        // Out of range fields are carried, as with timegm.
+        let carried = { tm_mon: 12 as c_int, tm_mday: 0 as c_int
+                        with first };
+        assert carried.to_timespec().sec == -62104147200_i64;
+    }
+
+    #[test]
+    fn test_extended_years() {
+        // 44 BC is year -43.
+        let ides = result::get(tm_from_ymd(-43, march, 15));
+        assert ides.tm_year == -1943 as c_int;
+        assert ides.strftime("%Y-%m-%d %a") == "-0043-03-15 Fri";
+        assert ides.to_timespec().sec == -63517824000_i64;
+        assert at_utc(ides.to_timespec()) == ides;
+
+        // Year 0 is 1 BC, and a leap year.
+        assert is_leap_year(0);
+        let zero = result::get(tm_from_ymd(0, february, 29));
+        assert zero.strftime("%F") == "0000-02-29";
+        assert at_utc({ sec: zero.to_timespec().sec + 86400_i64,
+                        nsec: 0_i32 }).strftime("%F") == "0000-03-01";
+
+        let far = at_utc({ sec: 253402300800_i64, nsec: 0_i32 });
+        assert far.strftime("%F %T") == "10000-01-01 00:00:00";
+        assert far.to_timespec().sec == 253402300800_i64;
+
+        let d = result::get(date_from_ymd(-43, march, 15));
+        assert d.to_str() == "-0043-03-15";
+        assert date_from_str("-0043-03-15") == ok(d);
+        assert date_from_str("+2009-02-13") == date_from_ymd(2009, february,
+                                                             13);
+
+        let parsed = result::get(strptime("-0043-03-15", "%Y-%m-%d"));
+        assert parsed.tm_year == -1943 as c_int;
+        assert strptime("-43-03-15", "%Y-%m-%d") ==
+            err(parse_error("Invalid year"));
+    }
+
+    #[test]
+    fn test_iso_expanded() {
+        let far = result::get(date_from_ymd(10000, january, 1));
+        assert far.to_iso_expanded(5u) == "+10000-01-01";
+        assert far.to_iso_expanded(6u) == "+010000-01-01";
+
+        let old = result::get(date_from_ymd(-333, february, 13));
+        assert old.to_iso_expanded(4u) == "-0333-02-13";
+        assert old.to_iso_expanded(5u) == "-00333-02-13";
+
+        assert date_from_iso_expanded("+10000-01-01", 5u) == ok(far);
+        assert date_from_iso_expanded("-0333-02-13", 4u) == ok(old);
+        assert date_from_iso_expanded("-00333-02-13", 5u) == ok(old);
+        assert date_from_iso_expanded("10000-01-01", 5u) ==
+            err(parse_error("Invalid year"));
+        assert date_from_iso_expanded("+10000-01-01", 6u) ==
+            err(parse_error("Invalid year"));
+
+        let tm = result::get(strptime("+10000-01-01", "%F"));
+        assert tm.year() == 10000;
+        assert tm.strftime("%F") == "10000-01-01";
+        assert date_from_str("-0333-02-13") == ok(old);
+    }
+
+    #[test]
+    fn test_hebrew() {
+        import calendar::hebrew;
+
+        let d = result::get(date_from_ymd(2009, february, 13));
+        let h = hebrew::from_gregorian(d);
+        assert h == { year: 5769, month: 11, day: 19 };
+        assert h.to_str() == "19 Shevat 5769";
+        assert hebrew::to_gregorian(h) == ok(d);
+
+        let rosh = result::get(date_from_ymd(2009, september, 19));
+        assert hebrew::from_gregorian(rosh) ==
+            { year: 5770, month: 7, day: 1 };
+
+        let purim = result::get(date_from_ymd(2011, march, 20));
+        let h = hebrew::from_gregorian(purim);
+        assert h == { year: 5771, month: 13, day: 14 };
+        assert h.month_name() == "Adar II";
+        let adar = { month: 12, day: 2 with h };
+        assert adar.month_name() == "Adar I";
+        assert hebrew::to_gregorian(adar) == date_from_ymd(2011, february, 6);
+
+        let unix = result::get(date_from_ymd(1970, january, 1));
+        assert hebrew::from_gregorian(unix).to_str() == "23 Tevet 5730";
+        let pesach = result::get(date_from_ymd(2024, april, 23));
+        assert hebrew::from_gregorian(pesach) ==
+            { year: 5784, month: 1, day: 15 };
+
+        assert hebrew::is_leap_year(5771);
+        assert !hebrew::is_leap_year(5769);
+        assert hebrew::days_in_year(5769) == 354;
+        assert hebrew::days_in_year(5770) == 355;
+        assert hebrew::days_in_year(5771) == 385;
+
+        assert hebrew::to_gregorian({ year: 5769, month: 13, day: 1 }) ==
+            err(out_of_range("Invalid month"));
+        assert hebrew::to_gregorian({ year: 5769, month: 12, day: 30 }) ==
+            err(out_of_range("Invalid day of the month"));
+    }
+
+    #[test]
+    fn test_japanese_era() {
+        import calendar::japanese;
+
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        assert utc.strftime("%EC") == "平成";
+        assert utc.strftime("%Ey") == "21";
+        assert utc.strftime("%EY%m月%d日") == "平成21年02月13日";
+        assert utc.strftime("%Ex") == utc.strftime("%x");
+
+        let first = result::get(tm_from_ymd(2019, may, 1));
+        assert first.strftime("%EY") == "令和元年";
+        let last_showa = result::get(tm_from_ymd(1989, january, 7));
+        assert last_showa.strftime("%EY") == "昭和64年";
+
+        let early = result::get(tm_from_ymd(1850, january, 1));
+        assert early.strftime("%EC %EY") == "18 1850";
+
+        let t = result::get(strptime("平成21年02月13日", "%EY%m月%d日"));
+        assert t.tm_year == 109 as c_int;
+        assert t.tm_mon == 1 as c_int;
+        assert t.tm_mday == 13 as c_int;
+
+        let t = result::get(strptime("令和元年", "%EY"));
+        assert t.year() == 2019;
+        let t = result::get(strptime("昭和 64", "%EC %Ey"));
+        assert t.year() == 1989;
+        assert strptime("天保3年", "%EY") ==
+            err(parse_error("Invalid era"));
+
+        assert japanese::to_gregorian_year("大正", 15) == some(1926);
+        assert japanese::to_gregorian_year("天保", 3) == none;
+        let d = result::get(date_from_ymd(1926, december, 25));
+        alt japanese::era_of(d) {
+          some((e, y)) { assert e.name == "昭和"; assert y == 1; }
+          none { fail; }
+        }
+    }
+
+    #[test]
+    fn test_buddhist_era() {
+        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let utc = at_utc(time);
+        assert utc.strftime_with_era("%Y", era_buddhist) == "2552";
+        assert utc.strftime_with_era("%d/%m/%Y", era_buddhist) ==
+            "13/02/2552";
+        assert utc.strftime_with_era("%EY", era_buddhist) == "พ.ศ. 2552";
+        assert utc.strftime_with_era("%EC %Ey", era_buddhist) == "พ.ศ. 2552";
+        assert utc.strftime_with_era("%a %s", era_buddhist) ==
+            "Fri 1234567890";
+        assert utc.strftime_with_era("%EY", era_japanese) == "平成21年";
+
+        let t = result::get(strptime_with_era("13/02/2552", "%d/%m/%Y",
+                                              era_buddhist));
+        assert t.year() == 2009;
+        assert t.tm_wday == 5 as c_int;
+        let t = result::get(strptime_with_era("พ.ศ. 2552", "%EY",
+                                              era_buddhist));
+        assert t.year() == 2009;
+        let t = result::get(strptime_with_era("2552-02-13", "%F",
+                                              era_buddhist));
+        assert t.year() == 2009;
+        assert strptime_with_era("2552", "%EY", era_buddhist) ==
+            err(parse_error("Invalid era"));
+    }
+
+    #[test]
+    fn test_chinese() {
+        import calendar::chinese;
+
+        fn chinese_ymd(y: int, m: month, d: int) -> chinese::chinese_date {
+            result::get(chinese::from_gregorian(
+                result::get(date_from_ymd(y, m, d))))
+        }
+
+        let d = chinese_ymd(2009, february, 13);
+        assert d == { cycle: 78, year: 26, month: 1, leap_month: false,
+                      day: 19 };
+
+        // New year's eve and day.
+        assert chinese_ymd(2009, january, 25) ==
+            { cycle: 78, year: 25, month: 12, leap_month: false, day: 30 };
+        assert chinese_ymd(2024, february, 10) ==
+            { cycle: 78, year: 41, month: 1, leap_month: false, day: 1 };
+        assert chinese_ymd(1970, january, 1) ==
+            { cycle: 77, year: 46, month: 11, leap_month: false, day: 24 };
+
+        // 2009 repeated its fifth month, and 2033 its eleventh.
+        assert chinese_ymd(2009, june, 23) ==
+            { cycle: 78, year: 26, month: 5, leap_month: true, day: 1 };
+        assert chinese_ymd(2009, july, 22) ==
+            { cycle: 78, year: 26, month: 6, leap_month: false, day: 1 };
+        assert chinese_ymd(2033, december, 22) ==
+            { cycle: 78, year: 50, month: 11, leap_month: true, day: 1 };
+
+        let early = result::get(date_from_ymd(1900, december, 31));
+        assert chinese::from_gregorian(early) ==
+            err(out_of_range("Date out of supported range"));
+    }
+
+    #[test]
+    fn test_ethiopian() {
+        import calendar::ethiopian;
+
+        let d = result::get(date_from_ymd(2009, february, 13));
+        let e = ethiopian::from_gregorian(d);
+        assert e == { year: 2001, month: 6, day: 6 };
+        assert e.to_str() == "6 Yekatit 2001";
+        assert ethiopian::to_gregorian(e) == ok(d);
+
+        // New year follows a six day Pagume after a leap year.
+        let new_year = result::get(date_from_ymd(2009, september, 11));
+        assert ethiopian::from_gregorian(new_year) ==
+            { year: 2002, month: 1, day: 1 };
+        let eve = ethiopian::from_gregorian(new_year.add_days(-1_i64));
+        assert eve == { year: 2001, month: 13, day: 5 };
+        assert eve.month_name() == "Pagume";
+        assert ethiopian::is_leap_year(1999);
+        let after_leap = result::get(date_from_ymd(2007, september, 12));
+        assert ethiopian::from_gregorian(after_leap) ==
+            { year: 2000, month: 1, day: 1 };
+
+        let unix = result::get(date_from_ymd(1970, january, 1));
+        assert ethiopian::from_gregorian(unix).to_str() == "23 Tahsas 1962";
+
+        assert ethiopian::to_gregorian({ year: 2001, month: 13, day: 6 }) ==
+            err(out_of_range("Invalid day of the month"));
+        assert ethiopian::to_gregorian({ year: 2001, month: 14, day: 1 }) ==
+            err(out_of_range("Invalid month"));
+    }
+
+    #[test]
+    fn test_julian() {
+        import calendar::julian;
+
+        let d = result::get(date_from_ymd(2009, february, 13));
+        let j = julian::from_gregorian(d);
+        assert j == { year: 2009, month: 1, day: 31 };
+        assert julian::to_gregorian(j) == ok(d);
+        assert julian::to_gregorian({ year: 1900, month: 2, day: 29 }) ==
+            date_from_ymd(1900, march, 13);
+        assert julian::to_gregorian({ year: 1901, month: 2, day: 29 }) ==
+            err(out_of_range("Invalid day of the month"));
+
+        // Britain went from September 2nd to September 14th, 1752.
+        let adoption = julian::british_adoption();
+        assert julian::from_historical(1752, 9, 2, adoption) ==
+            date_from_ymd(1752, september, 13);
+        assert julian::from_historical(1752, 9, 14, adoption) ==
+            date_from_ymd(1752, september, 14);
+        assert julian::from_historical(1752, 9, 10, adoption) ==
+            err(out_of_range("Date skipped by the calendar reform"));
+
+        // Newton was born on Christmas Day 1642, old style.
+        let newton = result::get(julian::from_historical(1642, 12, 25,
+                                                         adoption));
+        assert newton == result::get(date_from_ymd(1643, january, 4));
+        assert julian::to_historical(newton, adoption) ==
+            { year: 1642, month: 12, day: 25, old_style: true };
+        assert julian::to_historical(newton, julian::papal_adoption()) ==
+            { year: 1643, month: 1, day: 4, old_style: false };
+
+        let papal = julian::papal_adoption();
+        assert julian::from_historical(1582, 10, 4, papal) ==
+            date_from_ymd(1582, october, 14);
+        assert julian::from_historical(1582, 10, 5, papal) ==
+            err(out_of_range("Date skipped by the calendar reform"));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_strptime() {
+        let n = 100000u;
+        let start = time::precise_time_ns();
+        let mut i = 0u;
+        while i < n {
+            strptime("Friday, 13 February 2009 11:31:30 PM",
+                     "%A, %d %B %Y %I:%M:%S %p");
+            strptime("Fri Feb 13 23:31:30 2009", "%c");
+            strptime("Sat Dec 13", "%a %b %d");
+            i += 1u;
+        }
+        let elapsed = time::precise_time_ns() - start;
+        io::println(#fmt("strptime: %u ns/iter",
+                         (elapsed / (n as u64)) as uint));
+    }
+
+    #[test]
+    fn test_strftime_digits() {
+        let t = result::get(tm_from_ymd_hms(2009, january, 5, 7, 3, 9));
+        assert t.strftime("%d %e %H %k %I %l %M %S %m %j %y %C") ==
+            "05  5 07  7 07  7 03 09 01 005 09 20";
+        let t = result::get(tm_from_ymd_hms(44, december, 31, 23, 59, 59));
+        assert t.strftime("%Y %j %y %C") == "0044 366 44 00";
+
+        // Out of range fields still format like %02d.
+        let odd = { tm_sec: 100 as c_int, tm_min: -1 as c_int with t };
+        assert odd.strftime("%S %M") == "100 -1";
+    }
+
+    #[test]
+    fn test_log_formatter() {
+        let f = log_formatter("%F %T", zone_utc, 3u);
+        let t = { sec: 1234567890_i64, nsec: 123456789_i32 };
+        assert f.format(t) == "2009-02-13 23:31:30.123";
+        assert f.format({ nsec: 4000000_i32 with t }) ==
+            "2009-02-13 23:31:30.004";
+        assert f.format({ sec: 1234567891_i64, nsec: 0_i32 }) ==
+            "2009-02-13 23:31:31.000";
+
+        let f = log_formatter("%T", zone_offset(3600_i32), 9u);
+        assert f.format(t) == "00:31:30.123456789";
+        let f = log_formatter("%T", zone_utc, 0u);
+        assert f.format(t) == "23:31:30";
+    }
+
+    #[test]
+    fn test_format_buffer() {
+        let b = format_buffer();
+        let utc = at_utc({ sec: 1234567890_i64, nsec: 54321_i32 });
+        assert b.rfc3339(utc) {|s| s == utc.rfc3339() };
+        assert b.ctime(utc) {|s| s == "Fri Feb 13 23:31:30 2009" };
+        assert b.strftime(utc, "%F") {|s| s == "2009-02-13" };
+
+        let ist = at_zone({ sec: 1234567890_i64, nsec: 0_i32 },
+                          zone_offset(19800_i32));
+        assert b.rfc3339(ist) {|s| s == "2009-02-14T05:01:30+05:30" };
+        assert b.rfc3339(ist) {|s| s == ist.rfc3339() };
+    }
+
+    #[test]
+    fn test_conversion_cache() {
+        let cache = conversion_cache(zone_utc);
+        let mut sec = 1234483200_i64;
+        while sec < 1234656000_i64 {
+            let t = { sec: sec, nsec: 54321_i32 };
+            assert cache.at(t) == at_utc(t);
+            sec += 1789_i64;
+        }
+
+        let cache = conversion_cache(zone_offset(-28800_i32));
+        let t = { sec: 1234567890_i64, nsec: 0_i32 };
+        let expected = at_zone(t, zone_offset(-28800_i32));
+        assert cache.at(t) == expected;
+        assert cache.at({ sec: 1234567891_i64 with t }) ==
+            { tm_sec: 31 as c_int with expected };
+        assert cache.at({ sec: 1234598400_i64 with t }).tm_mday ==
+            14 as c_int;
+    }
+
+    #[test]
+    fn test_rfc_single_pass() {
+        let t = { sec: 1234567890_i64, nsec: 0_i32 };
+        for vec::each([at_utc(t), at_utc({ sec: -62135596800_i64 with t }),
+                       at_zone(t, zone_offset(19800_i32)),
+                       at_zone(t, zone_offset(-28800_i32)),
+                       at_zone(t, zone_offset(-30_i32))]) {|x|
+            let prefix = x.strftime("%Y-%m-%dT%H:%M:%S");
+            if x.tm_gmtoff == 0 as c_long {
+                assert x.rfc3339() == prefix + "Z";
+                assert x.rfc822() == x.strftime("%a, %d %b %Y %T GMT");
+            } else {
+                assert x.rfc3339() == prefix + str::slice(
+                    x.strftime("%z"), 0u, 3u) + ":" + str::slice(
+                    x.strftime("%z"), 3u, 5u);
+                assert x.rfc822() == x.strftime("%a, %d %b %Y %T %Z");
+            }
+            assert x.rfc822z() == x.strftime("%a, %d %b %Y %T %z");
+        }
+
+        let utc = at_utc_tm(t);
+        assert utc.rfc822() == "Fri, 13 Feb 2009 23:31:30 GMT";
+        assert utc.rfc3339() == "2009-02-13T23:31:30Z";
+        assert at_utc(t).rfc822z() == "Fri, 13 Feb 2009 23:31:30 -0000";
+    }
+
+    #[test]
+    fn test_local_backend() {
+        // These hold for both the C library and the pure Rust backends.
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        let local = at(t);
+        assert local.to_timespec() == t;
+        assert at_zone(t, zone_local) == local;
+
+        let midnight = local.start_of_day(zone_local);
+        assert midnight.tm_mday == local.tm_mday;
+        assert midnight.tm_hour == 0 as c_int;
+        assert midnight.tm_min == 0 as c_int;
+    }
+
+    #[test]
+    fn test_error() {
+        alt strptime("2009-13-01", "%Y-%m-%d") {
+          err(e) {
+            assert e == parse_error("Invalid month");
+            assert e.to_str() == "Invalid month";
+          }
+          ok(_) { fail; }
+        }
+        let e = result::get_err(date_from_str("2009-02-30"));
+        assert e == out_of_range("Invalid day of the month");
+        assert unknown_zone("XYZ").to_str() == "Unknown zone XYZ";
+        assert overflow.to_str() == "Overflow";
+    }
+
+    #[test]
+    fn test_try_at() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert try_at_utc(t) == ok(at_utc(t));
+        assert try_at(t) == ok(at(t));
+
+        let last = { sec: 67767976233532799_i64, nsec: 999999999_i32 };
+        let tm = result::get(try_at_utc(last));
+        assert tm.year() == 2147483647;
+        assert tm.strftime("%m-%d %T") == "12-31 23:59:59";
+        let first = { sec: -67768040609740800_i64, nsec: 0_i32 };
+        assert result::get(try_at_utc(first)).year() == -2147481748;
+
+        let out = err(out_of_range("Time out of range"));
+        assert try_at_utc({ sec: last.sec + 1_i64 with last }) == out;
+        assert try_at_utc({ sec: first.sec - 1_i64 with first }) == out;
+        assert try_at({ sec: i64_max with t }) == out;
+        assert try_at_utc({ nsec: 1000000000_i32 with t }) ==
+            err(out_of_range("Invalid nanosecond"));
+        assert try_at({ nsec: -1_i32 with t }) ==
+            err(out_of_range("Invalid nanosecond"));
+    }
+
+    #[test]
+    fn test_checked_to_timespec() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert at_utc(t).checked_to_timespec() == ok(t);
+
+        // Nanoseconds outside of their range are carried like the other
+        // fields.
+        let tm = { tm_nsec: 1500000000_i32 with at_utc(t) };
+        assert tm.to_timespec() ==
+            { sec: 1234567891_i64, nsec: 500000000_i32 };
+        let tm = { tm_nsec: -1_i32 with at_utc(t) };
+        assert tm.to_timespec() ==
+            { sec: 1234567889_i64, nsec: 999999999_i32 };
+
+        let last = at_utc({ sec: tm_max_sec, nsec: 0_i32 });
+        assert last.checked_to_timespec() == ok({ sec: tm_max_sec,
+                                                  nsec: 0_i32 });
+        let past = { tm_sec: 60 as c_int with last };
+        assert past.checked_to_timespec() == err(overflow);
+        let huge = { tm_mon: 2147483647 as c_int with last };
+        assert huge.checked_to_timespec() == err(overflow);
+
+        let first = at_utc({ sec: tm_min_sec, nsec: 0_i32 });
+        assert first.to_timespec().sec == tm_min_sec;
+        let before = { tm_mon: -1 as c_int with first };
+        assert before.checked_to_timespec() == err(overflow);
+    }
+
+    #[test]
+    #[should_fail]
+    #[ignore(cfg(target_os = "win32"))]
+    fn test_to_timespec_overflow() {
+        let last = at_utc({ sec: tm_max_sec, nsec: 0_i32 });
+        let past = { tm_mday: 32 as c_int with last };
+        past.to_timespec();
+    }
+
+    #[test]
+    fn test_to_timespec_gmtoff() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        for vec::each([19800_i32, -28800_i32, 3600_i32, -1_i32]) {|off|
+            let tm = at_zone(t, zone_offset(off));
+            assert tm.tm_gmtoff == off as c_long;
+            assert tm.to_timespec() == t;
+        }
+
+        let ist = result::get(parse_rfc3339("2009-02-14T05:01:30+05:30"));
+        assert ist.to_timespec() == { sec: 1234567890_i64, nsec: 0_i32 };
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_resolve_local() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        // The DST flag given is ignored.
+        let fields = {
+            tm_sec: 30 as c_int,
+            tm_min: 31 as c_int,
+            tm_hour: 15 as c_int,
+            tm_mday: 13 as c_int,
+            tm_mon: 1 as c_int,
+            tm_year: 109 as c_int,
+            tm_isdst: 1 as c_int,
+            tm_nsec: 54321_i32
+            with empty_tm()
+        };
+        let tm = result::get(resolve_local(fields));
+        assert tm.tm_isdst == 0 as c_int;
+        assert tm.tm_gmtoff == -28800 as c_long;
+        assert tm.to_timespec() == { sec: 1234567890_i64, nsec: 54321_i32 };
+
+        let summer = { tm_mon: 2 as c_int, tm_mday: 8 as c_int,
+                       tm_hour: 12 as c_int, tm_isdst: 0 as c_int
+                       with fields };
+        let tm = result::get(resolve_local(summer));
+        assert tm.tm_isdst == 1 as c_int;
+        assert tm.tm_gmtoff == -25200 as c_long;
+
+        // 2:30 was skipped when the clocks went forward.
+        let skipped = { tm_hour: 2 as c_int with summer };
+        let tm = result::get(resolve_local(skipped));
+        assert tm.strftime("%F %T") == "2009-03-08 03:31:30";
+
+        let carried = { tm_mon: 12 as c_int, tm_mday: 1 as c_int
+                        with fields };
+        let tm = result::get(resolve_local(carried));
+        assert tm.strftime("%F %T") == "2010-01-01 15:31:30";
+
+        let huge = { tm_year: 2147483647 as c_int with fields };
+        assert resolve_local(huge) == err(overflow);
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_is_dst_at() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let winter = { sec: 1234567890_i64, nsec: 0_i32 };
+        assert !is_dst_at(winter);
+        assert local_offset_at(winter) == -28800_i32;
+
+        // 2009-03-08 10:00:00 UTC, just after the clocks went forward.
+        let summer = { sec: 1236506400_i64, nsec: 0_i32 };
+        assert is_dst_at(summer);
+        assert local_offset_at(summer) == -25200_i32;
+        assert !is_dst_at({ sec: summer.sec - 1_i64 with summer });
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_local_zone_info() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let info = local_zone_info_at({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert info.std_name == "PST";
+        assert info.std_offset == -28800_i32;
+        assert info.dst_name == some("PDT");
+        assert info.dst_offset == some(-25200_i32);
+
+        os::setenv("TZ", "Asia/Tokyo");
+
+        let info = local_zone_info_at({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert info.std_name == "JST";
+        assert info.std_offset == 32400_i32;
+        assert info.dst_name == none;
+        assert info.dst_offset == none;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_zone_abbrev() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let time = { sec: 1234567890_i64, nsec: 0_i32 };
+        assert at_utc(time).strftime("%Z") == "UTC";
+        assert at(time).strftime("%Z") == "PST";
+        assert zone_abbrev(empty_tm()) == "UTC";
+
+        let tm = {
+            tm_gmtoff: -28800 as c_long,
+            tm_zone: "Pacific Standard Time"
+            with empty_tm()
+        };
+        assert zone_abbrev(tm) == "PST";
+        assert zone_abbrev({ tm_gmtoff: 32400 as c_long,
+                             tm_zone: "Tokyo Standard Time" with tm })
+            == "JST";
+        assert zone_abbrev({ tm_gmtoff: 20700 as c_long,
+                             tm_zone: "Nepal Standard Time" with tm })
+            == "+0545";
+        assert zone_abbrev({ tm_gmtoff: -12600 as c_long,
+                             tm_zone: "Some Standard Time" with tm })
+            == "-0330";
+
+        assert zone_abbrev({ tm_gmtoff: 3600 as c_long with empty_tm() })
+            == "";
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_strptime_opts() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let opts = strptime_options();
+        assert strptime_opts("Feb 13 2009", "%b %d %Y", opts) ==
+            strptime("Feb 13 2009", "%b %d %Y");
+
+        let lenient = { strict: false with opts };
+        let tm = result::get(strptime_opts("2009-02-13 trailing", "%F",
+                                           lenient));
+        assert tm.tm_mday == 13 as c_int;
+        assert strptime("2009-02-13 trailing", "%F") ==
+            err(parse_error("Invalid time"));
+
+        let spaced = { whitespace: true with opts };
+        let tm = result::get(strptime_opts("Feb  13\t 2009", "%b %d %Y",
+                                           spaced));
+        assert tm.tm_mday == 13 as c_int;
+        assert result::is_success(strptime_opts("Feb13 2009", "%b %d %Y ",
+                                                spaced));
+        assert result::is_failure(strptime("Feb  13 2009", "%b %d %Y"));
+
+        let nocase = { case_sensitive: false with opts };
+        let tm = result::get(strptime_opts("FEB 13 2009 pm utc",
+                                           "%b %d %Y %p %Z", nocase));
+        assert tm.tm_mon == 1 as c_int;
+        assert tm.tm_hour == 12 as c_int;
+        assert tm.tm_zone == "UTC";
+        let tm = result::get(strptime_opts("pst", "%Z", nocase));
+        assert tm.tm_zone == "PST";
+        assert result::is_failure(strptime("FEB", "%b"));
+
+        let pivot = { year_pivot: some(69) with opts };
+        let year = fn@(s: str) -> int {
+            result::get(strptime_opts(s, "%y", pivot)).year()
+        };
+        assert year("09") == 2009;
+        assert year("68") == 2068;
+        assert year("69") == 1969;
+        assert result::get(strptime("09", "%y")).year() == 9;
+
+        let utc = { default_zone: some(zone_utc) with opts };
+        let tm = result::get(strptime_opts("2009-02-13", "%F", utc));
+        assert tm.tm_zone == "UTC";
+        assert tm.tm_gmtoff == 0 as c_long;
+
+        let fixed = { default_zone: some(zone_offset(3600_i32)) with opts };
+        let tm = result::get(strptime_opts("2009-02-13", "%F", fixed));
+        assert tm.tm_gmtoff == 3600 as c_long;
+
+        let local = { default_zone: some(zone_local) with opts };
+        let tm = result::get(strptime_opts("2009-07-13", "%F", local));
+        assert tm.tm_gmtoff == -25200 as c_long;
+        assert tm.tm_isdst == 1 as c_int;
+        assert tm.tm_zone == "PDT";
+        let tm = result::get(strptime_opts("2009-07-13 -0000", "%F %z",
+                                           local));
+        assert tm.tm_gmtoff == 0 as c_long;
+        let tm = result::get(strptime_opts("2009-07-13 +0530", "%F %z",
+                                           local));
+        assert tm.tm_gmtoff == 19800 as c_long && tm.tm_zone == "";
+        assert tm.to_timespec() == { sec: 1247423400_i64, nsec: 0_i32 };
+    }
+
+    #[test]
+    fn test_strptime_posix_whitespace() {
+        let opts = { whitespace: true with strptime_options() };
+        let parse = fn@(s: str, format: str) -> bool {
+            alt strptime_opts(s, format, opts) {
+              ok(tm) { tm.tm_mday == 13 as c_int && tm.tm_mon == 1 as c_int }
+              err(_) { false }
+            }
+        };
+
+        assert parse("13\n\t Feb", "%d%n%b");
+        assert parse("13  \r\n\tFeb", "%d%t%b");
+        assert parse("13Feb", "%d%n%b");
+        assert parse("13\t\tFeb", "%d %b");
+        assert parse("13\nFeb", "%d\t%b");
+        assert parse("13 Feb", "%d %b%n");
+        assert parse("13 Feb", "%d %b %t");
+
+        assert result::is_failure(strptime("13  Feb", "%d%n%b"));
+        assert result::is_success(strptime("13\nFeb", "%d%n%b"));
+        assert result::is_success(strptime("13\tFeb", "%d%t%b"));
+    }
+
+    #[test]
+    fn test_strptime_ordinals() {
+        let opts = { ordinals: true with strptime_options() };
+        let day = fn@(s: str, format: str) -> int {
+            result::get(strptime_opts(s, format, opts)).day()
+        };
+
+        assert day("February 13th, 2009", "%B %d, %Y") == 13;
+        assert day("February 1st, 2009", "%B %e, %Y") == 1;
+        assert day("February  2nd, 2009", "%B %e, %Y") == 2;
+        assert day("February 03rd, 2009", "%B %d, %Y") == 3;
+        assert day("February 11th, 2009", "%B %d, %Y") == 11;
+        assert day("February 22nd, 2009", "%B %d, %Y") == 22;
+        assert day("February 13, 2009", "%B %d, %Y") == 13;
+
+        let nocase = { case_sensitive: false with opts };
+        let tm = strptime_opts("February 21ST", "%B %d", nocase);
+        assert result::get(tm).day() == 21;
+
+        assert result::is_failure(strptime_opts("February 13st", "%B %d",
+                                                opts));
+        assert result::is_failure(strptime("February 13th", "%B %d"));
+    }
+
+    #[test]
+    fn test_strptime_bytes() {
+        let opts = strptime_options();
+        let mut buf = str::bytes("2009-02-13 23:31:30 GET /index.html\n");
+        let (tm, used) = result::get(strptime_bytes(buf, "%F %T", opts));
+        assert tm.tm_hour == 23 as c_int;
+        assert tm.tm_mday == 13 as c_int;
+        assert used == 19u;
+
+        // Bytes after the time are never looked at, even invalid ones, and
+        // the time may run past the first window.
+        buf += [0xffu8, 0xfeu8];
+        while vec::len(buf) < 1000u { buf += [0x80u8]; }
+        let (_, used) = result::get(strptime_bytes(buf, "%F %T", opts));
+        assert used == 19u;
+
+        let long = str::bytes(str::from_chars(vec::from_elem(100u, ' ')) +
+                              "2009-02-13 rest");
+        let spaced = { whitespace: true with opts };
+        let (tm, used) = result::get(strptime_bytes(long, " %F", spaced));
+        assert tm.tm_mday == 13 as c_int;
+        assert used == 110u;
+
+        assert result::is_failure(strptime_bytes([0xffu8], "%F", opts));
+        assert result::is_failure(strptime_bytes(str::bytes("2009-02"),
+                                                 "%F", opts));
+    }
+
+    #[test]
+    #[ignore(cfg(target_os = "win32"))]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_libc_tm() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+        let c = to_libc_tm(local);
+        assert c.tm_hour == local.tm_hour;
+        assert c.tm_yday == local.tm_yday;
+        assert c.tm_gmtoff == -28800 as c_long;
+        assert c.tm_zone == ptr::null();
+        assert from_libc_tm(c) == { tm_zone: "", tm_nsec: 0_i32 with local };
+
+        let back = with_libc_tm(local) {|c|
+            assert c.tm_zone != ptr::null();
+            from_libc_tm(c)
+        };
+        assert back == { tm_nsec: 0_i32 with local };
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_c_tm() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 54321_i32 });
+        let c = to_c_tm(local);
+        assert c.tm_mday == 13_i32;
+        assert c.tm_nsec == 54321_i32;
+        assert c.tm_gmtoff == -28800_i64;
+        assert c.tm_zone == ptr::null();
+        assert from_c_tm(c) == { tm_zone: "" with local };
+
+        let back = with_c_tm(local) {|c|
+            assert unsafe { str::unsafe::from_c_str(c.tm_zone) } == "PST";
+            from_c_tm(c)
+        };
+        assert back == local;
+    }
+
+    #[test]
+    fn test_duration_to_display() {
+        assert duration_nanos(0_i64).to_display() == "0ns";
+        assert duration_nanos(250_i64).to_display() == "250ns";
+        assert duration_nanos(-250_i64).to_display() == "-250ns";
+        assert duration_nanos(1530_i64).to_display() == "1.53µs";
+        assert duration_nanos(9996_i64).to_display() == "10.0µs";
+        assert duration_nanos(999600_i64).to_display() == "1.00ms";
+        assert duration_micros(1534_i64).to_display() == "1.53ms";
+        assert duration_micros(1535_i64).to_display() == "1.54ms";
+        assert duration_millis(153_i64).to_display() == "153ms";
+        assert duration_millis(-20_i64).to_display() == "-20.0ms";
+        assert duration_seconds(5_i64).to_display() == "5.00s";
+        assert duration_millis(12345_i64).to_display() == "12.3s";
+        assert duration_millis(150600_i64).to_display() == "151s";
+        assert duration_days(2_i64).to_display() == "172800s";
+    }
+
+    #[test]
+    fn test_rate() {
+        assert rate(500u64, duration_seconds(2_i64)) == 250.0;
+        assert rate(3u64, duration_millis(1_i64)) == 3000.0;
+
+        assert format_rate(12345.0) == "12.3k ops/s";
+        assert format_rate(250.0) == "250 ops/s";
+        assert format_rate(1.5) == "1.50 ops/s";
+        assert format_rate(0.25) == "0.250 ops/s";
+        assert format_rate(999700.0) == "1.00M ops/s";
+        assert format_rate(2.5e9) == "2.50G ops/s";
+        assert format_rate(rate(1000u64, duration_micros(1_i64))) ==
+            "1.00G ops/s";
+    }
+
+    #[test]
+    #[should_fail]
+    #[ignore(cfg(target_os = "win32"))]
+    fn test_rate_zero_elapsed() {
+        rate(1u64, duration_seconds(0_i64));
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_next_occurrence_after() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let next = fn@(clock: i64, h: int, m: int, z: zone) -> i64 {
+            let clock = { sec: clock, nsec: 0_i32 };
+            result::get(next_occurrence_after(clock, h, m, 0, z)).sec
+        };
+
+        // 2009-02-13 23:31:30 UTC, 15:31:30 PST.
+        let t = 1234567890_i64;
+        assert next(t, 23, 45, zone_utc) == 1234568700_i64;
+        assert next(t, 23, 0, zone_utc) == 1234652400_i64;
+        assert next(t, 16, 0, zone_local) == 1234569600_i64;
+        assert next(t, 15, 0, zone_local) == 1234652400_i64;
+        assert next(t, 0, 0, zone_offset(3600_i32)) == 1234566000_i64 +
+            86400_i64;
+        assert next(1234566000_i64, 0, 0, zone_utc) == 1234569600_i64;
+
+        // 02:30 doesn't exist on 2009-03-08, so the next one is the 9th.
+        // 2009-03-07 12:00 PST is 1236456000.
+        assert next(1236456000_i64, 2, 30, zone_local) == 1236591000_i64;
+
+        // 01:30 happens twice on 2009-11-01, first at 08:30 UTC. Between
+        // the two, the next is on the 2nd.
+        // 2009-10-31 12:00 PDT is 1257015600.
+        assert next(1257015600_i64, 1, 30, zone_local) == 1257064200_i64;
+        assert next(1257066000_i64, 1, 30, zone_local) == 1257154200_i64;
+
+        let clock = { sec: t, nsec: 0_i32 };
+        assert next_occurrence_after(clock, 24, 0, 0, zone_utc) ==
+            err(out_of_range("Invalid hour"));
+    }
+
+    #[test]
+    fn test_millis_until() {
+        let now = { sec: 1234567890_i64, nsec: 500000000_i32 };
+        let in_ms = fn@(ms: i64) -> timespec { now.add(duration_millis(ms)) };
+
+        assert in_ms(1500_i64).millis_until(now) == some(1500u64);
+        assert now.add(duration_nanos(1_i64)).millis_until(now) == some(1u64);
+        assert now.add(duration_micros(1001_i64)).millis_until(now) ==
+            some(2u64);
+        assert now.millis_until(now) == none;
+        assert in_ms(-5_i64).millis_until(now) == none;
+        let far = { sec: 9223372036854775807_i64, nsec: 0_i32 };
+        assert far.millis_until(now) == some(18446744073709551615u64);
+
+        assert in_ms(250_i64).poll_timeout(now) == 250 as c_int;
+        assert now.poll_timeout(now) == 0 as c_int;
+        assert in_ms(-250_i64).poll_timeout(now) == 0 as c_int;
+        assert far.poll_timeout(now) == 2147483647 as c_int;
+    }
+
+    #[test]
+    fn test_unique_clock() {
+        let clock = unique_clock();
+        let t = { sec: 1234567890_i64, nsec: 999999999_i32 };
+        assert clock.next_from(t) == t;
+        assert clock.next_from(t) == { sec: 1234567891_i64, nsec: 0_i32 };
+        assert clock.next_from({ sec: 1234567880_i64 with t }) ==
+            { sec: 1234567891_i64, nsec: 1_i32 };
+        let later = { sec: 1234567900_i64, nsec: 0_i32 };
+        assert clock.next_from(later) == later;
+
+        let clock = unique_clock();
+        let mut last = clock.next();
+        let mut i = 0;
+        while i < 1000 {
+            let next = clock.next();
+            assert next.gt(last);
+            last = next;
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_ulid_timestamp() {
+        let t = { sec: 1469918176_i64, nsec: 385999999_i32 };
+        assert t.to_ulid_timestamp() == ok(1469918176385u64);
+        assert t.to_ulid_prefix() == ok("01ARYZ6S41");
+        let epoch = { sec: 0_i64, nsec: 0_i32 };
+        assert epoch.to_ulid_prefix() == ok("0000000000");
+
+        let ms = { sec: 1469918176_i64, nsec: 385000000_i32 };
+        assert timespec_from_ulid_timestamp(1469918176385u64) == ok(ms);
+        assert timespec_from_ulid("01ARYZ6S41") == ok(ms);
+        assert timespec_from_ulid("01aryz6s41TSV4RRFFQ69G5FAV") == ok(ms);
+        assert timespec_from_ulid("7ZZZZZZZZZ") ==
+            ok({ sec: 281474976710_i64, nsec: 655000000_i32 });
+
+        assert timespec_from_ulid("01ARYZ6S4") ==
+            err(parse_error("Expected a ULID"));
+        assert timespec_from_ulid("01ARYZ6SU1") ==
+            err(parse_error("Invalid ULID"));
+        assert timespec_from_ulid("8000000000") ==
+            err(out_of_range("Out of ULID range"));
+        assert { sec: -1_i64, nsec: 0_i32 }.to_ulid_timestamp() ==
+            err(out_of_range("Out of ULID range"));
+    }
+
+    #[test]
+    fn test_strftime_strptime_millis() {
+        let t = at_utc({ sec: 1234567890_i64, nsec: 54321000_i32 });
+        assert t.strftime("%Q") == "1234567890054";
+        assert at_utc({ sec: -1_i64, nsec: 500000000_i32 }).strftime("%Q")
+            == "-500";
+
+        let tm = result::get(strptime("1234567890054", "%Q"));
+        assert tm.to_timespec() == { sec: 1234567890_i64, nsec: 54000000_i32 };
+        assert tm.tm_hour == 23 as c_int;
+        assert tm.tm_zone == "UTC";
+        let tm = result::get(strptime("[-500]", "[%Q]"));
+        assert tm.to_timespec() == { sec: -1_i64, nsec: 500000000_i32 };
+
+        let local = { default_zone: some(zone_local) with strptime_options() };
+        let tm = result::get(strptime_opts("0", "%Q", local));
+        assert tm.tm_gmtoff == 0 as c_long;
+
+        assert strptime("x", "%Q") ==
+            err(parse_error("Invalid milliseconds since the epoch"));
+        assert strptime("-", "%Q") ==
+            err(parse_error("Invalid milliseconds since the epoch"));
+    }
+
+    #[test]
+    fn test_strptime_partial() {
+        let opts = strptime_options();
+        let format = "%Y-%m-%d %H:%M:%S";
+
+        let tm = strptime_partial("2009-02-13 23:31:30", format, opts);
+        assert result::get(tm).tm_sec == 30 as c_int;
+
+        let p = result::get_err(strptime_partial("2009-02-13 2x:31:30",
+                                                 format, opts));
+        assert p.error == parse_error("Invalid hour");
+        assert p.pos == 11u;
+        assert p.format_pos == 9u;
+        assert p.partial.year() == 2009;
+        assert p.partial.tm_mday == 13 as c_int;
+        assert p.partial.tm_hour == 0 as c_int;
+
+        let p = result::get_err(strptime_partial("2009-02/13", format,
+                                                 opts));
+        assert p.error == parse_error("Invalid time");
+        assert p.pos == 7u;
+        assert p.format_pos == 5u;
+        assert p.partial.tm_mon == 1 as c_int;
+
+        let p = result::get_err(strptime_partial("2009-02-13 23:31",
+                                                 format, opts));
+        assert p.error ==
+            parse_error("Input ended while expecting \":\" at offset 16");
+        assert p.pos == 16u;
+        assert p.format_pos == 14u;
+        assert p.partial.tm_min == 31 as c_int;
+
+        let p = result::get_err(strptime_partial("2009-02-13 23:31:30 x",
+                                                 format, opts));
+        assert p.pos == 19u;
+        assert p.format_pos == 17u;
+    }
+
+    #[test]
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_verify_roundtrip() {
+        os::setenv("TZ", "America/Los_Angeles");
+
+        let local = at({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert verify_roundtrip("%Y-%m-%d %H:%M:%S", local) == ok(());
+        assert verify_roundtrip("%c", local) == ok(());
+        assert verify_roundtrip("%F %r", local) == ok(());
+        assert verify_roundtrip("%F %T %Z", local) == ok(());
+
+        assert verify_roundtrip("%F %I:%M", local) ==
+            err(parse_error("Round trip through \"2009-02-13 03:31\" " +
+                            "changed tm_hour was 15 but parsed as 3"));
+        assert verify_roundtrip("%y", local) ==
+            err(parse_error("Round trip through \"09\" " +
+                            "changed tm_year was 109 but parsed as -1891"));
+        assert verify_roundtrip("%s", local) ==
+            err(parse_error("unknown formatting type: \"s\""));
+    }
+
+    #[test]
+    fn test_strptime_unicode_whitespace() {
+        let opts = { whitespace: true with strptime_options() };
+        let parse = fn@(s: str, format: str) -> option<tm> {
+            alt strptime_opts(s, format, opts) {
+              ok(tm) { some(tm) }
+              err(_) { none }
+            }
+        };
+
+        let tm = option::get(parse("13\u00a0Feb", "%d %b"));
+        assert tm.tm_mday == 13 as c_int && tm.tm_mon == 1 as c_int;
+        assert option::is_some(parse("13\u202f\u3000Feb", "%d%n%b"));
+        assert option::is_some(parse("13 Feb", "%d %b\u00a0"));
 
-    local: \"2012-02-22T07:53:18-07:00\"
-    utc:   \"2012-02-22T14:53:18Z\"
-    "]
-    fn rfc3339() -> str {
-        if self.tm_gmtoff == 0 as c_long{
-            self.strftime("%Y-%m-%dT%H:%M:%SZ")
-        } else {
-            let s = self.strftime("%Y-%m-%dT%H:%M:%S");
-            let gmtoff = self.tm_gmtoff as i32;
-            let sign = if gmtoff > 0_i32 { '+' } else { '-' };
-            let mut m = i32::abs(gmtoff) / 60_i32;
-            let h = m / 60_i32;
-            m -= h * 60_i32;
-            s + #fmt("%c%02d:%02d", sign, h as int, m as int)
-        }
+        let tm = option::get(parse("Fri\u00a0Feb 13 23:31:30\u00a02009",
+                                   "%c"));
+        assert tm.tm_year == 109 as c_int && tm.tm_hour == 23 as c_int;
+        let tm = option::get(parse("11:31:30\u00a0PM", "%r"));
+        assert tm.tm_hour == 23 as c_int;
+
+        assert result::is_failure(strptime("13\u00a0Feb", "%d %b"));
+        assert result::is_failure(strptime("11:31:30\u00a0PM", "%r"));
     }
-}
 
-#[cfg(test)]
-mod tests {
     #[test]
-    fn test_at_utc() {
-        os::setenv("TZ", "America/Los_Angeles");
+    fn test_parse_rfc2822() {
+        let parse = fn@(s: str) -> tm { result::get(parse_rfc2822(s)) };
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let utc = at_utc(time);
+        let tm = parse("Fri, 13 Feb 2009 23:31:30 +0000 (UTC)");
+        assert tm.tm_year == 109 as c_int && tm.tm_mon == 1 as c_int;
+        assert tm.tm_mday == 13 as c_int && tm.tm_hour == 23 as c_int;
+        assert tm.tm_min == 31 as c_int && tm.tm_sec == 30 as c_int;
+        assert tm.tm_gmtoff == 0 as c_long && tm.tm_zone == "";
 
-        assert utc.tm_sec == 30 as c_int;
-        assert utc.tm_min == 31 as c_int;
-        assert utc.tm_hour == 23 as c_int;
-        assert utc.tm_mday == 13 as c_int;
-        assert utc.tm_mon == 1 as c_int;
-        assert utc.tm_year == 109 as c_int;
-        assert utc.tm_wday == 5 as c_int;
-        assert utc.tm_yday == 43 as c_int;
-        assert utc.tm_isdst == 0 as c_int;
-        assert utc.tm_gmtoff == 0 as c_long;
-        assert utc.tm_zone != ptr::null();
-        assert unsafe { str::unsafe::from_c_str(utc.tm_zone) } == "UTC";
-        assert utc.tm_nsec == 54321_i32;
+        let folded = parse("Fri, 13 Feb 2009\r\n 15:31:30\r\n\t-0800");
+        assert folded.tm_hour == 15 as c_int;
+        assert folded.tm_gmtoff == -28800 as c_long;
+
+        let commented = parse("(sent) Fri (day) , 13 Feb (a (nested) " +
+                              "\\) comment) 2009 15:31:30 -0800 (PST)");
+        assert commented == folded;
+
+        let tm = parse("13 Feb 2009 23:31 GMT");
+        assert tm.tm_mday == 13 as c_int && tm.tm_sec == 0 as c_int;
+        assert tm.tm_gmtoff == 0 as c_long && tm.tm_zone == "GMT";
+        assert parse("Fri, 13 Feb 2009 18:31:30 est").tm_gmtoff ==
+            -18000 as c_long;
+        assert parse("Fri, 13 Feb 2009 23:31:30 +0530").tm_gmtoff ==
+            19800 as c_long;
+
+        let tm = parse("Tue, 3 Feb 2009 23:31:30 +0000");
+        assert tm.tm_mday == 3 as c_int && tm.tm_hour == 23 as c_int;
+        let short = parse("3 Feb 2009 23:31 +0000");
+        assert short.tm_mday == 3 as c_int && short.tm_sec == 0 as c_int;
+        assert parse("Tue, 03 Feb 2009 23:31:30 +0000") == tm;
+
+        assert parse_rfc2822("Fri, 13 Feb 2009 23:31:30 +0000 (UTC") ==
+            err(parse_error("Unterminated comment"));
+        assert parse_rfc2822("Fri, 13 Feb 2009 23:31:30 +0000 UTC)") ==
+            err(parse_error("Unbalanced comment"));
+        assert parse_rfc2822("Fri, 13 Feb 2009 23:31:30 XYZ") ==
+            err(unknown_zone("XYZ"));
+        assert parse_rfc2822("Fri, 13 Feb 2009 +0000") ==
+            err(parse_error("Invalid RFC 2822 date"));
     }
 
     #[test]
-    fn test_at() {
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_rfc5322() {
         os::setenv("TZ", "America/Los_Angeles");
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let local = at(time);
+        let t = { sec: 1234567890_i64, nsec: 0_i32 };
+        assert at_utc(t).rfc5322() == "Fri, 13 Feb 2009 23:31:30 +0000";
+        assert at(t).rfc5322() == "Fri, 13 Feb 2009 15:31:30 -0800";
+        let ist = at_gmtoff(t, 19800 as c_long);
+        assert ist.rfc5322() == "Sat, 14 Feb 2009 05:01:30 +0530";
 
-        assert local.tm_sec == 30 as c_int;
-        assert local.tm_min == 31 as c_int;
-        assert local.tm_hour == 15 as c_int;
-        assert local.tm_mday == 13 as c_int;
-        assert local.tm_mon == 1 as c_int;
-        assert local.tm_year == 109 as c_int;
-        assert local.tm_wday == 5 as c_int;
-        assert local.tm_yday == 43 as c_int;
-        assert local.tm_isdst == 0 as c_int;
-        assert local.tm_gmtoff == -28800 as c_long;
-        assert local.tm_zone != ptr::null();
-        assert unsafe { str::unsafe::from_c_str(local.tm_zone) } == "PST";
-        assert local.tm_nsec == 54321_i32;
+        let built = {
+            tm_sec: 5 as c_int,
+            tm_min: 4 as c_int,
+            tm_hour: 3 as c_int,
+            tm_mday: 2 as c_int,
+            tm_mon: 0 as c_int,
+            tm_year: 100 as c_int,
+            tm_wday: 0 as c_int,
+            tm_yday: 0 as c_int,
+            tm_isdst: 0 as c_int,
+            tm_gmtoff: -12600 as c_long,
+            tm_zone: "",
+            tm_nsec: 0_i32,
+        };
+        assert built.rfc5322() == "Sun, 02 Jan 2000 03:04:05 -0330";
+        assert ({ tm_mday: 1 as c_int with built }).rfc5322() ==
+            "Sat, 01 Jan 2000 03:04:05 -0330";
     }
 
     #[test]
-    fn test_to_timespec() {
+    fn test_cookie_expires() {
         os::setenv("TZ", "America/Los_Angeles");
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let utc = at_utc(time);
+        let t = { sec: 1234567890_i64, nsec: 0_i32 };
+        assert at_utc(t).cookie_expires() == "Fri, 13-Feb-2009 23:31:30 GMT";
+        assert at(t).cookie_expires() == "Fri, 13-Feb-2009 23:31:30 GMT";
+        let early = at_utc({ sec: 0_i64, nsec: 0_i32 });
+        assert early.cookie_expires() == "Thu, 01-Jan-1970 00:00:00 GMT";
 
-        assert utc.to_timespec() == time;
-        assert utc.to_local().to_timespec() == time;
+        let tm = result::get(
+            parse_cookie_expires("Fri, 13-Feb-2009 23:31:30 GMT"));
+        assert tm == at_utc(t);
+        assert tm.to_timespec() == t;
+
+        assert result::is_failure(
+            parse_cookie_expires("Fri, 13 Feb 2009 23:31:30 GMT"));
+        assert result::is_failure(
+            parse_cookie_expires("Fri, 13-Feb-2009 23:31:30 PST"));
     }
 
     #[test]
-    fn test_conversions() {
-        os::setenv("TZ", "America/Los_Angeles");
+    fn test_parse_ls_date() {
+        let reference = at_utc({ sec: 1234567890_i64, nsec: 0_i32 });
+        let parse = fn@(s: str) -> (int, int, int, int, int) {
+            let tm = result::get(parse_ls_date(s, reference));
+            (tm.year(), tm.tm_mon as int + 1, tm.day(), tm.tm_hour as int,
+             tm.tm_min as int)
+        };
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let utc = at_utc(time);
-        let local = at(time);
+        assert parse("Feb 13 23:31") == (2009, 2, 13, 23, 31);
+        assert parse("Jan  2 08:05") == (2009, 1, 2, 8, 5);
+        assert parse("Feb 14 10:00") == (2009, 2, 14, 10, 0);
+        assert parse("Feb 15 10:00") == (2008, 2, 15, 10, 0);
+        assert parse("Dec 31 23:59") == (2008, 12, 31, 23, 59);
+        assert parse("Feb 13  2009") == (2009, 2, 13, 0, 0);
+        assert parse("Mar  1  1999") == (1999, 3, 1, 0, 0);
 
-        assert local.to_local() == local;
-        assert local.to_utc() == utc;
-        assert local.to_utc().to_local() == local;
-        assert utc.to_utc() == utc;
-        assert utc.to_local() == local;
-        assert utc.to_local().to_utc() == utc;
+        assert result::is_failure(parse_ls_date("Feb 13", reference));
+        assert result::is_failure(parse_ls_date("Feb 13 23", reference));
+        assert result::is_failure(parse_ls_date("13 Feb 2009", reference));
     }
 
     #[test]
-    fn test_strptime() {
-        os::setenv("TZ", "America/Los_Angeles");
-
-        alt strptime("", "") {
-          ok(tm) {
-            assert tm.tm_sec == 0 as c_int;
-            assert tm.tm_min == 0 as c_int;
-            assert tm.tm_hour == 0 as c_int;
-            assert tm.tm_mday == 0 as c_int;
-            assert tm.tm_mon == 0 as c_int;
-            assert tm.tm_year == 0 as c_int;
-            assert tm.tm_wday == 0 as c_int;
-            assert tm.tm_isdst== 0 as c_int;
-            assert tm.tm_gmtoff == 0 as c_long;
-            assert tm.tm_zone == ptr::null();
-            assert tm.tm_nsec == 0_i32;
-          }
-          err(_) {}
-        }
+    fn test_locale() {
+        let utc = at_utc({ sec: 1234567890_i64, nsec: 0_i32 });
+        assert utc.strftime_with_locale("%x %X", c_locale()) ==
+            utc.strftime("%D %T");
+        assert system_locale() == c_locale();
 
-        let format = "%a %b %e %T %Y";
-        assert strptime("", format) == err("Invalid time");
-        assert strptime("Fri Feb 13 15:31:30", format) == err("Invalid time");
+        let de = { date_format: "%d.%m.%Y", time_format: "%H.%M Uhr" };
+        assert utc.strftime_with_locale("%x, %X", de) ==
+            "13.02.2009, 23.31 Uhr";
+        let nested = { date_format: "[%x]", time_format: "%X" };
+        assert utc.strftime_with_locale("%x %X", nested) ==
+            "[02/13/09] 23:31:30";
 
-        alt strptime("Fri Feb 13 15:31:30 2009", format) {
-          err(e) { fail e }
-          ok(tm) {
-            assert tm.tm_sec == 30 as c_int;
-            assert tm.tm_min == 31 as c_int;
-            assert tm.tm_hour == 15 as c_int;
-            assert tm.tm_mday == 13 as c_int;
-            assert tm.tm_mon == 1 as c_int;
-            assert tm.tm_year == 109 as c_int;
-            assert tm.tm_wday == 5 as c_int;
-            assert tm.tm_yday == 0 as c_int;
-            assert tm.tm_isdst == 0 as c_int;
-            assert tm.tm_gmtoff == 0 as c_long;
-            assert tm.tm_zone == ptr::null();
-            assert tm.tm_nsec == 0_i32;
-          }
-        }
+        let opts = { locale: de with strptime_options() };
+        let tm = result::get(strptime_opts("13.02.2009 23.31 Uhr", "%x %X",
+                                           opts));
+        assert tm.year() == 2009 && tm.tm_mon == 1 as c_int;
+        assert tm.day() == 13 && tm.tm_hour == 23 as c_int;
+        assert tm.tm_min == 31 as c_int && tm.tm_sec == 0 as c_int;
 
-        fn test(s: str, format: str) -> bool {
-            alt strptime(s, format) {
-              ok(tm) { tm.strftime(format) == s }
-              err(e) { fail e }
-            }
-        }
+        let tm = result::get(strptime("02/13/09 23:31:30", "%x %X"));
+        assert tm.tm_mday == 13 as c_int && tm.tm_sec == 30 as c_int;
 
-        vec::iter([
-            "Sunday",
-            "Monday",
-            "Tuesday",
-            "Wednesday",
-            "Thursday",
-            "Friday",
-            "Saturday"
-        ]) { |day| assert test(day, "%A"); }
+        assert strptime_opts("13.02.", "%x", opts) ==
+            err(parse_error("Input ended while parsing %x at offset 6"));
+        assert result::is_failure(strptime_opts("02/13/09", "%x", opts));
+    }
 
-        vec::iter([
-            "Sun",
-            "Mon",
-            "Tue",
-            "Wed",
-            "Thu",
-            "Fri",
-            "Sat"
-        ]) { |day| assert test(day, "%a"); }
+    #[test]
+    fn test_fill_derived() {
+        let utc = at_utc({ sec: 1234567890_i64, nsec: 0_i32 });
+        let stale = { tm_wday: 0 as c_int, tm_yday: 0 as c_int with utc };
+        assert stale.fill_derived() == utc;
+        assert stale.strftime("%a %A %j %u %w") == "Fri Friday 044 5 5";
+        assert stale.rfc822() == utc.rfc822();
 
-        vec::iter([
-            "January",
-            "February",
-            "March",
-            "April",
-            "May",
-            "June",
-            "July",
-            "August",
-            "September",
-            "October",
-            "November",
-            "December"
-        ]) { |day| assert test(day, "%B"); }
+        let edited = ({ tm_mday: 14 as c_int with utc }).fill_derived();
+        assert edited.tm_wday == 6 as c_int && edited.tm_yday == 44 as c_int;
+        let eve = ({ tm_year: 108 as c_int, tm_mon: 11 as c_int,
+                     tm_mday: 31 as c_int with utc }).fill_derived();
+        assert eve.tm_wday == 3 as c_int && eve.tm_yday == 365 as c_int;
 
-        vec::iter([
-            "Jan",
-            "Feb",
-            "Mar",
-            "Apr",
-            "May",
-            "Jun",
-            "Jul",
-            "Aug",
-            "Sep",
-            "Oct",
-            "Nov",
-            "Dec"
-        ]) { |day| assert test(day, "%b"); }
+        // Out of range fields are carried, but left as they are.
+        let carried = { tm_mon: 12 as c_int, tm_mday: 1 as c_int with utc };
+        let filled = carried.fill_derived();
+        assert filled.tm_wday == 5 as c_int && filled.tm_yday == 0 as c_int;
+        assert filled.tm_mon == 12 as c_int;
 
-        assert test("19", "%C");
-        assert test("Fri Feb 13 23:31:30 2009", "%c");
-        assert test("02/13/09", "%D");
-        assert test("03", "%d");
-        assert test("13", "%d");
-        assert test(" 3", "%e");
-        assert test("13", "%e");
-        assert test("2009-02-13", "%F");
-        assert test("03", "%H");
-        assert test("13", "%H");
-        assert test("03", "%I"); // FIXME: flesh out
-        assert test("11", "%I"); // FIXME: flesh out
-        assert test("044", "%j");
-        assert test(" 3", "%k");
-        assert test("13", "%k");
-        assert test(" 1", "%l");
-        assert test("11", "%l");
-        assert test("03", "%M");
-        assert test("13", "%M");
-        assert test("\n", "%n");
-        assert test("am", "%P");
-        assert test("pm", "%P");
-        assert test("AM", "%p");
-        assert test("PM", "%p");
-        assert test("23:31", "%R");
-        assert test("11:31:30 AM", "%r");
-        assert test("11:31:30 PM", "%r");
-        assert test("03", "%S");
-        assert test("13", "%S");
-        assert test("15:31:30", "%T");
-        assert test("\t", "%t");
-        assert test("1", "%u");
-        assert test("7", "%u");
-        assert test("13-Feb-2009", "%v");
-        assert test("0", "%w");
-        assert test("6", "%w");
-        assert test("2009", "%Y");
-        assert test("09", "%y");
-        //FIXME
-        assert result::get(strptime("UTC", "%Z")).tm_zone == ptr::null();
-        assert result::get(strptime("PST", "%Z")).tm_zone == ptr::null();
-        assert result::get(strptime("-0000", "%z")).tm_gmtoff == 0 as c_long;
-        assert result::get(strptime("-0800", "%z")).tm_gmtoff == 0 as c_long;
-        assert test("%", "%%");
+        // Without a valid date strftime keeps the fields it is given.
+        let undated = { tm_mday: 0 as c_int with stale };
+        assert undated.strftime("%a %j") == "Sun 001";
     }
 
     #[test]
-    fn test_ctime() {
+    #[ignore(cfg(time_pure))]
+    #[ignore(cfg(time_freestanding))]
+    fn test_strptime_isdst() {
         os::setenv("TZ", "America/Los_Angeles");
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let utc   = at_utc(time);
-        let local = at(time);
+        let isdst = fn@(s: str, format: str, opts: strptime_options) -> int {
+            result::get(strptime_opts(s, format, opts)).tm_isdst as int
+        };
+        let opts = strptime_options();
+        assert isdst("2009-07-13 12:00", "%F %R", opts) == -1;
+        assert isdst("2009-07-13 12:00 -0700", "%F %R %z", opts) == -1;
+        assert isdst("2009-07-13 12:00 UTC", "%F %R %Z", opts) == 0;
+        assert isdst("2009-07-13 12:00 PDT", "%F %R %Z", opts) == 1;
+        assert isdst("2009-01-13 12:00 PST", "%F %R %Z", opts) == 0;
+        assert isdst("1234567890000", "%Q", opts) == 0;
 
-        assert utc.ctime()   == "Fri Feb 13 23:31:30 2009";
-        assert local.ctime() == "Fri Feb 13 15:31:30 2009";
+        let summer = { default_isdst: 1 with opts };
+        assert isdst("2009-07-13 12:00", "%F %R", summer) == 1;
+        assert isdst("2009-07-13 12:00 GMT", "%F %R %Z", summer) == 0;
+        let utc = { default_zone: some(zone_utc) with summer };
+        assert isdst("2009-07-13 12:00", "%F %R", utc) == 0;
+
+        let p = result::get_err(strptime_partial("2009-07-13 x", "%F %R",
+                                                 opts));
+        assert p.partial.tm_isdst == -1 as c_int;
     }
 
     #[test]
-    fn test_strftime() {
-        os::setenv("TZ", "America/Los_Angeles");
+    fn test_key_i64() {
+        let t = { sec: 1234567890_i64, nsec: 123456789_i32 };
+        assert t.to_key_i64() == ok(1234567890123456789_i64);
+        assert timespec_from_key_i64(1234567890123456789_i64) == t;
 
-        let time = { sec: 1234567890_i64, nsec: 54321_i32 };
-        let utc = at_utc(time);
-        let local = at(time);
+        let times = [{ sec: -1_i64, nsec: 999999999_i32 },
+                     { sec: 0_i64, nsec: 0_i32 },
+                     { sec: 0_i64, nsec: 1_i32 },
+                     t,
+                     { sec: 9223372036_i64, nsec: 854775807_i32 }];
+        let mut last = none;
+        for vec::each(times) {|t|
+            let key = result::get(t.to_key_i64());
+            assert timespec_from_key_i64(key) == t;
+            alt last {
+              some(k) { assert k < key; }
+              none { }
+            }
+            last = some(key);
+        }
 
-        assert local.strftime("") == "";
-        assert local.strftime("%A") == "Friday";
-        assert local.strftime("%a") == "Fri";
-        assert local.strftime("%B") == "February";
-        assert local.strftime("%b") == "Feb";
-        assert local.strftime("%C") == "20";
-        assert local.strftime("%c") == "Fri Feb 13 15:31:30 2009";
-        assert local.strftime("%D") == "02/13/09";
-        assert local.strftime("%d") == "13";
-        assert local.strftime("%e") == "13";
-        assert local.strftime("%F") == "2009-02-13";
-        // assert local.strftime("%G") == "2009";
-        // assert local.strftime("%g") == "09";
-        assert local.strftime("%H") == "15";
-        assert local.strftime("%I") == "03";
-        assert local.strftime("%j") == "044";
-        assert local.strftime("%k") == "15";
-        assert local.strftime("%l") == " 3";
-        assert local.strftime("%M") == "31";
-        assert local.strftime("%m") == "02";
-        assert local.strftime("%n") == "\n";
-        assert local.strftime("%P") == "pm";
-        assert local.strftime("%p") == "PM";
-        assert local.strftime("%R") == "15:31";
-        assert local.strftime("%r") == "03:31:30 PM";
-        assert local.strftime("%S") == "30";
-        assert local.strftime("%s") == "1234567890";
-        assert local.strftime("%T") == "15:31:30";
-        assert local.strftime("%t") == "\t";
-        // assert local.strftime("%U") == "06";
-        assert local.strftime("%u") == "5";
-        // assert local.strftime("%V") == "07";
-        assert local.strftime("%v") == "13-Feb-2009";
-        // assert local.strftime("%W") == "06";
-        assert local.strftime("%w") == "5";
-        // handle "%X"
-        // handle "%x"
-        assert local.strftime("%Y") == "2009";
-        assert local.strftime("%y") == "09";
+        assert timespec_from_key_i64(i64_min).to_key_i64() == ok(i64_min);
+        let after = { sec: 9223372036_i64, nsec: 854775808_i32 };
+        assert after.to_key_i64() == err(overflow);
+        let before = { sec: -9223372037_i64, nsec: 145224191_i32 };
+        assert before.to_key_i64() == err(overflow);
+    }
 
-        // FIXME: We should probably standardize on the timezone
-        // abbreviation.
-        let zone = local.strftime("%Z");
-        assert zone == "PST" || zone == "Pacific Standard Time";
+    #[test]
+    fn test_offset_for_abbrev() {
+        assert offset_for_abbrev("UTC") == ok([0_i32]);
+        assert offset_for_abbrev("EST") == ok([-18000_i32]);
+        assert offset_for_abbrev("cest") == ok([7200_i32]);
+        assert offset_for_abbrev("NST") == ok([-12600_i32]);
+        assert offset_for_abbrev("CST") ==
+            ok([-21600_i32, 28800_i32, -18000_i32]);
+        assert offset_for_abbrev("IST") ==
+            ok([19800_i32, 3600_i32, 7200_i32]);
+        assert offset_for_abbrev("XYZ") == err(unknown_zone("XYZ"));
+        assert offset_for_abbrev("") == err(unknown_zone(""));
 
-        assert local.strftime("%z") == "-0800";
-        assert local.strftime("%%") == "%";
+        // %Z records the offset of an abbreviation that isn't ambiguous.
+        os::setenv("TZ", "America/Los_Angeles");
+        let tm = result::get(strptime("2009-02-13 23:31:30 JST",
+                                      "%F %T %Z"));
+        assert tm.tm_gmtoff == 32400 as c_long && tm.tm_zone == "JST";
+        assert tm.tm_isdst == 0 as c_int;
+        let tm = result::get(strptime("2009-07-13 23:31:30 CEST",
+                                      "%F %T %Z"));
+        assert tm.tm_gmtoff == 7200 as c_long && tm.tm_isdst == 1 as c_int;
+        let tm = result::get(strptime("2009-02-13 23:31:30 IST",
+                                      "%F %T %Z"));
+        assert tm.tm_gmtoff == 0 as c_long && tm.tm_zone == "";
+        assert tm.tm_isdst == -1 as c_int;
+        let tm = result::get(strptime("jst", "%Z"));
+        assert tm.tm_zone == "";
+        let nocase = { case_sensitive: false with strptime_options() };
+        let tm = result::get(strptime_opts("jst", "%Z", nocase));
+        assert tm.tm_gmtoff == 32400 as c_long && tm.tm_zone == "JST";
+    }
 
-        // FIXME: We should probably standardize on the timezone
-        // abbreviation.
-        let rfc822 = local.rfc822();
-        let prefix = "Fri, 13 Feb 2009 15:31:30 ";
-        assert rfc822 == prefix + "PST" ||
-               rfc822 == prefix + "Pacific Standard Time";
+    #[test]
+    #[cfg(time_pure)]
+    #[cfg(time_freestanding)]
+    fn test_pure_local_is_utc() {
+        let t = { sec: 1234567890_i64, nsec: 54321_i32 };
+        assert at(t) == at_utc(t);
+        assert at(t).to_timespec() == t;
 
-        assert local.ctime() == "Fri Feb 13 15:31:30 2009";
-        assert local.rfc822z() == "Fri, 13 Feb 2009 15:31:30 -0800";
-        assert local.rfc3339() == "2009-02-13T15:31:30-08:00";
+        let fields = { tm_isdst: 1 as c_int, tm_gmtoff: 3600 as c_long,
+                       tm_zone: "CET" with at_utc(t) };
+        assert resolve_local(fields) == ok(at_utc(t));
 
-        assert utc.ctime() == "Fri Feb 13 23:31:30 2009";
-        assert utc.rfc822() == "Fri, 13 Feb 2009 23:31:30 GMT";
-        assert utc.rfc822z() == "Fri, 13 Feb 2009 23:31:30 -0000";
-        assert utc.rfc3339() == "2009-02-13T23:31:30Z";
+        // UTC has no transitions, so 2:30 on a day when other zones skip
+        // it is kept.
+        let night = { tm_mon: 2 as c_int, tm_mday: 8 as c_int,
+                      tm_hour: 2 as c_int with fields };
+        let tm = result::get(resolve_local(night));
+        assert tm.strftime("%F %T %z") == "2009-03-08 02:31:30 +0000";
     }
 }
@@ -0,0 +1,460 @@
+import libc::{c_int, c_long};
+import result::{result, ok, err};
+
+export zone, zone_utc, zone_local, zone_offset, conversion_cache, at_utc,
+       now_utc, at, now, at_zone, try_at_utc, try_at, resolve_local,
+       is_dst_at, local_offset_at, zone_info, local_zone_info,
+       local_zone_info_at, zone_abbrev, offset_for_abbrev, lookup_abbrev,
+       next_occurrence_of, next_occurrence_after, midnight_in_zone,
+       at_gmtoff;
+
+#[doc = "The zone in which calendar computations are performed."]
+enum zone {
+    zone_utc,
+    zone_local,
+    // A fixed offset in seconds east of UTC.
+    zone_offset(i32),
+}
+
+#[doc = "
+Converts timespecs to tms in a zone, remembering the date of the last
+conversion so that converting many times within the same day skips the
+civil date computation. Created with `conversion_cache`.
+"]
+type conversion_cache = @{zone: zone, mut last: option<(i64, tm)>};
+
+#[doc = "
+The abbreviations and offsets of the local timezone, as returned by
+`local_zone_info`. Offsets are in seconds east of UTC, unlike the libc
+`timezone` global. `dst_name` and `dst_offset` are none when the zone
+doesn't observe daylight saving time.
+"]
+type zone_info = {
+    std_name: str,
+    std_offset: i32,
+    dst_name: option<str>,
+    dst_offset: option<i32>
+};
+
+#[doc = "Returns the specified time in UTC"]
+fn at_utc(clock: timespec) -> tm {
+    // This is computed directly rather than with gmtime_r, whose handling
+    // of times before 1970 varies between platforms.
+    let days = div_floor(clock.sec, 86400_i64);
+    let sod = (clock.sec - days * 86400_i64) as int;
+    let (y, m, d) = civil_from_days(days);
+    let tm = result::get(civil_tm(y, m, d, sod / 3600, sod % 3600 / 60,
+                                  sod % 60, clock.nsec));
+    { tm_zone: "UTC" with tm }
+}
+
+#[doc = "
+Returns the specified time in UTC, or an error if it can't be represented
+by a tm: the year must fit in `tm_year`, and the nanoseconds must be in
+the range [0, 1000000000).
+"]
+fn try_at_utc(clock: timespec) -> result<tm, error> {
+    check_tm_range(clock).chain {|clock| ok(at_utc(clock)) }
+}
+
+#[doc = "
+Returns the specified time in the local timezone, or an error if it can't
+be represented by a tm, see `try_at_utc`.
+"]
+fn try_at(clock: timespec) -> result<tm, error> {
+    check_tm_range(clock).chain {|clock| ok(at(clock)) }
+}
+
+fn check_tm_range(clock: timespec) -> result<timespec, error> {
+    if clock.sec < tm_min_sec || clock.sec > tm_max_sec {
+        err(out_of_range("Time out of range"))
+    } else if clock.nsec < 0_i32 || clock.nsec >= 1000000000_i32 {
+        err(out_of_range("Invalid nanosecond"))
+    } else {
+        ok(clock)
+    }
+}
+
+#[doc = "
+Converts wall-clock fields in the local timezone into a tm, leaving the
+platform to decide whether daylight saving time is in effect, like mktime
+given a tm_isdst of -1. The `tm_isdst`, `tm_gmtoff` and `tm_zone` of
+`fields` are ignored; the result has the ones in effect at that time, and
+its other fields carried into their usual ranges. A time skipped by a DST
+transition is moved forward by the length of the transition, and a
+repeated time resolves to one of its two instants, as mktime chooses.
+Returns an error if the time can't be represented.
+"]
+fn resolve_local(fields: tm) -> result<tm, error> {
+    let mon = fields.tm_mon as i64;
+    let year = (fields.tm_year as i64) + 1900_i64 + div_floor(mon, 12_i64);
+    if year < tm_min_year || year > tm_max_year {
+        ret err(overflow);
+    }
+
+    let civil = { tm_isdst: -1 as c_int, tm_gmtoff: 0 as c_long,
+                  tm_zone: "" with fields };
+    alt sys::mktime(civil) {
+      some(sec) {
+        let (sec, nsec) = carry_nanos(sec, fields.tm_nsec as i64);
+        try_at({ sec: sec, nsec: nsec })
+      }
+      none { err(out_of_range("Time out of range")) }
+    }
+}
+
+#[doc = "
+Returns true if daylight saving time is in effect in the local timezone
+at the given time.
+"]
+fn is_dst_at(clock: timespec) -> bool {
+    at(clock).tm_isdst > 0 as c_int
+}
+
+#[doc = "
+Returns the offset from UTC of the local timezone at the given time, in
+seconds east of UTC, including any daylight saving time in effect.
+"]
+fn local_offset_at(clock: timespec) -> i32 {
+    at(clock).tm_gmtoff as i32
+}
+
+#[doc = "
+Returns the abbreviations and offsets of the local timezone in the year
+containing the given time. These are read from the zone database after
+calling `tzset`, so they always agree with `at`, instead of from the
+libc `tzname` and `timezone` globals.
+"]
+fn local_zone_info_at(clock: timespec) -> zone_info {
+    // Sample noon on the first of each month, which finds both halves of
+    // the year in either hemisphere.
+    let year = at_utc(clock).tm_year;
+    let mut standard = none;
+    let mut daylight = none;
+    let mut mon = 0;
+    while mon < 12 {
+        let day = {
+            tm_year: year,
+            tm_mon: mon as c_int,
+            tm_mday: 1 as c_int,
+            tm_hour: 12 as c_int
+            with empty_tm()
+        };
+        let tm = at({ sec: civil_seconds(day), nsec: 0_i32 });
+        if tm.tm_isdst > 0 as c_int {
+            if option::is_none(daylight) { daylight = some(tm); }
+        } else if option::is_none(standard) {
+            standard = some(tm);
+        }
+        mon += 1;
+    }
+
+    // A zone on permanent daylight saving time has no standard half, so
+    // report its daylight time as the standard one.
+    let base = alt standard {
+      some(tm) { tm }
+      none { option::get(daylight) }
+    };
+    if option::is_none(standard) { daylight = none; }
+
+    {
+        std_name: zone_abbrev(base),
+        std_offset: base.tm_gmtoff as i32,
+        dst_name: option::map(daylight) {|tm| zone_abbrev(tm) },
+        dst_offset: option::map(daylight) {|tm| tm.tm_gmtoff as i32 }
+    }
+}
+
+#[doc = "Returns the abbreviations and offsets of the local timezone"]
+fn local_zone_info() -> zone_info {
+    local_zone_info_at(get_time())
+}
+
+#[doc = "
+Returns the canonical abbreviation of a tm's zone, as `%Z` formats it.
+UTC tms are always \"UTC\", whatever the C library calls it. Platforms
+which name zones in full, like \"Pacific Standard Time\" on Windows, have
+their common zones abbreviated from a table, and any other zone is
+written as its offset, like \"+0530\". Returns \"\" for a zone which the
+platform didn't name.
+"]
+fn zone_abbrev(tm: tm) -> str {
+    let name = tm.tm_zone;
+    if tm.tm_gmtoff == 0 as c_long && tm.tm_isdst <= 0 as c_int &&
+       (name == "" || name == "UTC" || name == "Coordinated Universal Time") {
+        ret "UTC";
+    }
+    if !str::contains_char(name, ' ') {
+        ret name;
+    }
+    for vec::each(full_zone_names()) {|entry|
+        let (full, abbrev) = entry;
+        if full == name {
+            ret abbrev;
+        }
+    }
+    let off = tm.tm_gmtoff as int;
+    let sign = if off < 0 { "-" } else { "+" };
+    let off = int::abs(off);
+    #fmt("%s%02d%02d", sign, off / 3600, off % 3600 / 60)
+}
+
+// The abbreviations of the full zone names which Windows reports, for
+// the zones whose abbreviations are in common use.
+fn full_zone_names() -> [(str, str)] {
+    [("Alaskan Standard Time", "AKST"), ("Alaskan Daylight Time", "AKDT"),
+     ("Atlantic Standard Time", "AST"), ("Atlantic Daylight Time", "ADT"),
+     ("AUS Central Standard Time", "ACST"),
+     ("AUS Eastern Standard Time", "AEST"),
+     ("AUS Eastern Daylight Time", "AEDT"),
+     ("Cen. Australia Standard Time", "ACST"),
+     ("Cen. Australia Daylight Time", "ACDT"),
+     ("Central Standard Time", "CST"), ("Central Daylight Time", "CDT"),
+     ("Central Europe Standard Time", "CET"),
+     ("Central Europe Daylight Time", "CEST"),
+     ("Central European Standard Time", "CET"),
+     ("Central European Daylight Time", "CEST"),
+     ("China Standard Time", "CST"),
+     ("E. Europe Standard Time", "EET"), ("E. Europe Daylight Time", "EEST"),
+     ("Eastern Standard Time", "EST"), ("Eastern Daylight Time", "EDT"),
+     ("FLE Standard Time", "EET"), ("FLE Daylight Time", "EEST"),
+     ("GMT Standard Time", "GMT"), ("GMT Daylight Time", "BST"),
+     ("GTB Standard Time", "EET"), ("GTB Daylight Time", "EEST"),
+     ("Hawaiian Standard Time", "HST"), ("India Standard Time", "IST"),
+     ("Israel Standard Time", "IST"), ("Israel Daylight Time", "IDT"),
+     ("Korea Standard Time", "KST"),
+     ("Mountain Standard Time", "MST"), ("Mountain Daylight Time", "MDT"),
+     ("New Zealand Standard Time", "NZST"),
+     ("New Zealand Daylight Time", "NZDT"),
+     ("Newfoundland Standard Time", "NST"),
+     ("Newfoundland Daylight Time", "NDT"),
+     ("Pacific Standard Time", "PST"), ("Pacific Daylight Time", "PDT"),
+     ("Romance Standard Time", "CET"), ("Romance Daylight Time", "CEST"),
+     ("Russian Standard Time", "MSK"), ("Singapore Standard Time", "SGT"),
+     ("South Africa Standard Time", "SAST"), ("Tokyo Standard Time", "JST"),
+     ("W. Australia Standard Time", "AWST"),
+     ("W. Europe Standard Time", "CET"), ("W. Europe Daylight Time", "CEST")]
+}
+
+#[doc = "
+Looks up a common zone abbreviation, such as \"EST\" or \"CEST\", in any
+case, returning its offsets in seconds east of UTC. An ambiguous
+abbreviation, such as \"CST\" or \"IST\", has more than one, the most
+widely used first. Returns an `unknown_zone` error for an abbreviation
+which isn't in the table.
+"]
+fn offset_for_abbrev(abbr: str) -> result<[i32], error> {
+    alt lookup_abbrev(abbr) {
+      some((offsets, _)) { ok(offsets) }
+      none { err(unknown_zone(abbr)) }
+    }
+}
+
+// Looks up a zone abbreviation in any case, returning its offsets as
+// `offset_for_abbrev` does and whether it names daylight saving time.
+fn lookup_abbrev(abbr: str) -> option<([i32], bool)> {
+    let upper = str::to_upper(abbr);
+    for vec::each(abbrev_offsets()) {|entry|
+        let (name, offsets, dst) = entry;
+        if name == upper {
+            ret some((vec::map(offsets) {|off| off as i32 }, dst));
+        }
+    }
+    none
+}
+
+// The offsets of common zone abbreviations, in seconds east of UTC, with
+// the most widely used first for those which are ambiguous, and whether
+// the abbreviation names daylight saving time. "BST" is British Summer
+// Time in its first meaning but Bangladesh Standard Time in its second.
+fn abbrev_offsets() -> [(str, [int], bool)] {
+    [("ACDT", [37800], true), ("ACST", [34200], false),
+     ("ADT", [-10800], true), ("AEDT", [39600], true),
+     ("AEST", [36000], false), ("AKDT", [-28800], true),
+     ("AKST", [-32400], false), ("ART", [-10800], false),
+     ("AST", [-14400, 10800], false), ("AWST", [28800], false),
+     ("BRT", [-10800], false), ("BST", [3600, 21600], true),
+     ("CAT", [7200], false), ("CDT", [-18000, -14400], true),
+     ("CEST", [7200], true), ("CET", [3600], false),
+     ("CST", [-21600, 28800, -18000], false), ("EAT", [10800], false),
+     ("EDT", [-14400], true), ("EEST", [10800], true), ("EET", [7200], false),
+     ("EST", [-18000], false), ("GMT", [0], false), ("HKT", [28800], false),
+     ("HST", [-36000], false), ("ICT", [25200], false), ("IDT", [10800], true),
+     ("IST", [19800, 3600, 7200], false), ("JST", [32400], false),
+     ("KST", [32400], false), ("MDT", [-21600], true), ("MSK", [10800], false),
+     ("MST", [-25200], false), ("NDT", [-9000], true),
+     ("NST", [-12600], false), ("NZDT", [46800], true),
+     ("NZST", [43200], false), ("PDT", [-25200], true),
+     ("PHT", [28800], false), ("PKT", [18000], false),
+     ("PST", [-28800, 28800], false), ("SAST", [7200], false),
+     ("SGT", [28800], false), ("UT", [0], false), ("UTC", [0], false),
+     ("WAT", [3600], false), ("WEST", [3600], true), ("WET", [0], false),
+     ("WIB", [25200], false), ("Z", [0], false)]
+}
+
+#[doc = "Returns the current time in UTC"]
+fn now_utc() -> tm {
+    at_utc(get_time())
+}
+
+#[doc = "Returns the specified time in the local timezone"]
+fn at(clock: timespec) -> tm {
+    // If the platform can't convert this time, fall back to UTC rather
+    // than returning garbage.
+    alt sys::localtime(clock) {
+      some(tm) { tm }
+      none { at_utc(clock) }
+    }
+}
+
+#[doc = "Returns the current time in the local timezone"]
+fn now() -> tm {
+    at(get_time())
+}
+
+#[doc = "Returns the specified time in the given zone"]
+fn at_zone(clock: timespec, z: zone) -> tm {
+    alt z {
+      zone_utc { at_utc(clock) }
+      zone_local { at(clock) }
+      zone_offset(off) { at_gmtoff(clock, off as c_long) }
+    }
+}
+
+#[doc = "
+Creates an empty conversion cache for the zone. Times in the local zone
+are not cached, since its offset can change during the day.
+"]
+fn conversion_cache(z: zone) -> conversion_cache {
+    @{zone: z, mut last: none}
+}
+
+impl conversion_cache for conversion_cache {
+    #[doc = "Returns the specified time in the cache's zone, see `at_zone`."]
+    fn at(clock: timespec) -> tm {
+        let gmtoff = alt self.zone {
+          zone_utc { 0_i32 }
+          zone_offset(off) { off }
+          zone_local { ret at(clock); }
+        };
+
+        let secs = clock.sec + (gmtoff as i64);
+        let day = div_floor(secs, 86400_i64);
+        let sod = (secs - day * 86400_i64) as int;
+        alt self.last {
+          some((last, tm)) if last == day {
+            ret {
+                tm_sec: (sod % 60) as c_int,
+                tm_min: (sod % 3600 / 60) as c_int,
+                tm_hour: (sod / 3600) as c_int,
+                tm_nsec: clock.nsec
+                with tm
+            };
+          }
+          _ { }
+        }
+
+        let tm = at_zone(clock, self.zone);
+        self.last = some((day, tm));
+        tm
+    }
+}
+
+#[doc = "
+Returns the next instant after now at which the wall clock in the zone
+reads the given time. See `next_occurrence_after`.
+"]
+fn next_occurrence_of(hour: int, min: int, sec: int, z: zone)
+    -> result<timespec, error> {
+    next_occurrence_after(get_time(), hour, min, sec, z)
+}
+
+#[doc = "
+Returns the first instant after `clock` at which the wall clock in the
+zone reads the given time, or an error if a field is out of range.
+
+A day on which the clocks skip over the time, such as 02:30 when daylight
+saving time starts, is skipped. A time which the clocks pass twice, when
+daylight saving time ends, occurs only the first time, so an alarm set
+between the two fires on the next day rather than twice.
+"]
+fn next_occurrence_after(clock: timespec, hour: int, min: int, sec: int,
+                         z: zone) -> result<timespec, error> {
+    time_of_day_from_hms(hour, min, sec).chain { |tod|
+        let mut day = at_zone(clock, z).date().days();
+        let secs = tod.seconds_from_midnight() as i64;
+        let mut next = none;
+        while option::is_none(next) {
+            alt first_instant_at(day * 86400_i64 + secs, z) {
+              some(sec) if sec > clock.sec { next = some(sec); }
+              _ { day += 1_i64; }
+            }
+        }
+        ok({ sec: option::get(next), nsec: 0_i32 })
+    }
+}
+
+// The first instant at which the wall clock in the zone reads `wall`,
+// given as seconds since the epoch as if the zone were UTC, or none if the
+// clocks skip over it. This assumes the offset changes at most once in the
+// two days around it.
+fn first_instant_at(wall: i64, z: zone) -> option<i64> {
+    let offset_at = fn@(sec: i64) -> i64 {
+        at_zone({ sec: sec, nsec: 0_i32 }, z).tm_gmtoff as i64
+    };
+    let before = wall - offset_at(wall - 86400_i64);
+    let after = wall - offset_at(wall + 86400_i64);
+    let (first, second) = if before <= after {
+        (before, after)
+    } else {
+        (after, before)
+    };
+    if wall - offset_at(first) == first {
+        some(first)
+    } else if wall - offset_at(second) == second {
+        some(second)
+    } else {
+        none
+    }
+}
+
+// The instant of the first moment of a date in the zone. When a DST
+// transition skips midnight in the local zone, libc picks the moment.
+fn midnight_in_zone(d: date, z: zone) -> timespec {
+    let days = d.days();
+    alt z {
+      zone_utc { { sec: days * 86400_i64, nsec: 0_i32 } }
+      zone_offset(off) {
+        { sec: days * 86400_i64 - (off as i64), nsec: 0_i32 }
+      }
+      zone_local {
+        let civil = {
+            tm_mday: d.day as c_int,
+            tm_mon: d.month.to_tm_mon(),
+            tm_year: (d.year - 1900) as c_int,
+            tm_isdst: -1 as c_int
+            with empty_tm()
+        };
+        let sec = alt sys::mktime(civil) {
+          some(sec) { sec }
+          none {
+            let utc = days * 86400_i64;
+            utc - (at({ sec: utc, nsec: 0_i32 }).tm_gmtoff as i64)
+          }
+        };
+        { sec: sec, nsec: 0_i32 }
+      }
+    }
+}
+
+// Returns the specified time with its civil fields shifted into the
+// given offset from UTC. The weekday and day of the year are filled in,
+// but the zone abbreviation is unknown unless the offset is zero.
+fn at_gmtoff(clock: timespec, gmtoff: c_long) -> tm {
+    let tm = at_utc({ sec: clock.sec + (gmtoff as i64) with clock });
+    if gmtoff == 0 as c_long {
+        tm
+    } else {
+        { tm_gmtoff: gmtoff, tm_zone: "" with tm }
+    }
+}